@@ -0,0 +1,124 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Runtime configuration of arke-admin
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use arke_common::config::{load_file, validate_endpoint, ConfigError};
+use arke_common::messages::AccountStatus;
+use arke_common::tls::{Pin, Pins};
+
+const DEFAULT_SERVER: &str = "127.0.0.1:9080";
+const DEFAULT_LIMIT: u32 = 100;
+
+/// Command line flags, each of them can also be set through its environment variable
+#[derive(Parser, Debug)]
+#[command(about = "Administer an Arke server through its admin endpoint")]
+struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ARKE_ADMIN_CONFIG")]
+    config: Option<PathBuf>,
+    /// Admin endpoint of the server
+    #[arg(long, env = "ARKE_ADMIN_SERVER")]
+    server: Option<String>,
+    /// File with the admin key of the server, admin.key in its data directory
+    #[arg(long, env = "ARKE_ADMIN_KEY")]
+    key: Option<PathBuf>,
+    /// Pin of the server, cert-sha256:<hex> or spki-sha256:<hex>, the same one clients use
+    #[arg(long, env = "ARKE_ADMIN_PIN")]
+    pin: Option<String>,
+    /// Connect over plain TCP to a server started without TLS, instead of refusing it for having no pin
+    #[arg(long, env = "ARKE_ADMIN_INSECURE_PLAINTEXT")]
+    insecure_plaintext: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Show the uptime, request totals and state of the server
+    Health,
+    /// Show how many requests of each action the server answered and rejected
+    Counts,
+    /// List the accounts of the database server
+    Users {
+        /// Only IDs starting with this
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Only accounts with this status: active, suspended or deleted
+        #[arg(long, value_parser = parse_status)]
+        status: Option<AccountStatus>,
+        /// List at most this many accounts
+        #[arg(long, default_value_t = DEFAULT_LIMIT)]
+        limit: u32,
+    },
+    /// Suspend an ID on the database server, it stays taken and cannot be used
+    Suspend {
+        id_string: String,
+    },
+    /// Make the server read its keys, parameters or user directory from disk again
+    Reload,
+    /// Make the registration authority attest with a new registrar key, attestations of the previous one keep verifying
+    RotateRegistrarKey,
+    /// Make the key-issuing authority refuse attestations of a registrar key, e.g. one that leaked.
+    /// Retiring the key in use rotates to a new one first
+    RetireRegistrarKey {
+        key_id: u32,
+    },
+}
+
+fn parse_status(value: &str) -> Result<AccountStatus, String> {
+    AccountStatus::parse(value).ok_or_else(|| "expected active, suspended or deleted".to_string())
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: Option<String>,
+    key: Option<PathBuf>,
+    pin: Option<String>,
+    insecure_plaintext: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub server: String,
+    pub key: PathBuf,
+    /// Holds the pin of the server if it is reached over TLS
+    pub pins: Pins,
+    pub command: Command,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file: FileConfig = load_file(cli.config.as_deref())?;
+
+        let server = cli.server.or(file.server).unwrap_or_else(|| DEFAULT_SERVER.to_string());
+        let server = validate_endpoint("server", &server)?;
+        let key = cli.key.or(file.key)
+            .ok_or_else(|| ConfigError::invalid("key", "", "give the admin key file of the server with --key"))?;
+        let mut pins = BTreeMap::new();
+        if let Some(pin) = cli.pin.or(file.pin) {
+            let parsed = Pin::parse(&pin)
+                .ok_or_else(|| ConfigError::invalid("pin", pin.clone(), "expected cert-sha256:<hex> or spki-sha256:<hex>"))?;
+            pins.insert(server.clone(), parsed);
+        }
+        let mut pins = Pins::new(pins);
+        if cli.insecure_plaintext || file.insecure_plaintext.unwrap_or(false) {
+            pins = pins.allow_plaintext();
+        }
+
+        Ok(Config {
+            server,
+            key,
+            pins,
+            command: cli.command,
+        })
+    }
+}
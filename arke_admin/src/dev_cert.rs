@@ -0,0 +1,64 @@
+// ---------------------------------------
+// File: dev_cert.rs
+// Date: 18 Oct 2026
+// Description: Generate a self-signed TLS certificate for local testing, and the pins clients need for it
+// ---------------------------------------
+
+// Clients authenticate the servers by pin rather than through a certificate authority (see
+// arke_common/src/tls.rs), so a self-signed certificate is as good as any for testing. Production
+// servers can use a certificate from anywhere, the pins are printed the same way.
+
+use std::path::PathBuf;
+use clap::Parser;
+use rcgen::{CertificateParams, DnType, KeyPair};
+use arke_common::durable_file::{write_atomic, write_atomic_private};
+use arke_common::tls::Pin;
+
+/// Command line flags
+#[derive(Parser, Debug)]
+#[command(about = "Generate a self-signed TLS certificate for an Arke server, for local testing only")]
+struct Cli {
+    /// Name of the server, used for the common name and the file names <name>.crt and <name>.key
+    #[arg(long, default_value = "arke-dev")]
+    name: String,
+    /// Host name or IP address clients connect to, repeat the flag for every one
+    #[arg(long = "host", default_values_t = ["127.0.0.1".to_string(), "localhost".to_string()])]
+    hosts: Vec<String>,
+    /// Directory to write the certificate and the key to
+    #[arg(long, default_value = ".")]
+    out: PathBuf,
+    /// Replace a certificate and key that already exist, which changes the pins
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(&cli) {
+        eprintln!("X {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let cert_path = cli.out.join(format!("{}.crt", cli.name));
+    let key_path = cli.out.join(format!("{}.key", cli.name));
+    if !cli.force && (cert_path.exists() || key_path.exists()) {
+        return Err(format!("{} or {} exists already, pass --force to replace them", cert_path.display(), key_path.display()).into());
+    }
+
+    let key_pair = KeyPair::generate()?;
+    let mut params = CertificateParams::new(cli.hosts.clone())?;
+    params.distinguished_name.push(DnType::CommonName, cli.name.as_str());
+    let cert = params.self_signed(&key_pair)?;
+
+    std::fs::create_dir_all(&cli.out)?;
+    write_atomic_private(&key_path, key_pair.serialize_pem().as_bytes())?;
+    write_atomic(&cert_path, cert.pem().as_bytes())?;
+    println!("✓ Wrote {} and {}", cert_path.display(), key_path.display());
+    println!("Start the server with --tls-cert {} --tls-key {}", cert_path.display(), key_path.display());
+    println!("Pin it in the client with one of");
+    println!("  public key:  {}", Pin::of_public_key(&key_pair.public_key_der()));
+    println!("  certificate: {}", Pin::of_certificate(cert.der()));
+    Ok(())
+}
@@ -0,0 +1,114 @@
+// ---------------------------------------
+// File: main.rs
+// Date: 18 Oct 2026
+// Description: Command line tool for the admin endpoint of the servers
+// ---------------------------------------
+
+use tokio::runtime::Runtime;
+use arke_common::admin::AdminKey;
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::messages::{Blob, Request, Response};
+mod config;
+use config::{Command, Config};
+
+fn main() {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let rt = Runtime::new().unwrap();
+    if let Err(e) = rt.block_on(run(&config)) {
+        eprintln!("X {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let key = AdminKey::load(&config.key)?;
+    let mut connection = Connection::connect_pinned(&config.server, &config.pins).await?;
+
+    // Log in by answering the challenge of the server with the admin key
+    let nonce = match connection.call(Request::AdminChallenge).await? {
+        Response::AdminChallenge { nonce } => nonce,
+        other => return Err(Box::new(ProtocolError::unexpected("admin_challenge", other))),
+    };
+    match connection.call(Request::AdminLogin { proof: Blob(key.prove(&nonce.0)) }).await? {
+        Response::AdminLoggedIn => {},
+        other => return Err(Box::new(ProtocolError::unexpected("admin_login", other))),
+    }
+
+    match &config.command {
+        Command::Health => match connection.call(Request::AdminHealth).await? {
+            Response::AdminHealth { health } => {
+                println!("server:               {}", health.server);
+                println!("uptime:               {} s", health.uptime_secs);
+                println!("protocol version:     {}", health.protocol_version);
+                println!("requests:             {} ({} rejected)", health.requests, health.rejected);
+                for (name, value) in &health.details {
+                    println!("{:<21} {}", format!("{}:", name), value);
+                }
+            },
+            other => return Err(Box::new(ProtocolError::unexpected("admin_health", other))),
+        },
+
+        Command::Counts => match connection.call(Request::AdminRequestCounts).await? {
+            Response::AdminRequestCounts { counts } => {
+                println!("{:<40} {:>10} {:>10}", "action", "ok", "rejected");
+                for count in counts {
+                    println!("{:<40} {:>10} {:>10}", count.action, count.ok, count.rejected);
+                }
+            },
+            other => return Err(Box::new(ProtocolError::unexpected("admin_request_counts", other))),
+        },
+
+        Command::Users { prefix, status, limit } => {
+            let request = Request::AdminListUsers { prefix: prefix.clone(), status: *status, limit: *limit };
+            match connection.call(request).await? {
+                Response::AdminUsers { users, truncated } => {
+                    println!("{:<20} {:<10} status changed at", "id", "status");
+                    for user in &users {
+                        println!("{:<20} {:<10} {}", user.id_string, user.status.as_str(), user.status_changed_at);
+                    }
+                    if truncated {
+                        println!("- More accounts match, showing the first {}. Raise --limit or narrow down with --prefix", users.len());
+                    }
+                },
+                other => return Err(Box::new(ProtocolError::unexpected("admin_list_users", other))),
+            }
+        },
+
+        Command::Suspend { id_string } => {
+            match connection.call(Request::AdminSuspendUser { id_string: id_string.clone() }).await? {
+                Response::AdminUserSuspended { changed: true } => println!("✓ Suspended {}", id_string),
+                Response::AdminUserSuspended { changed: false } => println!("✓ {} was already suspended", id_string),
+                other => return Err(Box::new(ProtocolError::unexpected("admin_suspend_user", other))),
+            }
+        },
+
+        Command::Reload => match connection.call(Request::AdminReload).await? {
+            Response::AdminReloaded { detail } => println!("✓ {}", detail),
+            other => return Err(Box::new(ProtocolError::unexpected("admin_reload", other))),
+        },
+
+        Command::RotateRegistrarKey => match connection.call(Request::AdminRotateRegistrarKey).await? {
+            Response::AdminRegistrarKeyRotated { key_id } => println!("✓ Attestations are now made with registrar key {}", key_id),
+            other => return Err(Box::new(ProtocolError::unexpected("admin_rotate_registrar_key", other))),
+        },
+
+        Command::RetireRegistrarKey { key_id } => {
+            match connection.call(Request::AdminRetireRegistrarKey { key_id: *key_id }).await? {
+                Response::AdminRegistrarKeyRetired { current_key_id, changed: true } => {
+                    println!("✓ Retired registrar key {}, attestations are now made with key {}", key_id, current_key_id);
+                },
+                Response::AdminRegistrarKeyRetired { changed: false, .. } => println!("✓ Registrar key {} was already retired", key_id),
+                other => return Err(Box::new(ProtocolError::unexpected("admin_retire_registrar_key", other))),
+            }
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+// ---------------------------------------
+// File: encoding.rs
+// Date: 18 Oct 2026
+// Description: Benchmarks of the JSON and CBOR encodings and of zstd on pp_zk
+// ---------------------------------------
+
+// Run with `cargo bench` in arke_common. The encodings are measured on random bytes of about the
+// size of the real objects, or on a real pp_zk if ARKE_BENCH_PP_ZK names a file holding one, as
+// returned by get_pp_zk. How well zstd does depends on the bytes alone, so it is only measured on
+// a real pp_zk and skipped without one.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::RngCore;
+use arke_common::compression::{compress, decompress};
+use arke_common::frame::{decode, encode, Frame, FrameKind, MAX_FRAME_LEN};
+use arke_common::messages::{Blob, Compression, Encoding, Response, ResponseEnvelope, ENCODINGS, PROTOCOL_VERSION};
+
+// A blind partial user key or a blind attestation
+const SMALL_PAYLOAD_LEN: usize = 512;
+// Stand-in for pp_zk
+const LARGE_PAYLOAD_LEN: usize = 8 << 20;
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn real_pp_zk() -> Option<Vec<u8>> {
+    std::env::var_os("ARKE_BENCH_PP_ZK").map(|path| std::fs::read(&path).expect("could not read ARKE_BENCH_PP_ZK"))
+}
+
+fn pp_zk() -> Vec<u8> {
+    real_pp_zk().unwrap_or_else(|| random_bytes(LARGE_PAYLOAD_LEN))
+}
+
+fn messages() -> Vec<(&'static str, ResponseEnvelope)> {
+    let envelope = |response| ResponseEnvelope { version: PROTOCOL_VERSION, response };
+    vec![
+        ("blind_partial_user_key", envelope(Response::BlindPartialUserKey {
+            issuer_index: 0,
+            blind_partial_user_key: Blob(random_bytes(SMALL_PAYLOAD_LEN)),
+        })),
+        ("pp_zk", envelope(Response::PpZk { pp_zk: Blob(pp_zk()), compression: None })),
+    ]
+}
+
+fn encodings(c: &mut Criterion) {
+    for (name, message) in messages() {
+        let mut group = c.benchmark_group(format!("encoding/{}", name));
+        if name == "pp_zk" {
+            group.sample_size(10);
+        }
+        for encoding in ENCODINGS {
+            let body = encode(encoding, &message).unwrap();
+            println!("{} as {}: {} bytes", name, encoding.as_str(), body.len());
+            group.throughput(Throughput::Bytes(body.len() as u64));
+            group.bench_with_input(BenchmarkId::new("encode", encoding.as_str()), &message, |b, message| {
+                b.iter(|| encode(encoding, message).unwrap())
+            });
+            let frame = Frame { kind: FrameKind::of(encoding), body };
+            group.bench_with_input(BenchmarkId::new("decode", encoding.as_str()), &frame, |b, frame| {
+                b.iter(|| decode::<ResponseEnvelope>(frame).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+fn zstd(c: &mut Criterion) {
+    let pp_zk = match real_pp_zk() {
+        Some(pp_zk) => pp_zk,
+        None => {
+            println!("zstd/pp_zk skipped, set ARKE_BENCH_PP_ZK to a file holding a real pp_zk to measure it");
+            return;
+        }
+    };
+    let compressed = compress(Compression::Zstd, &pp_zk).unwrap();
+    println!("pp_zk: {} bytes, {} with zstd", pp_zk.len(), compressed.len());
+    // What a client receives over CBOR, with and without compression
+    for compression in [None, Some(Compression::Zstd)] {
+        let pp_zk = match compression {
+            Some(compression) => Blob(compress(compression, &pp_zk).unwrap()),
+            None => Blob(pp_zk.clone()),
+        };
+        let message = ResponseEnvelope { version: PROTOCOL_VERSION, response: Response::PpZk { pp_zk, compression } };
+        println!("pp_zk over cbor, compression {:?}: {} bytes", compression, encode(Encoding::Cbor, &message).unwrap().len());
+    }
+
+    let mut group = c.benchmark_group("zstd/pp_zk");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(pp_zk.len() as u64));
+    group.bench_function("compress", |b| b.iter(|| compress(Compression::Zstd, &pp_zk).unwrap()));
+    group.bench_function("decompress", |b| b.iter(|| decompress(Compression::Zstd, &compressed, MAX_FRAME_LEN).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, encodings, zstd);
+criterion_main!(benches);
@@ -0,0 +1,227 @@
+// ---------------------------------------
+// File: admin.rs
+// Date: 18 Oct 2026
+// Description: Authenticated admin endpoint shared by the servers
+// ---------------------------------------
+
+// Every server can listen on a second address for arke-admin. It speaks the same protocol,
+// but a connection has to prove that it holds the admin key before anything else is answered:
+//
+//   admin_challenge                                   -> admin_challenge { nonce }
+//   admin_login { proof = HMAC-SHA256(key, nonce) }   -> admin_logged_in
+//
+// The key itself never goes over the wire, and every nonce is good for one login attempt. A server
+// that serves clients over TLS serves the admin endpoint with the same certificate, so arke-admin
+// pins it like a client would.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::net::TcpListener;
+
+use crate::error::{ErrorCode, ServiceError};
+use crate::messages::{ActionCount, Blob, Request, Response, ServerHealth, PROTOCOL_VERSION};
+use crate::server::serve_connection;
+use crate::tls::{accept, TlsAcceptor};
+
+const ADMIN_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum AdminKeyError {
+    #[error("could not access admin key {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("{path} is not an admin key, expected {} base64 encoded bytes", ADMIN_KEY_LEN)]
+    Malformed { path: PathBuf },
+}
+
+/// Secret shared by a server and the operators allowed to administer it
+pub struct AdminKey([u8; ADMIN_KEY_LEN]);
+
+impl AdminKey {
+    /// Read the key file written by load_or_create
+    pub fn load(path: &Path) -> Result<Self, AdminKeyError> {
+        let encoded = std::fs::read_to_string(path)
+            .map_err(|source| AdminKeyError::Io { path: path.to_path_buf(), source })?;
+        let malformed = || AdminKeyError::Malformed { path: path.to_path_buf() };
+        let bytes = base64::decode(encoded.trim()).map_err(|_| malformed())?;
+        let key: [u8; ADMIN_KEY_LEN] = bytes.as_slice().try_into().map_err(|_| malformed())?;
+        Ok(AdminKey(key))
+    }
+
+    /// Read the key file, or create it with a new random key that only the owner can read
+    pub fn load_or_create(path: &Path) -> Result<Self, AdminKeyError> {
+        if path.exists() {
+            return Self::load(path);
+        }
+        let mut key = [0u8; ADMIN_KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+
+        let io_error = |source| AdminKeyError::Io { path: path.to_path_buf(), source };
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(io_error)?;
+        std::io::Write::write_all(&mut file, format!("{}\n", base64::encode(key)).as_bytes()).map_err(io_error)?;
+        file.sync_all().map_err(io_error)?;
+        Ok(AdminKey(key))
+    }
+
+    fn mac(&self, nonce: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        mac.update(nonce);
+        mac
+    }
+
+    /// Answer to an admin_challenge
+    pub fn prove(&self, nonce: &[u8]) -> Vec<u8> {
+        self.mac(nonce).finalize().into_bytes().to_vec()
+    }
+
+    /// Check an answer in constant time
+    fn verify(&self, nonce: &[u8], proof: &[u8]) -> bool {
+        self.mac(nonce).verify_slice(proof).is_ok()
+    }
+}
+
+/// Requests answered by a server since it started, by action
+pub struct RequestStats {
+    started: Instant,
+    // (answered, rejected) per action
+    counts: Mutex<BTreeMap<&'static str, (u64, u64)>>,
+}
+
+impl Default for RequestStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestStats {
+    pub fn new() -> Self {
+        Self { started: Instant::now(), counts: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn record(&self, action: &'static str, ok: bool) {
+        let mut counts = self.counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (answered, rejected) = counts.entry(action).or_insert((0, 0));
+        if ok {
+            *answered += 1;
+        } else {
+            *rejected += 1;
+        }
+    }
+
+    pub fn counts(&self) -> Vec<ActionCount> {
+        let counts = self.counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        counts.iter()
+            .map(|(action, (ok, rejected))| ActionCount { action: action.to_string(), ok: *ok, rejected: *rejected })
+            .collect()
+    }
+
+    /// Health report of the server, with the facts only the server itself knows in details
+    pub fn health(&self, server: &str, details: BTreeMap<String, String>) -> ServerHealth {
+        let counts = self.counts();
+        ServerHealth {
+            server: server.to_string(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            protocol_version: PROTOCOL_VERSION,
+            requests: counts.iter().map(|count| count.ok + count.rejected).sum(),
+            rejected: counts.iter().map(|count| count.rejected).sum(),
+            details,
+        }
+    }
+}
+
+/// serve_connection, counting every request passed to handler in stats
+pub async fn serve_counted_connection<S, F, Fut>(socket: S, stats: Arc<RequestStats>, mut handler: F)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    F: FnMut(Request) -> Fut,
+    Fut: Future<Output = Result<Response, ServiceError>>,
+{
+    serve_connection(socket, move |request| {
+        let action = request.action();
+        let stats = Arc::clone(&stats);
+        let response = handler(request);
+        async move {
+            let response = response.await;
+            stats.record(action, response.is_ok());
+            response
+        }
+    }).await
+}
+
+type AdminFuture = Pin<Box<dyn Future<Output = Result<Response, ServiceError>> + Send>>;
+
+/// Accept admin connections on bind until the listener fails, over TLS if there is an acceptor.
+/// admin_request_counts is answered here, every other request is passed to handler once the connection has logged in.
+pub async fn serve_admin<F, Fut>(bind: SocketAddr, key: Arc<AdminKey>, stats: Arc<RequestStats>, tls: Option<TlsAcceptor>, handler: F)
+    -> std::io::Result<()>
+where
+    F: Fn(Request) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<Response, ServiceError>> + Send + 'static,
+{
+    let listener = TcpListener::bind(bind).await?;
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        let key = Arc::clone(&key);
+        let stats = Arc::clone(&stats);
+        let handler = handler.clone();
+        let tls = tls.clone();
+
+        tokio::spawn(async move {
+            let socket = match accept(tls.as_ref(), socket).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    eprintln!("X TLS handshake on the admin endpoint failed; err = {}", e);
+                    return;
+                },
+            };
+            // Session of this connection
+            let mut nonce: Option<Vec<u8>> = None;
+            let mut logged_in = false;
+            serve_connection(socket, move |request| -> AdminFuture {
+                match request {
+                    Request::AdminChallenge => {
+                        let mut fresh = vec![0u8; NONCE_LEN];
+                        rand::thread_rng().fill_bytes(&mut fresh);
+                        nonce = Some(fresh.clone());
+                        Box::pin(async move { Ok(Response::AdminChallenge { nonce: Blob(fresh) }) })
+                    },
+                    Request::AdminLogin { proof } => {
+                        // One attempt per challenge
+                        logged_in = nonce.take().is_some_and(|nonce| key.verify(&nonce, &proof.0));
+                        if logged_in {
+                            println!("✓ Admin logged in from {}", peer);
+                            Box::pin(async move { Ok(Response::AdminLoggedIn) })
+                        } else {
+                            eprintln!("X Failed admin login from {}", peer);
+                            Box::pin(async move { Err(ServiceError::new(ErrorCode::Unauthorized, "wrong admin key")) })
+                        }
+                    },
+                    _ if !logged_in => Box::pin(async move {
+                        Err(ServiceError::new(ErrorCode::Unauthorized, "log in with admin_challenge and admin_login first"))
+                    }),
+                    Request::AdminRequestCounts => {
+                        let counts = stats.counts();
+                        Box::pin(async move { Ok(Response::AdminRequestCounts { counts }) })
+                    },
+                    request => Box::pin(handler(request)),
+                }
+            }).await
+        });
+    }
+}
@@ -0,0 +1,145 @@
+// ---------------------------------------
+// File: client.rs
+// Date: 18 Oct 2026
+// Description: Client side of the wire protocol
+// ---------------------------------------
+
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+use crate::error::ErrorCode;
+use crate::frame::{read_message, write_message, FrameError, MAX_RESPONSE_FRAME_LEN};
+use crate::tls::{self, Pins, TlsError, Transport};
+use crate::messages::{Encoding, Request, RequestEnvelope, Response, ResponseEnvelope,
+                      ENCODINGS, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("could not connect to {addr}: {source}")]
+    Connect { addr: String, source: std::io::Error },
+    /// Also when the server is not the one pinned
+    #[error("TLS handshake with {addr} failed: {source}")]
+    Tls { addr: String, source: std::io::Error },
+    #[error(transparent)]
+    Unpinned(TlsError),
+    #[error(transparent)]
+    Frame(#[from] FrameError),
+    #[error("server speaks protocol versions {min_version} to {max_version}, this client speaks {} to {}",
+            MIN_PROTOCOL_VERSION, PROTOCOL_VERSION)]
+    VersionMismatch { min_version: u32, max_version: u32 },
+    #[error("server rejected the request ({code}): {message}")]
+    Server { code: ErrorCode, message: String },
+    #[error("unexpected response to {action}: {response:?}")]
+    UnexpectedResponse { action: &'static str, response: Response },
+}
+
+impl ProtocolError {
+    pub fn unexpected(action: &'static str, response: Response) -> Self {
+        ProtocolError::UnexpectedResponse { action, response }
+    }
+
+    /// Rejection reason given by the server, if the server rejected the request
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            ProtocolError::Server { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+/// A connection to one server on which the protocol version and the encoding have been agreed
+pub struct Connection<S = Transport> {
+    stream: S,
+    version: u32,
+    encoding: Encoding,
+}
+
+impl Connection<Transport> {
+    /// Connect over plain TCP, offering every encoding this build speaks
+    pub async fn connect(addr: &str) -> Result<Self, ProtocolError> {
+        Self::connect_with(addr, &ENCODINGS).await
+    }
+
+    /// Connect over plain TCP, offering only encodings, the preferred one first
+    pub async fn connect_with(addr: &str, encodings: &[Encoding]) -> Result<Self, ProtocolError> {
+        let stream = tcp_connect(addr).await?;
+        Self::handshake_with(Transport::Plain(stream), encodings).await
+    }
+
+    /// Connect over TLS if pins has a pin for addr, over plain TCP if pins allows it, and fail otherwise (see tls.rs)
+    pub async fn connect_pinned(addr: &str, pins: &Pins) -> Result<Self, ProtocolError> {
+        // Checked before connecting, an unpinned server is never sent anything
+        let pin = pins.get(addr).map_err(ProtocolError::Unpinned)?;
+        let stream = tcp_connect(addr).await?;
+        let stream = match pin {
+            Some(pin) => tls::connect(addr, pin, stream)
+                .await
+                .map_err(|source| ProtocolError::Tls { addr: addr.to_string(), source })?,
+            None => Transport::Plain(stream),
+        };
+        Self::handshake_with(stream, &ENCODINGS).await
+    }
+}
+
+async fn tcp_connect(addr: &str) -> Result<TcpStream, ProtocolError> {
+    TcpStream::connect(addr)
+        .await
+        .map_err(|source| ProtocolError::Connect { addr: addr.to_string(), source })
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    /// Send the hello on an open stream and wait for the server to pick a version and an encoding
+    pub async fn handshake(stream: S) -> Result<Self, ProtocolError> {
+        Self::handshake_with(stream, &ENCODINGS).await
+    }
+
+    /// handshake, offering only encodings
+    pub async fn handshake_with(stream: S, encodings: &[Encoding]) -> Result<Self, ProtocolError> {
+        // The hello always goes out as JSON, which every server reads
+        let mut connection = Connection { stream, version: PROTOCOL_VERSION, encoding: Encoding::Json };
+        let hello = Request::Hello {
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            encodings: encodings.iter().map(|encoding| encoding.as_str().to_string()).collect(),
+        };
+        match connection.call(hello).await? {
+            Response::Welcome { negotiated_version, encoding } => {
+                connection.version = negotiated_version;
+                connection.encoding = encoding;
+                Ok(connection)
+            }
+            other => Err(ProtocolError::unexpected("hello", other)),
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Send one request and wait for its response.
+    /// Error and version mismatch responses are returned as errors.
+    pub async fn call(&mut self, request: Request) -> Result<Response, ProtocolError> {
+        self.call_with_limit(request, MAX_RESPONSE_FRAME_LEN).await
+    }
+
+    /// call, accepting a response of up to max_len bytes, e.g. get_pp_zk with frame::MAX_FRAME_LEN
+    pub async fn call_with_limit(&mut self, request: Request, max_len: usize) -> Result<Response, ProtocolError> {
+        let envelope = RequestEnvelope { version: self.version, request };
+        write_message(&mut self.stream, self.encoding, &envelope).await?;
+        let envelope: ResponseEnvelope = read_message(&mut self.stream, max_len)
+            .await?
+            .ok_or(FrameError::Closed)?;
+        match envelope.response {
+            Response::Error { code, message } => Err(ProtocolError::Server { code, message }),
+            Response::VersionMismatch { min_version, max_version } => {
+                Err(ProtocolError::VersionMismatch { min_version, max_version })
+            }
+            response => Ok(response),
+        }
+    }
+}
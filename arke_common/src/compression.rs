@@ -0,0 +1,32 @@
+// ---------------------------------------
+// File: compression.rs
+// Date: 18 Oct 2026
+// Description: Optional compression of large payloads such as pp_zk
+// ---------------------------------------
+
+use std::io::Read;
+use crate::messages::Compression;
+
+/// The default level of zstd
+const ZSTD_LEVEL: i32 = 3;
+
+pub fn compress(compression: Compression, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    match compression {
+        Compression::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL),
+    }
+}
+
+/// Decompress bytes, refusing to produce more than max_len bytes
+pub fn decompress(compression: Compression, bytes: &[u8], max_len: usize) -> std::io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    match compression {
+        Compression::Zstd => {
+            zstd::Decoder::new(bytes)?.take(max_len as u64 + 1).read_to_end(&mut decompressed)?;
+        },
+    }
+    if decompressed.len() > max_len {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+            format!("decompresses to more than {} bytes", max_len)));
+    }
+    Ok(decompressed)
+}
@@ -0,0 +1,94 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Helpers for loading and validating the runtime configuration of every binary
+// ---------------------------------------
+
+// Each binary merges its settings in this order, later sources win:
+// built-in defaults, the TOML config file, environment variables, command line flags.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("invalid config file {path}: {source}")]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("invalid {field} {value:?}: {reason}")]
+    Invalid { field: &'static str, value: String, reason: String },
+}
+
+impl ConfigError {
+    pub fn invalid(field: &'static str, value: impl Into<String>, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid { field, value: value.into(), reason: reason.into() }
+    }
+}
+
+/// Read a TOML config file, or fall back to the defaults if no file was given
+pub fn load_file<T: DeserializeOwned + Default>(path: Option<&Path>) -> Result<T, ConfigError> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(T::default()),
+    };
+    let contents = std::fs::read_to_string(path)
+        .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+    toml::from_str(&contents).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+}
+
+/// Parse an address to listen on, e.g. 127.0.0.1:8080
+pub fn parse_socket_addr(field: &'static str, value: &str) -> Result<SocketAddr, ConfigError> {
+    value.parse().map_err(|e: std::net::AddrParseError| ConfigError::invalid(field, value, e.to_string()))
+}
+
+/// Check that an address to connect to has the form host:port
+pub fn validate_endpoint(field: &'static str, value: &str) -> Result<String, ConfigError> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .ok_or_else(|| ConfigError::invalid(field, value, "expected host:port"))?;
+    if host.is_empty() {
+        return Err(ConfigError::invalid(field, value, "missing host"));
+    }
+    port.parse::<u16>()
+        .map_err(|e| ConfigError::invalid(field, value, format!("bad port: {}", e)))?;
+    Ok(value.to_string())
+}
+
+/// Certificate chain and private key, both PEM, a server accepts TLS connections with
+#[derive(Clone, Debug)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// The TLS files of a server, which are given together or not at all
+pub fn tls_files(cert: Option<PathBuf>, key: Option<PathBuf>) -> Result<Option<TlsFiles>, ConfigError> {
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(Some(TlsFiles { cert, key })),
+        (None, None) => Ok(None),
+        (Some(cert), None) => Err(ConfigError::invalid("tls_cert", cert.display().to_string(), "tls_key is missing")),
+        (None, Some(key)) => Err(ConfigError::invalid("tls_key", key.display().to_string(), "tls_cert is missing")),
+    }
+}
+
+/// Create the data directory if needed and check that it is a directory
+pub fn prepare_data_dir(field: &'static str, path: PathBuf) -> Result<PathBuf, ConfigError> {
+    let value = path.display().to_string();
+    std::fs::create_dir_all(&path).map_err(|e| ConfigError::invalid(field, value.clone(), e.to_string()))?;
+    if !path.is_dir() {
+        return Err(ConfigError::invalid(field, value, "not a directory"));
+    }
+    Ok(path)
+}
+
+/// Refuse a data directory holding any of the secret files, for a network process whose signer holds the keys
+pub fn refuse_secrets(field: &'static str, data_dir: &Path, secrets: &[PathBuf]) -> Result<(), ConfigError> {
+    match secrets.iter().find(|secret| secret.exists()) {
+        Some(secret) => Err(ConfigError::invalid(field, data_dir.display().to_string(), format!(
+            "holds {} of the signer, start the network process on a data directory of its own", secret.display()))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,184 @@
+// ---------------------------------------
+// File: download.rs
+// Date: 18 Oct 2026
+// Description: Resumable download of pp_zk in chunks
+// ---------------------------------------
+
+// get_pp_zk sends all of pp_zk in one response, which on a slow link may not arrive before the
+// connection breaks, and then has to start over. get_pp_zk_chunk sends it in pieces instead. Every
+// chunk comes with its own digest and with the digest of the whole of pp_zk, and is written to the
+// partial download in the parameter cache as soon as it is checked. After a broken connection the
+// client connects again and asks for the chunk following the last one it has; a client that gives
+// up resumes in its next run. The digest of the whole is checked once the last chunk arrived.
+
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::{sleep, timeout};
+
+use crate::client::{Connection, ProtocolError};
+use crate::compression::decompress;
+use crate::durable_file::DurableFileError;
+use crate::error::ServiceError;
+use crate::frame::MAX_FRAME_LEN;
+use crate::messages::{Blob, Compression, Request, Response};
+use crate::params_cache::{digest, ParamsCache};
+use crate::tls::Pins;
+
+/// Length of the chunks clients ask for
+pub const CHUNK_LEN: u32 = 1 << 20;
+/// Longest chunk a server sends, longer requests get a shorter chunk
+pub const MAX_CHUNK_LEN: u32 = 1 << 24;
+// Wait between two connection attempts
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Error)]
+pub enum DownloadError {
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error(transparent)]
+    Cache(#[from] DurableFileError),
+    #[error("pp_zk changed during the download, its digest is now {0}")]
+    Changed(String),
+    #[error("the downloaded pp_zk does not match its digest, the download starts over next time")]
+    Corrupt,
+    #[error("gave up after {attempts} attempts without progress, the download resumes next time: {reason}")]
+    Stalled { attempts: u32, reason: String },
+}
+
+/// Server side of get_pp_zk_chunk, for served, the encoding of pp_zk compressed or not, whose uncompressed digest is digest
+pub fn serve_chunk(served: &[u8], digest: &str, compression: Option<Compression>, offset: u64, length: u32)
+                   -> Result<Response, ServiceError> {
+    let total_len = served.len() as u64;
+    if offset > total_len {
+        return Err(ServiceError::malformed_field("offset", format!("past the end of the {} bytes of pp_zk", total_len)));
+    }
+    if length == 0 {
+        return Err(ServiceError::malformed_field("length", "must be at least 1"));
+    }
+    let end = total_len.min(offset + length.min(MAX_CHUNK_LEN) as u64);
+    let chunk = served[offset as usize..end as usize].to_vec();
+    Ok(Response::PpZkChunk {
+        offset,
+        total_len,
+        digest: digest.to_string(),
+        chunk_digest: self::digest(&chunk),
+        chunk: Blob(chunk),
+        compression,
+    })
+}
+
+/// How a download is paced
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadOptions {
+    pub chunk_len: u32,
+    /// Longest wait for a connection or a chunk before connecting again
+    pub call_timeout: Duration,
+    /// Connection attempts in a row that may fail to bring a new chunk before giving up
+    pub retries: u32,
+}
+
+// Why the current connection is given up on
+enum Interrupted {
+    Retry(String),
+    Fail(DownloadError),
+}
+
+/// Download pp_zk from endpoint in chunks, resuming the partial download saved as name in cache if there is one.
+/// digest is the one published by get_pp_zk_digest. The uncompressed pp_zk is returned once it matches it.
+pub async fn download_pp_zk(endpoint: &str, pins: &Pins, cache: &ParamsCache, name: &str, digest: &str,
+                            compression: Option<Compression>, options: DownloadOptions) -> Result<Vec<u8>, DownloadError> {
+    let mut downloaded = cache.load_partial(name, digest, compression)?;
+    if !downloaded.is_empty() {
+        println!("- Resuming the download of pp_zk after {} bytes", downloaded.len());
+    }
+    let mut failed_attempts = 0;
+    loop {
+        let reason = match download_on(endpoint, pins, cache, name, digest, compression, options, &mut downloaded).await {
+            Ok(served) => return check(cache, name, digest, compression, served),
+            Err((Interrupted::Fail(e), _)) => return Err(e),
+            Err((Interrupted::Retry(reason), progressed)) => {
+                if progressed {
+                    failed_attempts = 0;
+                }
+                reason
+            },
+        };
+        failed_attempts += 1;
+        if failed_attempts > options.retries {
+            return Err(DownloadError::Stalled { attempts: failed_attempts, reason });
+        }
+        println!("X Download of pp_zk interrupted after {} bytes ({}), connecting again", downloaded.len(), reason);
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// Fetch chunks on one connection until all of pp_zk is downloaded or the connection fails.
+// On failure also tells whether any chunk arrived.
+#[allow(clippy::too_many_arguments)]
+async fn download_on(endpoint: &str, pins: &Pins, cache: &ParamsCache, name: &str, digest: &str, compression: Option<Compression>,
+                     options: DownloadOptions, downloaded: &mut Vec<u8>) -> Result<Vec<u8>, (Interrupted, bool)> {
+    let mut progressed = false;
+    let mut connection = match timeout(options.call_timeout, Connection::connect_pinned(endpoint, pins)).await {
+        Ok(Ok(connection)) => connection,
+        Ok(Err(e)) => return Err((interrupted(e), progressed)),
+        Err(_) => return Err((Interrupted::Retry(format!("no connection within {} s", options.call_timeout.as_secs())), progressed)),
+    };
+    loop {
+        let offset = downloaded.len() as u64;
+        let request = Request::GetPpZkChunk { offset, length: options.chunk_len, compression };
+        let response = match timeout(options.call_timeout, connection.call(request)).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return Err((interrupted(e), progressed)),
+            Err(_) => return Err((Interrupted::Retry(format!("no chunk within {} s", options.call_timeout.as_secs())), progressed)),
+        };
+        let (total_len, chunk) = match response {
+            Response::PpZkChunk { offset: chunk_offset, total_len, digest: published, chunk, chunk_digest, compression: served }
+                if chunk_offset == offset && served == compression
+                    && total_len <= MAX_FRAME_LEN as u64 && offset + chunk.0.len() as u64 <= total_len => {
+                if published != digest {
+                    cache.remove_partial(name);
+                    return Err((Interrupted::Fail(DownloadError::Changed(published)), progressed));
+                }
+                // A damaged chunk is asked for again rather than saved
+                if self::digest(&chunk.0) != chunk_digest {
+                    return Err((Interrupted::Retry(format!("the chunk at {} does not match its digest", offset)), progressed));
+                }
+                (total_len, chunk.0)
+            },
+            other => return Err((Interrupted::Fail(ProtocolError::unexpected("get_pp_zk_chunk", other).into()), progressed)),
+        };
+        if chunk.is_empty() && offset < total_len {
+            return Err((Interrupted::Retry(format!("empty chunk at {}", offset)), progressed));
+        }
+        cache.append_partial(name, digest, compression, offset, &chunk)
+            .map_err(|e| (Interrupted::Fail(e.into()), progressed))?;
+        downloaded.extend_from_slice(&chunk);
+        progressed = true;
+        if downloaded.len() as u64 == total_len {
+            return Ok(std::mem::take(downloaded));
+        }
+    }
+}
+
+// Failures to reach the endpoint are retried, error responses would just come back again
+fn interrupted(e: ProtocolError) -> Interrupted {
+    match e {
+        ProtocolError::Connect { .. } | ProtocolError::Frame(_) => Interrupted::Retry(e.to_string()),
+        e => Interrupted::Fail(e.into()),
+    }
+}
+
+// Decompress the whole download and check it against the published digest.
+// The partial download is no longer needed either way.
+fn check(cache: &ParamsCache, name: &str, digest: &str, compression: Option<Compression>, served: Vec<u8>)
+         -> Result<Vec<u8>, DownloadError> {
+    cache.remove_partial(name);
+    let pp_zk = match compression {
+        None => served,
+        Some(compression) => decompress(compression, &served, MAX_FRAME_LEN).map_err(|_| DownloadError::Corrupt)?,
+    };
+    if self::digest(&pp_zk) != digest {
+        return Err(DownloadError::Corrupt);
+    }
+    Ok(pp_zk)
+}
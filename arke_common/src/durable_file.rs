@@ -0,0 +1,173 @@
+// ---------------------------------------
+// File: durable_file.rs
+// Date: 18 Oct 2026
+// Description: Crash-safe writes and checked reads of the files holding persistent state
+// ---------------------------------------
+
+// A file is never overwritten in place. write_atomic writes the new contents to a temporary
+// file next to it, syncs it, and renames it over the old file, so a crash at any point leaves
+// either the old or the new contents. Before the rename the old file is kept as <name>.bak,
+// the last good copy to restore from if the file is ever damaged some other way.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DurableFileError {
+    #[error("could not access {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+    #[error("{path} is corrupt ({reason}), {hint}")]
+    Corrupt { path: String, reason: String, hint: String },
+}
+
+/// Where write_atomic keeps the previous contents of path
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// The error for a file that exists but cannot be read back, pointing at its backup if there is one
+pub fn corrupt(path: &Path, reason: impl std::fmt::Display) -> DurableFileError {
+    let backup = backup_path(path);
+    let hint = if backup.exists() {
+        format!("the last good copy is {}, copy it over the file to restore it", backup.display())
+    } else {
+        "there is no backup of it".to_string()
+    };
+    DurableFileError::Corrupt { path: path.display().to_string(), reason: reason.to_string(), hint }
+}
+
+/// Replace the contents of path, keeping the old contents in the backup
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), DurableFileError> {
+    write(path, contents, false)
+}
+
+/// write_atomic for files holding secret keys, which only the owner can read
+pub fn write_atomic_private(path: &Path, contents: &[u8]) -> Result<(), DurableFileError> {
+    write(path, contents, true)
+}
+
+fn write(path: &Path, contents: &[u8], private: bool) -> Result<(), DurableFileError> {
+    let io_error = |path: &Path| {
+        let path = path.display().to_string();
+        move |source| DurableFileError::Io { path, source }
+    };
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    // Two processes writing the same file must not share a temporary file
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    let written = options.open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+    if let Err(source) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(io_error(&temp_path)(source));
+    }
+
+    if path.exists() {
+        // A second name for the old contents, which the rename below leaves in place
+        let backup = backup_path(path);
+        let _ = std::fs::remove_file(&backup);
+        if std::fs::hard_link(path, &backup).is_err() {
+            std::fs::copy(path, &backup).map_err(io_error(&backup))?;
+        }
+    }
+    std::fs::rename(&temp_path, path).map_err(io_error(path))?;
+    sync_parent_dir(path).map_err(io_error(path))
+}
+
+/// Overwrite the backup of path and remove it, for files whose old contents must not stay on disk
+pub fn discard_backup(path: &Path) -> Result<(), DurableFileError> {
+    let backup = backup_path(path);
+    let io_error = |source| DurableFileError::Io { path: backup.display().to_string(), source };
+    let len = match std::fs::metadata(&backup) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(source) => return Err(io_error(source)),
+    };
+    // Once the file has been replaced, the backup is the last name of the old contents,
+    // so zeroing it in place leaves no copy behind after the unlink
+    std::fs::OpenOptions::new().write(true).open(&backup)
+        .and_then(|mut file| {
+            file.write_all(&vec![0; len as usize])?;
+            file.sync_all()
+        })
+        .map_err(io_error)?;
+    std::fs::remove_file(&backup).map_err(io_error)?;
+    sync_parent_dir(path).map_err(io_error)
+}
+
+/// Make the rename itself survive a crash
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Read a file holding a JSON array. A missing or empty file is an empty list, anything
+/// else that does not parse is reported as corrupt instead of being taken for an empty list.
+pub fn read_json_list<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, DurableFileError> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(DurableFileError::Io { path: path.display().to_string(), source }),
+    };
+    if contents.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+    serde_json::from_slice(&contents).map_err(|e| corrupt(path, e))
+}
+
+/// Write a JSON array with write_atomic
+pub fn write_json_list<T: Serialize>(path: &Path, items: &[T]) -> Result<(), DurableFileError> {
+    let contents = serde_json::to_vec(items).map_err(|e| DurableFileError::Io {
+        path: path.display().to_string(),
+        source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+    })?;
+    write_atomic(path, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discarded_backup_is_gone_and_the_file_stays() {
+        let dir = std::env::temp_dir().join(format!("arke_durable_file_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("my_info.bin");
+        write_atomic_private(&path, b"secret key").unwrap();
+        write_atomic_private(&path, &[]).unwrap();
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"secret key");
+
+        discard_backup(&path).unwrap();
+        assert!(!backup_path(&path).exists());
+        assert_eq!(std::fs::read(&path).unwrap(), b"");
+        // Nothing to discard the second time
+        discard_backup(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+// ---------------------------------------
+// File: error.rs
+// Date: 18 Oct 2026
+// Description: Error codes returned by the servers
+// ---------------------------------------
+
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+/// Stable reason for rejecting a request, safe to match on in tests and in the client
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The body is not a request this protocol knows (unknown action, missing or mistyped field)
+    MalformedRequest,
+    /// A field is present but its value is not acceptable (e.g. an ID of the wrong length)
+    MalformedField,
+    /// A field does not decode to the cryptographic object it should hold
+    BadEncoding,
+    /// A zero-knowledge proof or an attestation did not verify
+    InvalidProof,
+    /// The server could not read or write its persistent state
+    StorageFailure,
+    /// The action is valid but this server does not serve it
+    UnsupportedAction,
+    /// The admin endpoint was used without logging in with the admin key, or an action of the
+    /// registration authority without logging in as the registration authority
+    Unauthorized,
+    /// The lease token is unknown, expired or was issued for another ID
+    InvalidLease,
+    /// The request frame is larger than the server accepts
+    OversizedFrame,
+    /// The job queue of the server is full, try again later
+    Busy,
+    /// No job with this ID, or its result has expired
+    UnknownJob,
+    /// The request needs a verified ID, and the answer to the challenge was missing, wrong or expired
+    Unverified,
+    /// Anything else that went wrong on the server side
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::MalformedRequest => "malformed_request",
+            ErrorCode::MalformedField => "malformed_field",
+            ErrorCode::BadEncoding => "bad_encoding",
+            ErrorCode::InvalidProof => "invalid_proof",
+            ErrorCode::StorageFailure => "storage_failure",
+            ErrorCode::UnsupportedAction => "unsupported_action",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::InvalidLease => "invalid_lease",
+            ErrorCode::OversizedFrame => "oversized_frame",
+            ErrorCode::Busy => "busy",
+            ErrorCode::UnknownJob => "unknown_job",
+            ErrorCode::Unverified => "unverified",
+            ErrorCode::Internal => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error raised while handling a request, sent back to the client as an error response
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{code}: {message}")]
+pub struct ServiceError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ServiceError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn malformed_field(field: &str, reason: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::MalformedField, format!("{}: {}", field, reason))
+    }
+
+    pub fn bad_encoding(field: &str, reason: impl fmt::Debug) -> Self {
+        Self::new(ErrorCode::BadEncoding, format!("{}: {:?}", field, reason))
+    }
+
+    pub fn invalid_proof(reason: impl fmt::Debug) -> Self {
+        Self::new(ErrorCode::InvalidProof, format!("{:?}", reason))
+    }
+
+    pub fn storage(reason: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::StorageFailure, reason.to_string())
+    }
+
+    pub fn internal(reason: impl fmt::Debug) -> Self {
+        Self::new(ErrorCode::Internal, format!("{:?}", reason))
+    }
+
+    pub fn unsupported_action(action: &str) -> Self {
+        Self::new(ErrorCode::UnsupportedAction, format!("this server does not serve {}", action))
+    }
+}
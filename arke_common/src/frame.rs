@@ -0,0 +1,288 @@
+// ---------------------------------------
+// File: frame.rs
+// Date: 18 Oct 2026
+// Description: Length-prefixed framing of the wire protocol
+// ---------------------------------------
+
+// Every message sent between the client and the servers is one frame:
+//
+//   +----------------+-----------+------------------+
+//   | length (u32 BE)| kind (u8) | body (length B)  |
+//   +----------------+-----------+------------------+
+//
+// The length only counts the body, so an empty body is a valid frame.
+// The body of a JSON frame is JSON, the body of a binary frame is CBOR. Which of the two a peer
+// writes is agreed on in the hello (see Encoding), a reader decodes a frame by its kind.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::messages::Encoding;
+
+/// Number of bytes in the frame header (length and kind)
+pub const FRAME_HEADER_LEN: usize = 5;
+/// Largest frame a server accepts as a request
+pub const MAX_REQUEST_FRAME_LEN: usize = 1 << 20;
+/// Largest frame a client accepts as a response, a chunk of pp_zk is the biggest payload (see download.rs)
+pub const MAX_RESPONSE_FRAME_LEN: usize = 32 << 20;
+/// Largest frame written at all, and read only where all of pp_zk comes in one response
+pub const MAX_FRAME_LEN: usize = 1 << 30;
+// The body of a frame is read in steps of at least this many bytes, doubling with what has arrived
+const READ_STEP: usize = 64 << 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Json,
+    Binary,
+}
+
+impl FrameKind {
+    /// Kind of the frames carrying messages in encoding
+    pub fn of(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Json => FrameKind::Json,
+            Encoding::Cbor => FrameKind::Binary,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameKind::Json => 0,
+            FrameKind::Binary => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, FrameError> {
+        match byte {
+            0 => Ok(FrameKind::Json),
+            1 => Ok(FrameKind::Binary),
+            other => Err(FrameError::UnknownKind(other)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("frame of {len} bytes exceeds the limit of {max} bytes")]
+    Oversized { len: usize, max: usize },
+    #[error("connection closed after {got} of {expected} bytes of a frame")]
+    Truncated { expected: usize, got: usize },
+    #[error("connection closed while waiting for a frame")]
+    Closed,
+    #[error("unknown frame kind {0}")]
+    UnknownKind(u8),
+    #[error("expected a {expected:?} frame but got a {got:?} frame")]
+    UnexpectedKind { expected: FrameKind, got: FrameKind },
+    #[error("invalid JSON body: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid CBOR body: {0}")]
+    Cbor(String),
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// Read until buf is full or the peer closes the connection, returning the number of bytes read
+async fn read_full<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Result<usize, FrameError> {
+    let mut got = 0;
+    while got < buf.len() {
+        let n = match reader.read(&mut buf[got..]).await {
+            Ok(n) => n,
+            // A TLS peer that closes without close_notify. Frames carry their length, so a cut frame is still caught as Truncated.
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if n == 0 {
+            break;
+        }
+        got += n;
+    }
+    Ok(got)
+}
+
+/// Read one frame whose body is at most max_len bytes.
+/// Returns None if the peer closed the connection between two frames.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, max_len: usize) -> Result<Option<Frame>, FrameError> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    let got = read_full(reader, &mut header).await?;
+    if got == 0 {
+        return Ok(None);
+    }
+    if got < FRAME_HEADER_LEN {
+        return Err(FrameError::Truncated { expected: FRAME_HEADER_LEN, got });
+    }
+
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let kind = FrameKind::from_byte(header[4])?;
+    if len > max_len {
+        return Err(FrameError::Oversized { len, max: max_len });
+    }
+
+    // Memory is only taken for what the peer actually sent, announcing a large frame costs nothing
+    let mut body = Vec::new();
+    while body.len() < len {
+        let start = body.len();
+        let end = len.min(start + start.max(READ_STEP));
+        body.resize(end, 0);
+        let got = read_full(reader, &mut body[start..]).await?;
+        if got < end - start {
+            return Err(FrameError::Truncated { expected: len, got: start + got });
+        }
+    }
+    Ok(Some(Frame { kind, body }))
+}
+
+/// Write one frame and flush it
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, kind: FrameKind, body: &[u8]) -> Result<(), FrameError> {
+    if body.len() > MAX_FRAME_LEN {
+        return Err(FrameError::Oversized { len: body.len(), max: MAX_FRAME_LEN });
+    }
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    header[..4].copy_from_slice(&(body.len() as u32).to_be_bytes());
+    header[4] = kind.to_byte();
+    writer.write_all(&header).await?;
+    writer.write_all(body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one JSON frame and deserialize its body.
+/// Returns None if the peer closed the connection between two frames.
+pub async fn read_json<R: AsyncRead + Unpin, T: DeserializeOwned>(reader: &mut R, max_len: usize) -> Result<Option<T>, FrameError> {
+    let frame = match read_frame(reader, max_len).await? {
+        Some(frame) => frame,
+        None => return Ok(None),
+    };
+    if frame.kind != FrameKind::Json {
+        return Err(FrameError::UnexpectedKind { expected: FrameKind::Json, got: frame.kind });
+    }
+    Ok(Some(serde_json::from_slice(&frame.body)?))
+}
+
+/// Serialize a value and write it as one JSON frame
+pub async fn write_json<W: AsyncWrite + Unpin, T: Serialize>(writer: &mut W, value: &T) -> Result<(), FrameError> {
+    let body = serde_json::to_vec(value)?;
+    write_frame(writer, FrameKind::Json, &body).await
+}
+
+/// The body of a message in encoding
+pub fn encode<T: Serialize>(encoding: Encoding, value: &T) -> Result<Vec<u8>, FrameError> {
+    match encoding {
+        Encoding::Json => Ok(serde_json::to_vec(value)?),
+        Encoding::Cbor => {
+            let mut body = Vec::new();
+            ciborium::into_writer(value, &mut body).map_err(|e| FrameError::Cbor(e.to_string()))?;
+            Ok(body)
+        },
+    }
+}
+
+/// The message in the body of a frame, decoded according to the kind of the frame
+pub fn decode<T: DeserializeOwned>(frame: &Frame) -> Result<T, FrameError> {
+    match frame.kind {
+        FrameKind::Json => Ok(serde_json::from_slice(&frame.body)?),
+        FrameKind::Binary => ciborium::from_reader(frame.body.as_slice()).map_err(|e| FrameError::Cbor(e.to_string())),
+    }
+}
+
+/// Read one frame in either encoding and decode its body.
+/// Returns None if the peer closed the connection between two frames.
+pub async fn read_message<R: AsyncRead + Unpin, T: DeserializeOwned>(reader: &mut R, max_len: usize) -> Result<Option<T>, FrameError> {
+    match read_frame(reader, max_len).await? {
+        Some(frame) => Ok(Some(decode(&frame)?)),
+        None => Ok(None),
+    }
+}
+
+/// Serialize a value and write it as one frame in encoding
+pub async fn write_message<W: AsyncWrite + Unpin, T: Serialize>(writer: &mut W, encoding: Encoding, value: &T) -> Result<(), FrameError> {
+    let body = encode(encoding, value)?;
+    write_frame(writer, FrameKind::of(encoding), &body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(len: u32, kind: u8) -> Vec<u8> {
+        let mut header = len.to_be_bytes().to_vec();
+        header.push(kind);
+        header
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        let mut written = Vec::new();
+        write_frame(&mut written, FrameKind::Json, b"{}").await.unwrap();
+        write_frame(&mut written, FrameKind::Binary, &[]).await.unwrap();
+        let body: Vec<u8> = (0..3 * READ_STEP + 7).map(|i| i as u8).collect();
+        write_frame(&mut written, FrameKind::Binary, &body).await.unwrap();
+
+        let mut reader = written.as_slice();
+        let frame = read_frame(&mut reader, MAX_REQUEST_FRAME_LEN).await.unwrap().unwrap();
+        assert_eq!((frame.kind, frame.body.as_slice()), (FrameKind::Json, b"{}".as_slice()));
+        let frame = read_frame(&mut reader, MAX_REQUEST_FRAME_LEN).await.unwrap().unwrap();
+        assert_eq!((frame.kind, frame.body.len()), (FrameKind::Binary, 0));
+        let frame = read_frame(&mut reader, MAX_REQUEST_FRAME_LEN).await.unwrap().unwrap();
+        assert!(frame.body == body);
+        // Closed between two frames
+        assert!(read_frame(&mut reader, MAX_REQUEST_FRAME_LEN).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_frame_is_refused_before_its_body() {
+        let mut reader = header(MAX_REQUEST_FRAME_LEN as u32 + 1, 0);
+        reader.extend_from_slice(b"{}");
+        let result = read_frame(&mut reader.as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::Oversized { len, max })
+                         if len == MAX_REQUEST_FRAME_LEN + 1 && max == MAX_REQUEST_FRAME_LEN));
+    }
+
+    #[tokio::test]
+    async fn truncated_header_and_body_are_reported() {
+        let result = read_frame(&mut [0u8, 0, 0].as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::Truncated { expected: FRAME_HEADER_LEN, got: 3 })));
+
+        // A peer announcing the largest response and sending a few bytes only gets those few read
+        let mut reader = header(MAX_RESPONSE_FRAME_LEN as u32, 1);
+        reader.extend_from_slice(&[7; 10]);
+        let result = read_frame(&mut reader.as_slice(), MAX_RESPONSE_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::Truncated { expected, got: 10 }) if expected == MAX_RESPONSE_FRAME_LEN));
+
+        // Cut after the first step of the body
+        let mut reader = header(2 * READ_STEP as u32, 1);
+        reader.extend_from_slice(&vec![7; READ_STEP + 1]);
+        let result = read_frame(&mut reader.as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::Truncated { got, .. }) if got == READ_STEP + 1));
+    }
+
+    #[tokio::test]
+    async fn unknown_and_unexpected_kinds_are_refused() {
+        let mut reader = header(2, 9);
+        reader.extend_from_slice(b"{}");
+        let result = read_frame(&mut reader.as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::UnknownKind(9))));
+
+        let mut written = Vec::new();
+        write_frame(&mut written, FrameKind::Binary, b"{}").await.unwrap();
+        let result = read_json::<_, serde_json::Value>(&mut written.as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::UnexpectedKind { expected: FrameKind::Json, got: FrameKind::Binary })));
+    }
+
+    #[tokio::test]
+    async fn bodies_that_do_not_decode_are_refused() {
+        let mut written = Vec::new();
+        write_frame(&mut written, FrameKind::Json, b"{not json").await.unwrap();
+        let result = read_json::<_, serde_json::Value>(&mut written.as_slice(), MAX_REQUEST_FRAME_LEN).await;
+        assert!(matches!(result, Err(FrameError::Json(_))));
+
+        let frame = Frame { kind: FrameKind::Binary, body: vec![0xff] };
+        assert!(matches!(decode::<serde_json::Value>(&frame), Err(FrameError::Cbor(_))));
+    }
+}
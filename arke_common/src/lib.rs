@@ -0,0 +1,22 @@
+// ---------------------------------------
+// File: lib.rs
+// Date: 18 Oct 2026
+// Description: Code shared by the client and the servers
+// ---------------------------------------
+
+pub mod admin;
+pub mod client;
+pub mod compression;
+pub mod config;
+pub mod download;
+pub mod durable_file;
+pub mod error;
+pub mod frame;
+pub mod messages;
+pub mod params_cache;
+pub mod server;
+// Unix sockets only
+#[cfg(unix)]
+pub mod signer;
+pub mod state_file;
+pub mod tls;
@@ -0,0 +1,557 @@
+// ---------------------------------------
+// File: messages.rs
+// Date: 18 Oct 2026
+// Description: Typed requests and responses of the wire protocol
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::de::Visitor;
+use crate::error::{ErrorCode, ServiceError};
+
+// Every change to the fields of a request or response takes a new version:
+//   2: add_user and commit_id carry an enrollment token, bound to the lease and the time it was issued at
+//   3: to_Register and authorize_deletion carry the answer to a challenge of start_verification
+//   4: the registration authority logs in to the database server and enrolls the ID itself,
+//      reg_attestation no longer carries an enrollment token
+//   5: reg_attestation carries the key_id of the registrar key, extraction requests the registrar_key_id
+/// Protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 5;
+/// Oldest protocol version this build still accepts. None of the changes above can be read by an older peer.
+pub const MIN_PROTOCOL_VERSION: u32 = 5;
+/// Length of the id string
+pub const IDENTIFIER_STRING_LENGTH: usize = 8;
+/// Prefix of what the registration authority signs in an enrollment token, so the signature is good for nothing else
+const ENROLLMENT_DOMAIN: &[u8] = b"arke-enrollment-v1:";
+/// Prefix of what the registration authority signs in a deletion token
+const DELETION_DOMAIN: &[u8] = b"arke-deletion-v1:";
+/// Prefix of what the registration authority signs to log in to the database server
+const REGISTRAR_LOGIN_DOMAIN: &[u8] = b"arke-registrar-login-v1:";
+
+/// CanonicalSerialize output of a cryptographic object, base64 encoded in JSON and raw bytes in CBOR
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blob(pub Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64::encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Blob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Inside the tagged and flattened messages serde no longer tells which format it reads,
+        // so the value itself decides: a string is base64, bytes are taken as they are
+        deserializer.deserialize_any(BlobVisitor)
+    }
+}
+
+struct BlobVisitor;
+
+impl<'de> Visitor<'de> for BlobVisitor {
+    type Value = Blob;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a base64 string or bytes")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, encoded: &str) -> Result<Blob, E> {
+        base64::decode(encoded).map(Blob).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Blob, E> {
+        Ok(Blob(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<Blob, E> {
+        Ok(Blob(bytes))
+    }
+}
+
+/// How the frames after the hello are encoded, see frame.rs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// JSON with base64 blobs, what every peer speaks
+    #[default]
+    Json,
+    /// CBOR with raw byte strings for the blobs, a third smaller and faster to parse
+    Cbor,
+}
+
+/// Encodings this build speaks, the preferred one first
+pub const ENCODINGS: [Encoding; 2] = [Encoding::Cbor, Encoding::Json];
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::Cbor => "cbor",
+        }
+    }
+
+    pub fn parse(encoding: &str) -> Option<Self> {
+        match encoding {
+            "json" => Some(Encoding::Json),
+            "cbor" => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+}
+
+/// How a large payload is compressed on top of the encoding
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    Zstd,
+}
+
+/// Answer to a challenge of start_verification
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub challenge_id: String,
+    /// The one-time code as received
+    pub code: String,
+}
+
+/// Lifecycle of an account in the user directory
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    Active,
+    /// Blocked by the operators, e.g. because the ID was enrolled fraudulently. The ID stays taken.
+    Suspended,
+    /// Deleted by its owner. The ID can be taken again once the reuse cooldown has passed.
+    Deleted,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Suspended => "suspended",
+            AccountStatus::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "active" => Some(AccountStatus::Active),
+            "suspended" => Some(AccountStatus::Suspended),
+            "deleted" => Some(AccountStatus::Deleted),
+            _ => None,
+        }
+    }
+}
+
+/// An account as listed on the admin endpoint of the database server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserSummary {
+    pub id_string: String,
+    pub status: AccountStatus,
+    /// Seconds since the Unix epoch when the status last changed
+    pub status_changed_at: u64,
+}
+
+/// One version of the registrar keypair, as the registration authority publishes it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RegistrarKeyInfo {
+    pub key_id: u32,
+    pub registrar_public_key: Blob,
+    /// Seconds since the Unix epoch when the key was created
+    pub valid_from: u64,
+    /// When a newer key took over signing attestations, None for the key in use.
+    /// Attestations made with a superseded key still verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_at: Option<u64>,
+    /// When the key was retired, attestations made with it no longer verify from then on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<u64>,
+}
+
+/// The key of key_id in keys, as long as it is not retired
+pub fn valid_registrar_key(keys: &[RegistrarKeyInfo], key_id: u32) -> Result<&RegistrarKeyInfo, ServiceError> {
+    match keys.iter().find(|key| key.key_id == key_id) {
+        Some(key) if key.retired_at.is_none() => Ok(key),
+        Some(_) => Err(ServiceError::new(ErrorCode::InvalidProof,
+            format!("registrar key {} was retired, register again to get an attestation under the current key", key_id))),
+        None => Err(ServiceError::new(ErrorCode::InvalidProof, format!("there is no registrar key {}", key_id))),
+    }
+}
+
+/// Requests answered for one action since the server started
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ActionCount {
+    pub action: String,
+    pub ok: u64,
+    pub rejected: u64,
+}
+
+/// State of a server as reported on its admin endpoint
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServerHealth {
+    pub server: String,
+    pub uptime_secs: u64,
+    pub protocol_version: u32,
+    pub requests: u64,
+    pub rejected: u64,
+    /// Server specific facts, e.g. the storage backend of the database server
+    pub details: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action")]
+pub enum Request {
+    /// Agree on a protocol version and an encoding, sent first on every connection and always as JSON.
+    /// encodings are the names of the encodings the client speaks, the preferred one first
+    #[serde(rename = "hello")]
+    Hello {
+        min_version: u32,
+        max_version: u32,
+        #[serde(default)]
+        encodings: Vec<String>,
+    },
+
+    // Database server
+    #[serde(rename = "check_uniqueness")]
+    CheckUniqueness { id_string: String },
+    /// Ask for a nonce for the registration authority to sign, see registrar_login_message
+    #[serde(rename = "registrar_challenge")]
+    RegistrarChallenge,
+    /// signature is the one of the enrollment key on registrar_login_message(nonce). Only a connection
+    /// logged in this way may use add_user, reserve_id, commit_id and release_id
+    #[serde(rename = "registrar_login")]
+    RegistrarLogin { signature: Blob },
+    /// enrollment_token is the signature of the registration authority on enrollment_message(id_string, None, issued_at)
+    #[serde(rename = "add_user")]
+    AddUser { id_string: String, enrollment_token: Blob, issued_at: u64 },
+    /// Hold the ID for the rest of the sign-up, so nobody else can take it meanwhile
+    #[serde(rename = "reserve_id")]
+    ReserveId { id_string: String },
+    /// Add the user holding the lease on the ID. enrollment_token is the signature of the registration
+    /// authority on enrollment_message(id_string, Some(lease_token), issued_at)
+    #[serde(rename = "commit_id")]
+    CommitId { id_string: String, lease_token: String, enrollment_token: Blob, issued_at: u64 },
+    /// Give up a lease before it expires
+    #[serde(rename = "release_id")]
+    ReleaseId { id_string: String, lease_token: String },
+    /// Status of an account, for contact discovery
+    #[serde(rename = "get_user_status")]
+    GetUserStatus { id_string: String },
+    /// deletion_token is the signature of the registration authority on deletion_message(id_string, issued_at)
+    #[serde(rename = "delete_user")]
+    DeleteUser { id_string: String, deletion_token: Blob, issued_at: u64 },
+
+    // Registration authority
+    /// Send a one-time code to the contact the ID is bound to, or to contact (an email address or a
+    /// phone number) for an ID that is not bound to one yet
+    #[serde(rename = "start_verification")]
+    StartVerification {
+        id_string: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        contact: Option<String>,
+    },
+    /// verification answers a challenge of start_verification for the ID. The registration authority
+    /// enrolls a new ID with the database server before it answers
+    #[serde(rename = "to_Register")]
+    Register {
+        id_string: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        verification: Option<Verification>,
+    },
+    /// The key new attestations are made with
+    #[serde(rename = "get_registrar_public_key")]
+    GetRegistrarPublicKey,
+    /// Every registrar key so far, retired ones included, oldest first
+    #[serde(rename = "get_registrar_key_history")]
+    GetRegistrarKeyHistory,
+    /// Let the owner of an ID delete its account, verified like to_Register
+    #[serde(rename = "authorize_deletion")]
+    AuthorizeDeletion {
+        id_string: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        verification: Option<Verification>,
+    },
+
+    // Key-issuing authority
+    /// Servers that do not know the compression asked for send pp_zk uncompressed
+    #[serde(rename = "get_pp_zk")]
+    GetPpZk {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+    },
+    /// Digest of what get_pp_zk returns, to check a cached copy against
+    #[serde(rename = "get_pp_zk_digest")]
+    GetPpZkDigest,
+    /// At most length bytes of what get_pp_zk returns, starting at offset (see download.rs)
+    #[serde(rename = "get_pp_zk_chunk")]
+    GetPpZkChunk {
+        offset: u64,
+        length: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+    },
+    #[serde(rename = "get_issuer_public_keys")]
+    GetIssuerPublicKeys,
+    /// registrar_key_id is the key_id the attestation came with, the key has to be one the registration
+    /// authority has not retired
+    #[serde(rename = "to_VerifyID_and_BlindPartialExtract")]
+    VerifyIdAndBlindPartialExtract {
+        registrar_key_id: u32,
+        blind_id: Blob,
+        blind_reg_attestation: Blob,
+    },
+    /// Queue the same work as to_VerifyID_and_BlindPartialExtract and answer at once with a job ID,
+    /// the keys are then fetched with get_job_status
+    #[serde(rename = "submit_blind_partial_extract")]
+    SubmitBlindPartialExtract {
+        registrar_key_id: u32,
+        blind_id: Blob,
+        blind_reg_attestation: Blob,
+    },
+    /// Answered with job_pending or job_running while the job is not done, then with the result of the job
+    #[serde(rename = "get_job_status")]
+    GetJobStatus { job_id: String },
+
+    // Admin endpoint of every server, see admin.rs
+    /// Ask for a nonce to prove knowledge of the admin key on
+    #[serde(rename = "admin_challenge")]
+    AdminChallenge,
+    /// proof is the HMAC-SHA256 of the nonce under the admin key
+    #[serde(rename = "admin_login")]
+    AdminLogin { proof: Blob },
+    #[serde(rename = "admin_health")]
+    AdminHealth,
+    #[serde(rename = "admin_request_counts")]
+    AdminRequestCounts,
+    /// Accounts whose ID starts with prefix and that have status, at most limit of them
+    #[serde(rename = "admin_list_users")]
+    AdminListUsers { prefix: Option<String>, status: Option<AccountStatus>, limit: u32 },
+    #[serde(rename = "admin_suspend_user")]
+    AdminSuspendUser { id_string: String },
+    /// Load the keys and parameters of the server again from its data directory
+    #[serde(rename = "admin_reload")]
+    AdminReload,
+    /// Make a new registrar key the one attestations are made with, the previous one keeps verifying
+    #[serde(rename = "admin_rotate_registrar_key")]
+    AdminRotateRegistrarKey,
+    /// Stop accepting attestations made with the key, e.g. because it leaked. Retiring the key in use
+    /// rotates to a new one first
+    #[serde(rename = "admin_retire_registrar_key")]
+    AdminRetireRegistrarKey { key_id: u32 },
+}
+
+impl Request {
+    /// Name of the action on the wire, used in logs and error messages
+    pub fn action(&self) -> &'static str {
+        match self {
+            Request::Hello { .. } => "hello",
+            Request::CheckUniqueness { .. } => "check_uniqueness",
+            Request::RegistrarChallenge => "registrar_challenge",
+            Request::RegistrarLogin { .. } => "registrar_login",
+            Request::AddUser { .. } => "add_user",
+            Request::ReserveId { .. } => "reserve_id",
+            Request::CommitId { .. } => "commit_id",
+            Request::ReleaseId { .. } => "release_id",
+            Request::GetUserStatus { .. } => "get_user_status",
+            Request::DeleteUser { .. } => "delete_user",
+            Request::StartVerification { .. } => "start_verification",
+            Request::Register { .. } => "to_Register",
+            Request::GetRegistrarPublicKey => "get_registrar_public_key",
+            Request::GetRegistrarKeyHistory => "get_registrar_key_history",
+            Request::AuthorizeDeletion { .. } => "authorize_deletion",
+            Request::GetPpZk { .. } => "get_pp_zk",
+            Request::GetPpZkDigest => "get_pp_zk_digest",
+            Request::GetPpZkChunk { .. } => "get_pp_zk_chunk",
+            Request::GetIssuerPublicKeys => "get_issuer_public_keys",
+            Request::VerifyIdAndBlindPartialExtract { .. } => "to_VerifyID_and_BlindPartialExtract",
+            Request::SubmitBlindPartialExtract { .. } => "submit_blind_partial_extract",
+            Request::GetJobStatus { .. } => "get_job_status",
+            Request::AdminChallenge => "admin_challenge",
+            Request::AdminLogin { .. } => "admin_login",
+            Request::AdminHealth => "admin_health",
+            Request::AdminRequestCounts => "admin_request_counts",
+            Request::AdminListUsers { .. } => "admin_list_users",
+            Request::AdminSuspendUser { .. } => "admin_suspend_user",
+            Request::AdminReload => "admin_reload",
+            Request::AdminRotateRegistrarKey => "admin_rotate_registrar_key",
+            Request::AdminRetireRegistrarKey { .. } => "admin_retire_registrar_key",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    /// The server accepted the hello and both sides now speak this version, in this encoding from the next frame on.
+    /// Servers predating the encodings send none and stay with JSON
+    Welcome {
+        negotiated_version: u32,
+        #[serde(default)]
+        encoding: Encoding,
+    },
+    /// The versions offered by the peer do not overlap with the versions of the server
+    VersionMismatch { min_version: u32, max_version: u32 },
+    Error { code: ErrorCode, message: String },
+
+    // Database server
+    IdAvailable,
+    IdTaken,
+    UserAdded,
+    /// The ID is held for ttl_secs seconds, commit_id with lease_token adds the user
+    IdReserved { lease_token: String, ttl_secs: u64 },
+    IdReleased,
+    UserStatus { status: AccountStatus },
+    UserNotFound,
+    UserDeleted,
+    RegistrarChallenge { nonce: Blob },
+    RegistrarLoggedIn,
+
+    // Registration authority
+    /// The code was sent to sent_to, shown partly hidden. It is good for attempts tries within expires_in_secs seconds
+    VerificationStarted { challenge_id: String, sent_to: String, expires_in_secs: u64, attempts: u32 },
+    /// key_id is the one of the registrar key the attestation was made with
+    RegAttestation { reg_attestation: Blob, key_id: u32 },
+    RegistrarPublicKey { registrar_public_key: Blob, key_id: u32 },
+    RegistrarKeyHistory { keys: Vec<RegistrarKeyInfo> },
+    /// Good for delete_user for a few minutes after issued_at (seconds since the Unix epoch)
+    DeletionToken { deletion_token: Blob, issued_at: u64 },
+
+    // Key-issuing authority
+    /// compression is how pp_zk is compressed, if it is
+    PpZk {
+        pp_zk: Blob,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+    },
+    /// Hex SHA-256 of the pp_zk encoding, and the version of that encoding (see params_cache.rs)
+    PpZkDigest { version: u32, digest: String },
+    /// The bytes of pp_zk at offset, compressed or not, out of total_len. digest is the one of
+    /// get_pp_zk_digest, so a client notices when pp_zk changes between two chunks.
+    PpZkChunk {
+        offset: u64,
+        total_len: u64,
+        digest: String,
+        chunk: Blob,
+        /// Hex SHA-256 of chunk
+        chunk_digest: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+    },
+    /// Public key of every issuer, by issuer index
+    IssuerPublicKeys { issuer_public_keys: Vec<Blob> },
+    BlindPartialUserKeys { blind_partial_user_keys: Vec<Blob> },
+    /// Answer of a single issuer node, which only holds the share of issuer_index
+    BlindPartialUserKey { issuer_index: u32, blind_partial_user_key: Blob },
+    /// queue_position jobs are ahead of this one, 0 if it runs next
+    JobQueued { job_id: String, queue_position: u32 },
+    JobPending { queue_position: u32 },
+    JobRunning,
+
+    // Admin endpoint
+    AdminChallenge { nonce: Blob },
+    AdminLoggedIn,
+    AdminHealth { health: ServerHealth },
+    AdminRequestCounts { counts: Vec<ActionCount> },
+    /// truncated is set when more accounts matched than the limit
+    AdminUsers { users: Vec<UserSummary>, truncated: bool },
+    /// changed is false if the ID already was suspended
+    AdminUserSuspended { changed: bool },
+    AdminReloaded { detail: String },
+    /// key_id is the one of the new key in use
+    AdminRegistrarKeyRotated { key_id: u32 },
+    /// current_key_id is the one of the key in use afterwards. changed is false if the key already was retired
+    AdminRegistrarKeyRetired { current_key_id: u32, changed: bool },
+}
+
+impl From<ServiceError> for Response {
+    fn from(error: ServiceError) -> Self {
+        Response::Error { code: error.code, message: error.message }
+    }
+}
+
+/// Every request on the wire carries the protocol version it was written for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RequestEnvelope {
+    pub version: u32,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResponseEnvelope {
+    pub version: u32,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+/// Pick the highest version supported by both sides, if any
+pub fn negotiate_version(min_version: u32, max_version: u32) -> Response {
+    let version = max_version.min(PROTOCOL_VERSION);
+    if version < min_version.max(MIN_PROTOCOL_VERSION) {
+        return Response::VersionMismatch {
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+        };
+    }
+    Response::Welcome { negotiated_version: version, encoding: Encoding::Json }
+}
+
+/// The first encoding offered by the peer that this build speaks, JSON if there is none
+pub fn negotiate_encoding(offered: &[String]) -> Encoding {
+    offered.iter()
+        .find_map(|encoding| Encoding::parse(encoding))
+        .unwrap_or_default()
+}
+
+/// What the registration authority signs to let an ID enroll with the database server, with the lease
+/// it holds on the ID if any. A token is only good for that lease and for a while after issued_at.
+pub fn enrollment_message(id_string: &str, lease_token: Option<&str>, issued_at: u64) -> Vec<u8> {
+    let mut message = ENROLLMENT_DOMAIN.to_vec();
+    // Both strings are prefixed with their length, an ID cannot borrow the start of a lease token
+    message.extend_from_slice(&(id_string.len() as u32).to_be_bytes());
+    message.extend_from_slice(id_string.as_bytes());
+    message.extend_from_slice(&issued_at.to_be_bytes());
+    match lease_token {
+        Some(lease_token) => {
+            message.push(1);
+            message.extend_from_slice(&(lease_token.len() as u32).to_be_bytes());
+            message.extend_from_slice(lease_token.as_bytes());
+        },
+        None => message.push(0),
+    }
+    message
+}
+
+/// What the registration authority signs to let the owner of an ID delete its account
+pub fn deletion_message(id_string: &str, issued_at: u64) -> Vec<u8> {
+    let mut message = DELETION_DOMAIN.to_vec();
+    message.extend_from_slice(id_string.as_bytes());
+    message.extend_from_slice(&issued_at.to_be_bytes());
+    message
+}
+
+/// What the registration authority signs to prove to the database server that it holds the enrollment key
+pub fn registrar_login_message(nonce: &[u8]) -> Vec<u8> {
+    let mut message = REGISTRAR_LOGIN_DOMAIN.to_vec();
+    message.extend_from_slice(nonce);
+    message
+}
+
+/// Check that an id string has the shape every user ID must have
+pub fn validate_id_string(id_string: &str) -> Result<(), ServiceError> {
+    if !id_string.chars().all(char::is_alphanumeric) {
+        return Err(ServiceError::malformed_field("id_string", "the ID has to be alphanumeric"));
+    }
+    if id_string.len() != IDENTIFIER_STRING_LENGTH {
+        return Err(ServiceError::malformed_field(
+            "id_string",
+            format!("the ID has to be {} characters long", IDENTIFIER_STRING_LENGTH),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+// ---------------------------------------
+// File: params_cache.rs
+// Date: 18 Oct 2026
+// Description: Digests of the public parameters, and the client cache checked against them
+// ---------------------------------------
+
+// pp_zk is large and slow to deserialize with every point checked. The key-issuing authority
+// publishes the digest of its encoding (get_pp_zk_digest), and the client keeps the parameters in a
+// local cache together with the digest they were downloaded under. While the published digest stays
+// the same the client loads the cache instead of downloading again. The cached bytes carry a digest
+// of their own, so a damaged cache is downloaded again rather than loaded.
+//
+// A download in progress is kept next to the cache in <name>.part, so that an interrupted download
+// resumes where it stopped, even in a later run of the client (see download.rs).
+
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::durable_file::{backup_path, corrupt, write_atomic, DurableFileError};
+use crate::messages::Compression;
+
+/// Version of the pp_zk encoding served by get_pp_zk, raised whenever it changes so that caches are refreshed
+pub const PP_ZK_VERSION: u32 = 1;
+
+/// Hex SHA-256 of bytes
+pub fn digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// What the bytes in <name>.bin are
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    version: u32,
+    /// Digest published by the server when the bytes were downloaded
+    digest: String,
+    /// Digest of the cached bytes, which may be encoded differently from the downloaded ones
+    cached_digest: String,
+}
+
+// What the bytes in <name>.part are the beginning of
+#[derive(Serialize, Deserialize, PartialEq)]
+struct PartialEntry {
+    /// Digest published by the server when the download started
+    digest: String,
+    compression: Option<Compression>,
+}
+
+/// Public parameters cached in dir, every entry in <name>.bin with its digests in <name>.json
+pub struct ParamsCache {
+    dir: PathBuf,
+}
+
+impl ParamsCache {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    fn paths(&self, name: &str) -> (PathBuf, PathBuf) {
+        (self.dir.join(format!("{}.json", name)), self.dir.join(format!("{}.bin", name)))
+    }
+
+    fn partial_paths(&self, name: &str) -> (PathBuf, PathBuf) {
+        (self.dir.join(format!("{}.part.json", name)), self.dir.join(format!("{}.part", name)))
+    }
+
+    /// The bytes cached as name, if they were downloaded under this version and digest.
+    /// Cached bytes not matching their own digest are reported as corrupt.
+    pub fn load(&self, name: &str, version: u32, digest: &str) -> Result<Option<Vec<u8>>, DurableFileError> {
+        let (entry_path, bytes_path) = self.paths(name);
+        let entry = match std::fs::read(&entry_path) {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(DurableFileError::Io { path: entry_path.display().to_string(), source }),
+        };
+        let entry: CacheEntry = serde_json::from_slice(&entry).map_err(|e| corrupt(&entry_path, e))?;
+        if entry.version != version || entry.digest != digest {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(&bytes_path)
+            .map_err(|source| DurableFileError::Io { path: bytes_path.display().to_string(), source })?;
+        if self::digest(&bytes) != entry.cached_digest {
+            return Err(corrupt(&bytes_path, "its digest does not match"));
+        }
+        Ok(Some(bytes))
+    }
+
+    /// Cache bytes as name, for the version and digest the server published
+    pub fn store(&self, name: &str, version: u32, digest: &str, bytes: &[u8]) -> Result<(), DurableFileError> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|source| DurableFileError::Io { path: self.dir.display().to_string(), source })?;
+        let (entry_path, bytes_path) = self.paths(name);
+        let entry = CacheEntry { version, digest: digest.to_string(), cached_digest: self::digest(bytes) };
+        // The bytes first: a crash in between leaves an entry whose cached digest does not match,
+        // which is downloaded again
+        write_atomic(&bytes_path, bytes)?;
+        write_atomic(&entry_path, &serde_json::to_vec(&entry).expect("a cache entry always serializes"))?;
+        // A cache can always be downloaded again, the previous parameters need no backup
+        let _ = std::fs::remove_file(backup_path(&bytes_path));
+        let _ = std::fs::remove_file(backup_path(&entry_path));
+        Ok(())
+    }
+
+    /// The bytes downloaded so far of name, if the download was started under this digest and compression.
+    /// A download of anything else is discarded.
+    pub fn load_partial(&self, name: &str, digest: &str, compression: Option<Compression>) -> Result<Vec<u8>, DurableFileError> {
+        let (entry_path, bytes_path) = self.partial_paths(name);
+        let entry = match std::fs::read(&entry_path) {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(DurableFileError::Io { path: entry_path.display().to_string(), source }),
+        };
+        let current = PartialEntry { digest: digest.to_string(), compression };
+        // A damaged entry only costs the bytes downloaded so far
+        if serde_json::from_slice::<PartialEntry>(&entry).ok().as_ref() != Some(&current) {
+            self.remove_partial(name);
+            return Ok(Vec::new());
+        }
+        match std::fs::read(&bytes_path) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(source) => Err(DurableFileError::Io { path: bytes_path.display().to_string(), source }),
+        }
+    }
+
+    /// Add chunk to the download of name at offset, the number of bytes downloaded so far.
+    /// The download starts over at offset 0.
+    pub fn append_partial(&self, name: &str, digest: &str, compression: Option<Compression>, offset: u64, chunk: &[u8])
+                          -> Result<(), DurableFileError> {
+        let (entry_path, bytes_path) = self.partial_paths(name);
+        let io_error = |source| DurableFileError::Io { path: bytes_path.display().to_string(), source };
+        if offset == 0 {
+            std::fs::create_dir_all(&self.dir)
+                .map_err(|source| DurableFileError::Io { path: self.dir.display().to_string(), source })?;
+            let entry = PartialEntry { digest: digest.to_string(), compression };
+            write_atomic(&entry_path, &serde_json::to_vec(&entry).expect("a partial entry always serializes"))?;
+            let _ = std::fs::remove_file(backup_path(&entry_path));
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).write(true).truncate(false).open(&bytes_path).map_err(io_error)?;
+        // Whatever follows offset, e.g. half a chunk written before a crash, is overwritten
+        file.set_len(offset).map_err(io_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        file.write_all(chunk).map_err(io_error)?;
+        file.sync_data().map_err(io_error)
+    }
+
+    /// Forget the download of name
+    pub fn remove_partial(&self, name: &str) {
+        let (entry_path, bytes_path) = self.partial_paths(name);
+        let _ = std::fs::remove_file(entry_path);
+        let _ = std::fs::remove_file(bytes_path);
+    }
+}
@@ -0,0 +1,90 @@
+// ---------------------------------------
+// File: server.rs
+// Date: 18 Oct 2026
+// Description: Request loop shared by the database server and the authorities
+// ---------------------------------------
+
+use std::future::Future;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::error::{ErrorCode, ServiceError};
+use crate::frame::{read_message, write_message, FrameError, MAX_REQUEST_FRAME_LEN};
+use crate::messages::{negotiate_encoding, negotiate_version, Encoding, Request, RequestEnvelope, Response, ResponseEnvelope,
+                      MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+/// Serve one client connection until it is closed.
+/// Hello requests, version checks and the choice of encoding are handled here, every other request is passed to handler.
+/// Errors returned by handler are sent back as error responses.
+pub async fn serve_connection<S, F, Fut>(mut socket: S, mut handler: F)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: FnMut(Request) -> Fut,
+    Fut: Future<Output = Result<Response, ServiceError>>,
+{
+    // Version agreed on in the hello, if the client sent one
+    let mut negotiated: Option<u32> = None;
+    // Encoding of the responses, JSON until the hello picked another one
+    let mut encoding = Encoding::Json;
+
+    loop {
+        let response = match read_message::<_, RequestEnvelope>(&mut socket, MAX_REQUEST_FRAME_LEN).await {
+            // Connection closed
+            Ok(None) => return,
+            // Version and encoding negotiation
+            Ok(Some(RequestEnvelope { request: Request::Hello { min_version, max_version, encodings }, .. })) => {
+                let mut response = negotiate_version(min_version, max_version);
+                if let Response::Welcome { negotiated_version, encoding } = &mut response {
+                    negotiated = Some(*negotiated_version);
+                    *encoding = negotiate_encoding(&encodings);
+                }
+                response
+            }
+            // Connection open, handle request
+            Ok(Some(envelope)) => {
+                let supported = match negotiated {
+                    Some(version) => envelope.version == version,
+                    None => (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&envelope.version),
+                };
+                if supported {
+                    let action = envelope.request.action();
+                    handler(envelope.request).await.unwrap_or_else(|e| {
+                        eprintln!("X {} rejected; err = {}", action, e);
+                        e.into()
+                    })
+                } else {
+                    Response::VersionMismatch { min_version: MIN_PROTOCOL_VERSION, max_version: PROTOCOL_VERSION }
+                }
+            }
+            // The frame was intact but its body was not a valid request, the stream is still usable
+            Err(FrameError::Json(e)) => ServiceError::new(ErrorCode::MalformedRequest, e.to_string()).into(),
+            Err(FrameError::Cbor(e)) => ServiceError::new(ErrorCode::MalformedRequest, e).into(),
+            // The stream cannot be resynchronised, report the error and close the connection
+            Err(e @ FrameError::Oversized { .. }) | Err(e @ FrameError::UnknownKind(_)) => {
+                eprintln!("rejected frame; err = {:?}", e);
+                let code = match e {
+                    FrameError::Oversized { .. } => ErrorCode::OversizedFrame,
+                    _ => ErrorCode::MalformedRequest,
+                };
+                let response: Response = ServiceError::new(code, e.to_string()).into();
+                let _ = write_message(&mut socket, encoding, &ResponseEnvelope { version: PROTOCOL_VERSION, response }).await;
+                return;
+            }
+            Err(e) => {
+                eprintln!("failed to read from socket; err = {:?}", e);
+                return;
+            }
+        };
+
+        let version = negotiated.unwrap_or(PROTOCOL_VERSION);
+        // The welcome itself still goes out in the encoding of the hello
+        let next_encoding = match &response {
+            Response::Welcome { encoding, .. } => *encoding,
+            _ => encoding,
+        };
+        if let Err(e) = write_message(&mut socket, encoding, &ResponseEnvelope { version, response }).await {
+            eprintln!("failed to write to socket; err = {:?}", e);
+            return;
+        }
+        encoding = next_encoding;
+    }
+}
@@ -0,0 +1,239 @@
+// ---------------------------------------
+// File: signer.rs
+// Date: 18 Oct 2026
+// Description: Local signer process holding the secret keys of an authority
+// ---------------------------------------
+
+// An authority can be split in two processes on the same machine. The signer holds the secret
+// keys and only listens on a Unix socket, the network process serves clients and holds no key
+// material. They speak JSON frames (see frame.rs), one request and one response at a time:
+//
+//   register { id_string }                                                 -> reg_attestation
+//   registrar_login { nonce }                                              -> signature
+//   enrollment_token { id_string, lease_token }                            -> enrollment_token
+//   deletion_token { id_string }                                           -> deletion_token
+//   blind_partial_extract { registrar_key_id, blind_id, blind_reg_attestation } -> blind_partial_user_keys
+//   public_keys {}                                                         -> registrar_public_keys | public_setup
+//
+// These are the whole interface, the signer answers nothing else. The registration authority
+// signer answers the first four, with the registrar keys and the enrollment key, the key-issuing
+// authority signer blind_partial_extract. Both answer public_keys with the public half of what they
+// hold, so the network process needs no file of the signer. The network process has
+// checked every request before it passes it on, a parser bug on its side cannot reach the keys.
+
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::error::{ErrorCode, ServiceError};
+use crate::frame::{read_json, write_json, FrameError, MAX_FRAME_LEN, MAX_REQUEST_FRAME_LEN, MAX_RESPONSE_FRAME_LEN};
+use crate::messages::{Blob, RegistrarKeyInfo};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SignerRequest {
+    /// Run ID-NIKE.Register for an ID the network process has verified and enrolled
+    Register { id_string: String },
+    /// Sign the nonce the database server challenges the registration authority with
+    RegistrarLogin { nonce: Blob },
+    /// Let an ID the network process has verified and reserved under lease_token enroll with the database server
+    EnrollmentToken { id_string: String, lease_token: Option<String> },
+    /// Let the owner of an ID delete its account, from now on for as long as the database server allows
+    DeletionToken { id_string: String },
+    /// Run ID-NIKE.VerifyID and ID-NIKE.BlindPartialExtract with every share the signer holds
+    BlindPartialExtract { registrar_key_id: u32, blind_id: Blob, blind_reg_attestation: Blob },
+    /// The public half of the keys the signer holds
+    PublicKeys,
+}
+
+impl SignerRequest {
+    pub fn action(&self) -> &'static str {
+        match self {
+            SignerRequest::Register { .. } => "register",
+            SignerRequest::RegistrarLogin { .. } => "registrar_login",
+            SignerRequest::EnrollmentToken { .. } => "enrollment_token",
+            SignerRequest::DeletionToken { .. } => "deletion_token",
+            SignerRequest::BlindPartialExtract { .. } => "blind_partial_extract",
+            SignerRequest::PublicKeys => "public_keys",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum SignerResponse {
+    /// key_id is the registrar key the attestation was made with
+    RegAttestation { reg_attestation: Blob, key_id: u32 },
+    /// Made with the enrollment key
+    Signature { signature: Blob },
+    /// issued_at is the time the signer signed it at, in seconds since the Unix epoch
+    EnrollmentToken { enrollment_token: Blob, issued_at: u64 },
+    /// issued_at is the time the signer signed it at, in seconds since the Unix epoch
+    DeletionToken { deletion_token: Blob, issued_at: u64 },
+    /// One key per share, in the order of the issuers
+    BlindPartialUserKeys { blind_partial_user_keys: Vec<Blob> },
+    /// Every registrar key oldest first, and the public key of the enrollment key
+    RegistrarPublicKeys { registrar_keys: Vec<RegistrarKeyInfo>, enrollment_public_key: Blob },
+    /// pp_zk and the issuer public keys, serialized as the key-issuing authority saves them
+    PublicSetup { public_setup: Blob },
+    Error { code: ErrorCode, message: String },
+}
+
+impl From<ServiceError> for SignerResponse {
+    fn from(error: ServiceError) -> Self {
+        SignerResponse::Error { code: error.code, message: error.message }
+    }
+}
+
+/// Listen on the Unix socket at path and answer every request with handler, until the listener fails.
+/// Only the owner of the process can connect.
+pub async fn serve_signer<F>(path: &Path, handler: F) -> std::io::Result<()>
+where
+    F: Fn(SignerRequest) -> Result<SignerResponse, ServiceError> + Clone + Send + 'static,
+{
+    // Left behind by a signer that did not shut down cleanly. Anything else at path is not ours to remove.
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let handler = handler.clone();
+        tokio::spawn(async move { serve_signer_connection(socket, handler).await });
+    }
+}
+
+async fn serve_signer_connection<F>(mut socket: UnixStream, handler: F)
+where
+    F: Fn(SignerRequest) -> Result<SignerResponse, ServiceError> + Clone + Send + 'static,
+{
+    loop {
+        let response = match read_json::<_, SignerRequest>(&mut socket, MAX_REQUEST_FRAME_LEN).await {
+            Ok(None) => return,
+            Ok(Some(request)) => {
+                let action = request.action();
+                let handler = handler.clone();
+                // The keys are used on a blocking thread, VerifyID takes long enough to stall the runtime
+                let response = tokio::task::spawn_blocking(move || handler(request)).await
+                    .unwrap_or_else(|_| Err(ServiceError::new(ErrorCode::Internal, "the signer panicked")));
+                response.unwrap_or_else(|e| {
+                    eprintln!("X {} refused by the signer; err = {}", action, e);
+                    e.into()
+                })
+            },
+            // The frame was intact, the stream is still usable
+            Err(FrameError::Json(e)) => ServiceError::new(ErrorCode::MalformedRequest, e.to_string()).into(),
+            Err(e) => {
+                eprintln!("X Signer connection closed; err = {}", e);
+                return;
+            },
+        };
+        if let Err(e) = write_json(&mut socket, &response).await {
+            eprintln!("X Signer connection closed; err = {}", e);
+            return;
+        }
+    }
+}
+
+/// Where the network process reaches its signer, a connection is opened for every request
+#[derive(Clone, Debug)]
+pub struct SignerClient {
+    path: PathBuf,
+}
+
+impl SignerClient {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Send one request to the signer. Its refusals come back with their own code, so e.g. a proof
+    /// that does not verify is still reported to the client as invalid_proof.
+    pub async fn call(&self, request: SignerRequest) -> Result<SignerResponse, ServiceError> {
+        let unavailable = |reason: String| ServiceError::new(ErrorCode::Internal,
+            format!("the signer at {} could not be used: {}", self.path.display(), reason));
+        let mut socket = UnixStream::connect(&self.path).await.map_err(|e| unavailable(e.to_string()))?;
+        write_json(&mut socket, &request).await.map_err(|e| unavailable(e.to_string()))?;
+        // The public setup of the key-issuing authority carries all of pp_zk in one response
+        let max_len = match request {
+            SignerRequest::PublicKeys => MAX_FRAME_LEN,
+            _ => MAX_RESPONSE_FRAME_LEN,
+        };
+        let response = read_json(&mut socket, max_len).await
+            .map_err(|e| unavailable(e.to_string()))?
+            .ok_or_else(|| unavailable(FrameError::Closed.to_string()))?;
+        match response {
+            SignerResponse::Error { code, message } => Err(ServiceError::new(code, message)),
+            response => Ok(response),
+        }
+    }
+
+    /// The attestation of id_string and the key_id of the registrar key it was made with
+    pub async fn register(&self, id_string: &str) -> Result<(Blob, u32), ServiceError> {
+        match self.call(SignerRequest::Register { id_string: id_string.to_string() }).await? {
+            SignerResponse::RegAttestation { reg_attestation, key_id } => Ok((reg_attestation, key_id)),
+            other => Err(unexpected("register", other)),
+        }
+    }
+
+    /// The signature of the login to the database server with the nonce it sent
+    pub async fn registrar_login(&self, nonce: &Blob) -> Result<Blob, ServiceError> {
+        match self.call(SignerRequest::RegistrarLogin { nonce: nonce.clone() }).await? {
+            SignerResponse::Signature { signature } => Ok(signature),
+            other => Err(unexpected("registrar_login", other)),
+        }
+    }
+
+    /// An enrollment token of id_string for the lease on it, and the time it was issued at
+    pub async fn enrollment_token(&self, id_string: &str, lease_token: Option<&str>) -> Result<(Blob, u64), ServiceError> {
+        let request = SignerRequest::EnrollmentToken { id_string: id_string.to_string(), lease_token: lease_token.map(str::to_string) };
+        match self.call(request).await? {
+            SignerResponse::EnrollmentToken { enrollment_token, issued_at } => Ok((enrollment_token, issued_at)),
+            other => Err(unexpected("enrollment_token", other)),
+        }
+    }
+
+    /// A deletion token of id_string and the time it was issued at
+    pub async fn deletion_token(&self, id_string: &str) -> Result<(Blob, u64), ServiceError> {
+        match self.call(SignerRequest::DeletionToken { id_string: id_string.to_string() }).await? {
+            SignerResponse::DeletionToken { deletion_token, issued_at } => Ok((deletion_token, issued_at)),
+            other => Err(unexpected("deletion_token", other)),
+        }
+    }
+
+    /// One blind partial user key per share the signer holds
+    pub async fn blind_partial_extract(&self, registrar_key_id: u32, blind_id: Blob, blind_reg_attestation: Blob)
+        -> Result<Vec<Blob>, ServiceError> {
+        let request = SignerRequest::BlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation };
+        match self.call(request).await? {
+            SignerResponse::BlindPartialUserKeys { blind_partial_user_keys } => Ok(blind_partial_user_keys),
+            other => Err(unexpected("blind_partial_extract", other)),
+        }
+    }
+
+    /// The public half of every registrar key, oldest first, and the enrollment public key
+    pub async fn registrar_public_keys(&self) -> Result<(Vec<RegistrarKeyInfo>, Blob), ServiceError> {
+        match self.call(SignerRequest::PublicKeys).await? {
+            SignerResponse::RegistrarPublicKeys { registrar_keys, enrollment_public_key } => Ok((registrar_keys, enrollment_public_key)),
+            other => Err(unexpected("public_keys", other)),
+        }
+    }
+
+    /// The serialized pp_zk and issuer public keys of the key-issuing authority
+    pub async fn public_setup(&self) -> Result<Blob, ServiceError> {
+        match self.call(SignerRequest::PublicKeys).await? {
+            SignerResponse::PublicSetup { public_setup } => Ok(public_setup),
+            other => Err(unexpected("public_keys", other)),
+        }
+    }
+}
+
+fn unexpected(action: &str, response: SignerResponse) -> ServiceError {
+    ServiceError::new(ErrorCode::Internal, format!("unexpected answer of the signer to {}: {:?}", action, response))
+}
@@ -0,0 +1,64 @@
+// ---------------------------------------
+// File: state_file.rs
+// Date: 18 Oct 2026
+// Description: Versioned files holding the persistent state of the servers
+// ---------------------------------------
+
+// A state file is:
+//
+//   +-----------------+------------------+-----------------------------------+
+//   | magic (8 bytes) | version (u32 LE) | body (CanonicalSerialize output)  |
+//   +-----------------+------------------+-----------------------------------+
+//
+// The magic tells which server wrote the file, the version tells how to read the body.
+
+use std::path::Path;
+use thiserror::Error;
+use crate::durable_file::{corrupt, write_atomic_private, DurableFileError};
+
+#[derive(Debug, Error)]
+pub enum StateFileError {
+    #[error("could not access {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+    #[error("{path} is not a {expected} state file")]
+    BadMagic { path: String, expected: String },
+    #[error("{path} has format version {found}, this build reads version {supported}")]
+    UnsupportedVersion { path: String, found: u32, supported: u32 },
+    #[error(transparent)]
+    File(#[from] DurableFileError),
+}
+
+const HEADER_LEN: usize = 12;
+
+/// Read the body of a state file, or None if the file does not exist
+pub fn read_state_file(path: &Path, magic: &[u8; 8], version: u32) -> Result<Option<Vec<u8>>, StateFileError> {
+    let display = path.display().to_string();
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(StateFileError::Io { path: display, source }),
+    };
+    if contents.len() < HEADER_LEN {
+        return Err(corrupt(path, "shorter than its header").into());
+    }
+    if &contents[..8] != magic {
+        return Err(StateFileError::BadMagic { path: display, expected: String::from_utf8_lossy(magic).into_owned() });
+    }
+    let found = u32::from_le_bytes([contents[8], contents[9], contents[10], contents[11]]);
+    if found != version {
+        return Err(StateFileError::UnsupportedVersion { path: display, found, supported: version });
+    }
+    Ok(Some(contents[HEADER_LEN..].to_vec()))
+}
+
+/// Write a state file that only the owner can read, since it holds secret keys.
+/// The previous file is kept as a backup, see durable_file.
+pub fn write_state_file(path: &Path, magic: &[u8; 8], version: u32, body: &[u8]) -> Result<(), StateFileError> {
+    let mut contents = Vec::with_capacity(HEADER_LEN + body.len());
+    contents.extend_from_slice(magic);
+    contents.extend_from_slice(&version.to_le_bytes());
+    contents.extend_from_slice(body);
+
+    write_atomic_private(path, &contents)?;
+    Ok(())
+}
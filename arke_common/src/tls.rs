@@ -0,0 +1,263 @@
+// ---------------------------------------
+// File: tls.rs
+// Date: 18 Oct 2026
+// Description: TLS between the client and the servers, authenticated by pinning the key of the server
+// ---------------------------------------
+
+// A server given a certificate and a private key (--tls-cert, --tls-key) only accepts TLS
+// connections. The client does not trust certificate authorities: it connects over TLS to every
+// endpoint it has a pin for, and accepts the server only if the SHA-256 digest of its certificate
+// (cert-sha256:<hex>) or of its public key (spki-sha256:<hex>) is the pinned one. The chain, the
+// name in the certificate and its validity dates are not checked, the pin stands for all of them;
+// the signature of the handshake still has to verify under the pinned key. A public key pin stays
+// valid when the certificate is renewed with the same key. An endpoint without a pin is refused,
+// unless the client was explicitly allowed to fall back to plain TCP.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::pin::Pin as StdPin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::ParsedCertificate;
+use rustls::{ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::{TlsConnector, TlsStream};
+
+use crate::config::TlsFiles;
+use crate::params_cache::digest;
+
+pub use tokio_rustls::TlsAcceptor;
+
+// Longest either side waits for the other to finish the handshake before dropping the connection
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("could not read {path}: {source}")]
+    Read { path: String, source: io::Error },
+    #[error("{path} holds no {what}")]
+    Missing { path: String, what: &'static str },
+    #[error("unusable TLS certificate or key: {0}")]
+    Rustls(#[from] rustls::Error),
+    #[error("no pin for {endpoint}, pin its certificate or public key or explicitly allow plain TCP")]
+    Unpinned { endpoint: String },
+}
+
+/// What a client recognises a server by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pin {
+    /// Hex SHA-256 of the DER certificate
+    Certificate(String),
+    /// Hex SHA-256 of the DER SubjectPublicKeyInfo of the certificate
+    PublicKey(String),
+}
+
+impl Pin {
+    pub fn of_certificate(certificate: &[u8]) -> Self {
+        Pin::Certificate(digest(certificate))
+    }
+
+    pub fn of_public_key(subject_public_key_info: &[u8]) -> Self {
+        Pin::PublicKey(digest(subject_public_key_info))
+    }
+
+    /// Parse cert-sha256:<hex> or spki-sha256:<hex>
+    pub fn parse(pin: &str) -> Option<Self> {
+        let (kind, hex) = pin.split_once(':')?;
+        if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let hex = hex.to_ascii_lowercase();
+        match kind {
+            "cert-sha256" => Some(Pin::Certificate(hex)),
+            "spki-sha256" => Some(Pin::PublicKey(hex)),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, certificate: &CertificateDer<'_>) -> Result<bool, rustls::Error> {
+        Ok(match self {
+            Pin::Certificate(hex) => digest(certificate) == *hex,
+            Pin::PublicKey(hex) => digest(&ParsedCertificate::try_from(certificate)?.subject_public_key_info()) == *hex,
+        })
+    }
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pin::Certificate(hex) => write!(f, "cert-sha256:{}", hex),
+            Pin::PublicKey(hex) => write!(f, "spki-sha256:{}", hex),
+        }
+    }
+}
+
+/// Pins by endpoint (host:port). Endpoints with a pin are connected to over TLS, the others are refused
+/// unless plain TCP was allowed.
+#[derive(Debug, Clone)]
+pub struct Pins {
+    pins: Arc<BTreeMap<String, Pin>>,
+    plaintext: bool,
+}
+
+impl Pins {
+    /// Only the endpoints in pins can be connected to
+    pub fn new(pins: BTreeMap<String, Pin>) -> Self {
+        Pins { pins: Arc::new(pins), plaintext: false }
+    }
+
+    /// Connect to endpoints without a pin over plain TCP instead of refusing them, for local testing.
+    /// Anyone on the path can then read and change the traffic.
+    pub fn allow_plaintext(self) -> Self {
+        Pins { plaintext: true, ..self }
+    }
+
+    pub fn allows_plaintext(&self) -> bool {
+        self.plaintext
+    }
+
+    /// The pin of endpoint, None if it may be connected to over plain TCP
+    pub fn get(&self, endpoint: &str) -> Result<Option<&Pin>, TlsError> {
+        match self.pins.get(endpoint) {
+            Some(pin) => Ok(Some(pin)),
+            None if self.plaintext => Ok(None),
+            None => Err(TlsError::Unpinned { endpoint: endpoint.to_string() }),
+        }
+    }
+}
+
+/// A connection either side of which may be TLS
+pub enum Transport {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(self: StdPin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => StdPin::new(stream).poll_read(cx, buf),
+            Transport::Tls(stream) => StdPin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(self: StdPin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => StdPin::new(stream).poll_write(cx, buf),
+            Transport::Tls(stream) => StdPin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: StdPin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => StdPin::new(stream).poll_flush(cx),
+            Transport::Tls(stream) => StdPin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: StdPin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => StdPin::new(stream).poll_shutdown(cx),
+            Transport::Tls(stream) => StdPin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The acceptor of a server, with the certificate chain and the private key in files
+pub fn load_acceptor(files: &TlsFiles) -> Result<TlsAcceptor, TlsError> {
+    let read = |path: &Path| std::fs::read(path).map_err(|source| TlsError::Read { path: path.display().to_string(), source });
+    let missing = |path: &Path, what| TlsError::Missing { path: path.display().to_string(), what };
+
+    let cert_pem = read(&files.cert)?;
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Read { path: files.cert.display().to_string(), source })?;
+    if certs.is_empty() {
+        return Err(missing(&files.cert, "PEM certificate"));
+    }
+    let key_pem = read(&files.key)?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+        .map_err(|source| TlsError::Read { path: files.key.display().to_string(), source })?
+        .ok_or_else(|| missing(&files.key, "PEM private key"))?;
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Run the server side of the handshake on socket if the server has an acceptor
+pub async fn accept(acceptor: Option<&TlsAcceptor>, socket: TcpStream) -> io::Result<Transport> {
+    let acceptor = match acceptor {
+        Some(acceptor) => acceptor,
+        None => return Ok(Transport::Plain(socket)),
+    };
+    match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(socket)).await {
+        Ok(stream) => Ok(Transport::Tls(Box::new(TlsStream::Server(stream?)))),
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "no TLS handshake in time")),
+    }
+}
+
+/// Run the client side of the handshake with the server at addr, which has to present the pinned key
+pub async fn connect(addr: &str, pin: &Pin, socket: TcpStream) -> io::Result<Transport> {
+    let provider = Arc::new(ring::default_provider());
+    let verifier = PinVerifier { pin: pin.clone(), algorithms: provider.signature_verification_algorithms };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    // Only sent as SNI, the pin is what the server is checked against
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host).trim_start_matches('[').trim_end_matches(']');
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // A server that accepts the connection but never answers must not hold the client forever
+    match timeout(HANDSHAKE_TIMEOUT, TlsConnector::from(Arc::new(config)).connect(server_name, socket)).await {
+        Ok(stream) => Ok(Transport::Tls(Box::new(TlsStream::Client(stream?)))),
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "no TLS handshake in time")),
+    }
+}
+
+// Accepts the server whose certificate matches the pin
+#[derive(Debug)]
+struct PinVerifier {
+    pin: Pin,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(&self, end_entity: &CertificateDer<'_>, _intermediates: &[CertificateDer<'_>],
+                          _server_name: &ServerName<'_>, _ocsp_response: &[u8], _now: UnixTime)
+                          -> Result<ServerCertVerified, rustls::Error> {
+        if self.pin.matches(end_entity)? {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!("the server certificate does not match the pin {}", self.pin)))
+        }
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct)
+                              -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct)
+                              -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,199 @@
+// ---------------------------------------
+// File: resumable_download.rs
+// Date: 18 Oct 2026
+// Description: A download of pp_zk cut midway resumes and still ends with the right parameters in the cache
+// ---------------------------------------
+
+// The server serves random bytes in place of pp_zk, arke_common does not know BlindIDCircuitParameters.
+// The client checks what it downloaded against the published digest and caches it the way the
+// client caches pp_zk, so a cache loaded under that digest holds the exact bytes served.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use arke_common::download::{download_pp_zk, serve_chunk, DownloadError, DownloadOptions};
+use arke_common::messages::Request;
+use arke_common::params_cache::{digest, ParamsCache, PP_ZK_VERSION};
+use arke_common::server::serve_connection;
+use arke_common::client::ProtocolError;
+use arke_common::tls::{Pins, TlsError};
+
+const PP_ZK_LEN: usize = 3 << 20;
+const CHUNK_LEN: u32 = 256 << 10;
+// The first connection through the proxy is cut after this many bytes from the server
+const CUT_AFTER: usize = 1 << 20;
+
+struct Server {
+    addr: SocketAddr,
+    pp_zk: Arc<Vec<u8>>,
+    digest: String,
+    // Offset of the first chunk asked for on every connection, in the order of the connections
+    first_offsets: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+// Serve random bytes as pp_zk on get_pp_zk_chunk
+async fn start_server() -> Server {
+    let pp_zk: Arc<Vec<u8>> = Arc::new((0..PP_ZK_LEN).map(|_| rand::random::<u8>()).collect());
+    let digest = digest(&pp_zk);
+    let first_offsets = Arc::new(Mutex::new(Vec::new()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Server { addr, pp_zk: Arc::clone(&pp_zk), digest: digest.clone(), first_offsets: Arc::clone(&first_offsets) };
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let connection = {
+                let mut first_offsets = first_offsets.lock().unwrap();
+                first_offsets.push(None);
+                first_offsets.len() - 1
+            };
+            let (pp_zk, digest, first_offsets) = (Arc::clone(&pp_zk), digest.clone(), Arc::clone(&first_offsets));
+            tokio::spawn(serve_connection(socket, move |request| {
+                let response = match request {
+                    Request::GetPpZkChunk { offset, length, compression: None } => {
+                        first_offsets.lock().unwrap()[connection].get_or_insert(offset);
+                        serve_chunk(&pp_zk, &digest, None, offset, length)
+                    },
+                    other => panic!("unexpected request {}", other.action()),
+                };
+                async move { response }
+            }));
+        }
+    });
+    server
+}
+
+// Forward connections to server, cutting the first cut_connections of them once CUT_AFTER bytes of responses went through
+async fn start_proxy(server: SocketAddr, cut_connections: usize) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        loop {
+            let (client, _) = listener.accept().await.unwrap();
+            let cut = connections.fetch_add(1, Ordering::SeqCst) < cut_connections;
+            tokio::spawn(async move {
+                let upstream = TcpStream::connect(server).await.unwrap();
+                let (mut client_read, mut client_write) = client.into_split();
+                let (mut upstream_read, mut upstream_write) = upstream.into_split();
+                let requests = tokio::spawn(async move { tokio::io::copy(&mut client_read, &mut upstream_write).await });
+                let mut budget = if cut { CUT_AFTER } else { usize::MAX };
+                let mut buffer = vec![0; 64 << 10];
+                loop {
+                    let read = match upstream_read.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => read,
+                    };
+                    let forwarded = read.min(budget);
+                    if client_write.write_all(&buffer[..forwarded]).await.is_err() {
+                        break;
+                    }
+                    budget -= forwarded;
+                    // Cut in the middle of a response, both streams are dropped
+                    if budget == 0 {
+                        break;
+                    }
+                }
+                requests.abort();
+            });
+        }
+    });
+    addr
+}
+
+fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("arke_download_test_{:016x}", rand::random::<u64>()))
+}
+
+// The servers in these tests have no certificate
+fn plaintext() -> Pins {
+    Pins::new(BTreeMap::new()).allow_plaintext()
+}
+
+fn options(retries: u32) -> DownloadOptions {
+    DownloadOptions { chunk_len: CHUNK_LEN, call_timeout: Duration::from_secs(10), retries }
+}
+
+// What the client does with a download that matched the published digest
+fn cache_and_reload(cache: &ParamsCache, server: &Server, downloaded: &[u8]) -> Vec<u8> {
+    cache.store("pp_zk", PP_ZK_VERSION, &server.digest, downloaded).unwrap();
+    cache.load("pp_zk", PP_ZK_VERSION, &server.digest).unwrap().expect("pp_zk was just cached")
+}
+
+#[tokio::test]
+async fn cut_connection_resumes_in_the_same_download() {
+    let server = start_server().await;
+    let proxy = start_proxy(server.addr, 1).await;
+    let dir = cache_dir();
+    let cache = ParamsCache::new(&dir);
+
+    let downloaded = download_pp_zk(&proxy.to_string(), &plaintext(), &cache, "pp_zk", &server.digest, None, options(2)).await.unwrap();
+    assert!(downloaded == *server.pp_zk);
+
+    // The second connection went on after the chunks that got through the first one
+    let first_offsets = server.first_offsets.lock().unwrap().clone();
+    assert_eq!(first_offsets.len(), 2);
+    assert_eq!(first_offsets[0], Some(0));
+    let resumed_at = first_offsets[1].unwrap();
+    assert!(resumed_at > 0 && resumed_at < PP_ZK_LEN as u64 && resumed_at % CHUNK_LEN as u64 == 0);
+
+    assert!(cache_and_reload(&cache, &server, &downloaded) == *server.pp_zk);
+    assert!(!dir.join("pp_zk.part").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn cut_connection_resumes_in_the_next_session() {
+    let server = start_server().await;
+    let proxy = start_proxy(server.addr, 1).await;
+    let dir = cache_dir();
+
+    // The first session gives up as soon as the connection is cut
+    let cache = ParamsCache::new(&dir);
+    let stalled = download_pp_zk(&proxy.to_string(), &plaintext(), &cache, "pp_zk", &server.digest, None, options(0)).await;
+    assert!(matches!(stalled, Err(DownloadError::Stalled { .. })));
+    let kept = std::fs::metadata(dir.join("pp_zk.part")).unwrap().len();
+    assert!(kept > 0 && kept < PP_ZK_LEN as u64);
+
+    // The next one, with a cache opened anew, only fetches the rest
+    let cache = ParamsCache::new(&dir);
+    let downloaded = download_pp_zk(&proxy.to_string(), &plaintext(), &cache, "pp_zk", &server.digest, None, options(0)).await.unwrap();
+    assert!(downloaded == *server.pp_zk);
+    assert_eq!(server.first_offsets.lock().unwrap()[1], Some(kept));
+
+    assert!(cache_and_reload(&cache, &server, &downloaded) == *server.pp_zk);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn partial_download_of_other_parameters_is_discarded() {
+    let server = start_server().await;
+    let dir = cache_dir();
+    let cache = ParamsCache::new(&dir);
+    // Left over from parameters published before, e.g. a setup that was regenerated since
+    cache.append_partial("pp_zk", &digest(b"old parameters"), None, 0, &[0; 1000]).unwrap();
+
+    let downloaded = download_pp_zk(&server.addr.to_string(), &plaintext(), &cache, "pp_zk", &server.digest, None, options(0)).await.unwrap();
+    assert!(downloaded == *server.pp_zk);
+    assert_eq!(server.first_offsets.lock().unwrap()[0], Some(0));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn unpinned_server_is_refused_without_plaintext() {
+    let server = start_server().await;
+    let dir = cache_dir();
+    let cache = ParamsCache::new(&dir);
+
+    let refused = download_pp_zk(&server.addr.to_string(), &Pins::new(BTreeMap::new()), &cache, "pp_zk", &server.digest, None, options(2)).await;
+    assert!(matches!(refused, Err(DownloadError::Protocol(ProtocolError::Unpinned(TlsError::Unpinned { .. })))));
+    // Refused before connecting, not after a retry
+    assert!(server.first_offsets.lock().unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,141 @@
+// ---------------------------------------
+// File: tls.rs
+// Date: 18 Oct 2026
+// Description: The client accepts a TLS server only by its pin, and does not wait forever for the handshake
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::config::TlsFiles;
+use arke_common::messages::{Request, Response};
+use arke_common::server::serve_connection;
+use arke_common::tls::{self, load_acceptor, Pin, Pins, TlsError};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+struct Server {
+    addr: String,
+    certificate: Vec<u8>,
+    subject_public_key_info: Vec<u8>,
+    // Connections accepted, whether or not their handshake went through
+    accepted: Arc<AtomicUsize>,
+    dir: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// A server with a new self-signed certificate, loaded from files the way the servers load theirs,
+// which answers check_uniqueness with id_available
+async fn start_server() -> Server {
+    let key_pair = rcgen::KeyPair::generate().unwrap();
+    let certificate = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap().self_signed(&key_pair).unwrap();
+    let dir = std::env::temp_dir().join(format!("arke_tls_test_{}_{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed)));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files = TlsFiles { cert: dir.join("cert.pem"), key: dir.join("key.pem") };
+    std::fs::write(&files.cert, certificate.pem()).unwrap();
+    std::fs::write(&files.key, key_pair.serialize_pem()).unwrap();
+    let acceptor = load_acceptor(&files).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&accepted);
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            counter.fetch_add(1, Ordering::Relaxed);
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(transport) = tls::accept(Some(&acceptor), socket).await {
+                    serve_connection(transport, |_| async { Ok(Response::IdAvailable) }).await;
+                }
+            });
+        }
+    });
+    Server {
+        addr,
+        certificate: certificate.der().to_vec(),
+        subject_public_key_info: key_pair.public_key_der(),
+        accepted,
+        dir,
+    }
+}
+
+fn pinned(addr: &str, pin: Pin) -> Pins {
+    Pins::new(BTreeMap::from([(addr.to_string(), pin)]))
+}
+
+async fn check_uniqueness(connection: &mut Connection) -> Response {
+    connection.call(Request::CheckUniqueness { id_string: "abcdefgh".to_string() }).await.unwrap()
+}
+
+#[tokio::test]
+async fn server_matching_the_certificate_pin_is_accepted() {
+    let server = start_server().await;
+    let pins = pinned(&server.addr, Pin::of_certificate(&server.certificate));
+
+    let mut connection = Connection::connect_pinned(&server.addr, &pins).await.unwrap();
+    assert_eq!(check_uniqueness(&mut connection).await, Response::IdAvailable);
+}
+
+#[tokio::test]
+async fn server_matching_the_public_key_pin_is_accepted() {
+    let server = start_server().await;
+    // Parsed the way the client reads a pin from its profile
+    let pin = Pin::parse(&Pin::of_public_key(&server.subject_public_key_info).to_string()).unwrap();
+
+    let mut connection = Connection::connect_pinned(&server.addr, &pinned(&server.addr, pin)).await.unwrap();
+    assert_eq!(check_uniqueness(&mut connection).await, Response::IdAvailable);
+}
+
+#[tokio::test]
+async fn server_not_matching_the_pin_is_refused() {
+    let server = start_server().await;
+    let other = start_server().await;
+
+    for pin in [Pin::of_certificate(&other.certificate), Pin::of_public_key(&other.subject_public_key_info)] {
+        let refused = Connection::connect_pinned(&server.addr, &pinned(&server.addr, pin)).await;
+        assert!(matches!(refused, Err(ProtocolError::Tls { .. })));
+    }
+}
+
+#[tokio::test]
+async fn unpinned_server_is_refused_before_connecting() {
+    let server = start_server().await;
+    // A pin for another endpoint does not count
+    let pins = pinned("127.0.0.1:1", Pin::of_certificate(&server.certificate));
+
+    let refused = Connection::connect_pinned(&server.addr, &pins).await;
+    assert!(matches!(refused, Err(ProtocolError::Unpinned(TlsError::Unpinned { .. }))));
+    assert_eq!(server.accepted.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn silent_server_does_not_hold_the_handshake_forever() {
+    // Accepts the connection and never says anything
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        loop {
+            sockets.push(listener.accept().await.unwrap());
+        }
+    });
+
+    let started = Instant::now();
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let handshake = tls::connect(&addr.to_string(), &Pin::of_certificate(b"any certificate"), socket).await;
+    assert_eq!(handshake.err().map(|e| e.kind()), Some(std::io::ErrorKind::TimedOut));
+    assert!(started.elapsed() < Duration::from_secs(30));
+}
@@ -0,0 +1,242 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Runtime configuration of the client
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use clap::Parser;
+use serde::Deserialize;
+use web3::types::Address;
+use arke_common::config::{load_file, prepare_data_dir, validate_endpoint, ConfigError};
+use arke_common::tls::{Pin, Pins};
+use crate::key_issuers::THRESHOLD;
+use crate::profile::{create_profile, profile_dir, read_active_profile, validate_profile_name, DEFAULT_PROFILE};
+
+const DEFAULT_DATABASE_SERVER: &str = "127.0.0.1:8080";
+const DEFAULT_KEY_ISSUING_AUTHORITY: &str = "127.0.0.1:8081";
+const DEFAULT_REGISTRATION_AUTHORITY: &str = "127.0.0.1:8082";
+const DEFAULT_RPC_URL: &str = "ws://127.0.0.1:9545";
+// Address of the key-value store contract deployed on ganache
+const DEFAULT_CONTRACT_ADDRESS: &str = "0xc23EDB04DebB123CDB1ac96a28eA18E8403a34d6";
+// Chain id of a ganache workspace
+const DEFAULT_CHAIN_ID: u64 = 1337;
+const DEFAULT_DATA_DIR: &str = "src";
+// VerifyID checks a zk-SNARK proof, which takes a while
+const DEFAULT_ISSUER_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_ISSUER_RETRIES: u32 = 2;
+// An extraction may wait in the queue of a busy key issuer, but not for ever
+const DEFAULT_EXTRACT_TIMEOUT_SECS: u64 = 600;
+
+/// Command line flags, each of them can also be set through its environment variable
+#[derive(Parser, Debug)]
+#[command(about = "Arke command line chatting application")]
+struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ARKE_CLIENT_CONFIG")]
+    config: Option<PathBuf>,
+    /// host:port of the database server
+    #[arg(long, env = "ARKE_CLIENT_DATABASE_SERVER")]
+    database_server: Option<String>,
+    /// host:port of the key-issuing authority
+    #[arg(long, env = "ARKE_CLIENT_KEY_ISSUING_AUTHORITY")]
+    key_issuing_authority: Option<String>,
+    /// host:port of a key issuer node, repeat the flag (or separate with commas) for every node.
+    /// When given, the keys are collected from the nodes instead of the key-issuing authority
+    #[arg(long = "key-issuer", env = "ARKE_CLIENT_KEY_ISSUERS", value_delimiter = ',')]
+    key_issuers: Vec<String>,
+    /// Seconds to wait for the answer of a key issuer
+    #[arg(long, env = "ARKE_CLIENT_ISSUER_TIMEOUT")]
+    issuer_timeout: Option<u64>,
+    /// How often to retry a key issuer that cannot be reached or does not answer in time
+    #[arg(long, env = "ARKE_CLIENT_ISSUER_RETRIES")]
+    issuer_retries: Option<u32>,
+    /// Seconds to wait for the partial user key of a key issuer in all, queueing, polling and retries included
+    #[arg(long, env = "ARKE_CLIENT_EXTRACT_TIMEOUT")]
+    extract_timeout: Option<u64>,
+    /// Ask the key issuers for pp_zk compressed with zstd
+    #[arg(long, env = "ARKE_CLIENT_COMPRESS_PP_ZK")]
+    compress_pp_zk: bool,
+    /// host:port of the registration authority
+    #[arg(long, env = "ARKE_CLIENT_REGISTRATION_AUTHORITY")]
+    registration_authority: Option<String>,
+    /// host:port=pin of a server to connect to over TLS, the pin being cert-sha256:<hex> or spki-sha256:<hex>
+    /// as printed by arke-dev-cert. Repeat the flag (or separate with commas) for every server
+    #[arg(long = "pin", env = "ARKE_CLIENT_PINS", value_delimiter = ',')]
+    pins: Vec<String>,
+    /// Connect over plain TCP to servers without a pin instead of refusing them, for servers started without TLS
+    #[arg(long, env = "ARKE_CLIENT_INSECURE_PLAINTEXT")]
+    insecure_plaintext: bool,
+    /// WebSocket URL of the Ethereum node
+    #[arg(long, env = "ARKE_CLIENT_RPC_URL")]
+    rpc_url: Option<String>,
+    /// Address of the deployed key-value store contract
+    #[arg(long, env = "ARKE_CLIENT_CONTRACT_ADDRESS")]
+    contract_address: Option<String>,
+    /// Chain id the Ethereum node must report
+    #[arg(long, env = "ARKE_CLIENT_CHAIN_ID")]
+    chain_id: Option<u64>,
+    /// Directory holding the profiles
+    #[arg(long, env = "ARKE_CLIENT_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Profile to use, created if it does not exist yet
+    #[arg(long, env = "ARKE_CLIENT_PROFILE")]
+    profile: Option<String>,
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    database_server: Option<String>,
+    key_issuing_authority: Option<String>,
+    key_issuers: Option<Vec<String>>,
+    issuer_timeout: Option<u64>,
+    issuer_retries: Option<u32>,
+    extract_timeout: Option<u64>,
+    compress_pp_zk: Option<bool>,
+    registration_authority: Option<String>,
+    /// Pins by host:port
+    pins: Option<BTreeMap<String, String>>,
+    insecure_plaintext: Option<bool>,
+    rpc_url: Option<String>,
+    contract_address: Option<String>,
+    chain_id: Option<u64>,
+    data_dir: Option<PathBuf>,
+    profile: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub database_server: String,
+    pub key_issuing_authority: String,
+    /// Separate key issuer nodes, empty when a single key-issuing authority holds every share
+    pub key_issuers: Vec<String>,
+    pub issuer_timeout: Duration,
+    pub issuer_retries: u32,
+    /// Deadline for the partial user key of one key issuer
+    pub extract_timeout: Duration,
+    pub compress_pp_zk: bool,
+    pub registration_authority: String,
+    /// Servers connected to over TLS, the others are refused unless plain TCP is allowed
+    pub pins: Pins,
+    pub rpc_url: String,
+    pub contract_address: Address,
+    pub chain_id: u64,
+    pub data_dir: PathBuf,
+    /// Profile whose state files are used, see profile.rs
+    pub profile: String,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file: FileConfig = load_file(cli.config.as_deref())?;
+
+        let database_server = cli.database_server.or(file.database_server)
+            .unwrap_or_else(|| DEFAULT_DATABASE_SERVER.to_string());
+        let key_issuing_authority = cli.key_issuing_authority.or(file.key_issuing_authority)
+            .unwrap_or_else(|| DEFAULT_KEY_ISSUING_AUTHORITY.to_string());
+        let key_issuers = if cli.key_issuers.is_empty() { file.key_issuers.unwrap_or_default() } else { cli.key_issuers };
+        let issuer_timeout = cli.issuer_timeout.or(file.issuer_timeout).unwrap_or(DEFAULT_ISSUER_TIMEOUT_SECS);
+        let issuer_retries = cli.issuer_retries.or(file.issuer_retries).unwrap_or(DEFAULT_ISSUER_RETRIES);
+        let extract_timeout = cli.extract_timeout.or(file.extract_timeout).unwrap_or(DEFAULT_EXTRACT_TIMEOUT_SECS);
+        let registration_authority = cli.registration_authority.or(file.registration_authority)
+            .unwrap_or_else(|| DEFAULT_REGISTRATION_AUTHORITY.to_string());
+        // A pin on the command line replaces the one for the same server in the file
+        let mut pins = file.pins.unwrap_or_default();
+        for pin in &cli.pins {
+            let (endpoint, pin) = pin.split_once('=')
+                .ok_or_else(|| ConfigError::invalid("pins", pin.clone(), "expected host:port=pin"))?;
+            pins.insert(endpoint.to_string(), pin.to_string());
+        }
+        let rpc_url = cli.rpc_url.or(file.rpc_url).unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+        let contract_address = cli.contract_address.or(file.contract_address)
+            .unwrap_or_else(|| DEFAULT_CONTRACT_ADDRESS.to_string());
+        let chain_id = cli.chain_id.or(file.chain_id).unwrap_or(DEFAULT_CHAIN_ID);
+        let data_dir = cli.data_dir.or(file.data_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+        let data_dir = prepare_data_dir("data_dir", data_dir)?;
+        // Without an explicit profile, continue with the profile selected last
+        let profile = cli.profile.or(file.profile)
+            .or_else(|| read_active_profile(&data_dir))
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        validate_profile_name(&profile).map_err(|reason| ConfigError::invalid("profile", profile.clone(), reason))?;
+        create_profile(&data_dir, &profile)
+            .map_err(|e| ConfigError::invalid("profile", profile.clone(), e.to_string()))?;
+
+        // The key-value store is only reachable over WebSocket, subscriptions need it
+        if !rpc_url.starts_with("ws://") && !rpc_url.starts_with("wss://") {
+            return Err(ConfigError::invalid("rpc_url", rpc_url, "expected a ws:// or wss:// URL"));
+        }
+        // Combine needs THRESHOLD + 1 keys, so fewer nodes could never give a user key
+        if !key_issuers.is_empty() && key_issuers.len() < THRESHOLD + 1 {
+            return Err(ConfigError::invalid("key_issuers", key_issuers.join(","),
+                format!("at least {} key issuers are needed", THRESHOLD + 1)));
+        }
+        if issuer_timeout == 0 {
+            return Err(ConfigError::invalid("issuer_timeout", "0", "must be at least 1 second"));
+        }
+        // Otherwise a single call that takes issuer_timeout would already run out of time
+        if extract_timeout < issuer_timeout {
+            return Err(ConfigError::invalid("extract_timeout", extract_timeout.to_string(),
+                format!("must be at least issuer_timeout ({} seconds)", issuer_timeout)));
+        }
+        let contract_address = Address::from_str(&contract_address)
+            .map_err(|e| ConfigError::invalid("contract_address", contract_address.clone(), e.to_string()))?;
+
+        Ok(Config {
+            database_server: validate_endpoint("database_server", &database_server)?,
+            key_issuing_authority: validate_endpoint("key_issuing_authority", &key_issuing_authority)?,
+            key_issuers: key_issuers.iter()
+                .map(|key_issuer| validate_endpoint("key_issuers", key_issuer))
+                .collect::<Result<_, _>>()?,
+            issuer_timeout: Duration::from_secs(issuer_timeout),
+            issuer_retries,
+            extract_timeout: Duration::from_secs(extract_timeout),
+            compress_pp_zk: cli.compress_pp_zk || file.compress_pp_zk.unwrap_or(false),
+            registration_authority: validate_endpoint("registration_authority", &registration_authority)?,
+            pins: parse_pins(&pins, cli.insecure_plaintext || file.insecure_plaintext.unwrap_or(false))?,
+            rpc_url,
+            contract_address,
+            chain_id,
+            data_dir,
+            profile,
+        })
+    }
+
+    /// Public parameters downloaded from the key issuers, shared by all profiles
+    pub fn params_cache_dir(&self) -> PathBuf {
+        self.data_dir.join("cache")
+    }
+
+    pub fn profile_dir(&self) -> PathBuf {
+        profile_dir(&self.data_dir, &self.profile)
+    }
+
+    pub fn my_info_path(&self) -> PathBuf {
+        self.profile_dir().join("my_info.bin")
+    }
+
+    pub fn friends_path(&self) -> PathBuf {
+        self.profile_dir().join("friends.json")
+    }
+
+    pub fn groups_path(&self) -> PathBuf {
+        self.profile_dir().join("groups.json")
+    }
+}
+
+fn parse_pins(pins: &BTreeMap<String, String>, insecure_plaintext: bool) -> Result<Pins, ConfigError> {
+    let mut parsed = BTreeMap::new();
+    for (endpoint, pin) in pins {
+        let endpoint = validate_endpoint("pins", endpoint)?;
+        let pin = Pin::parse(pin)
+            .ok_or_else(|| ConfigError::invalid("pins", pin.clone(), "expected cert-sha256:<hex> or spki-sha256:<hex>"))?;
+        parsed.insert(endpoint, pin);
+    }
+    let pins = Pins::new(parsed);
+    Ok(if insecure_plaintext { pins.allow_plaintext() } else { pins })
+}
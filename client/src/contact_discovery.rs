@@ -13,11 +13,13 @@ use ark_bw6_761::BW6_761;
 use ark_bls12_377::{Bls12_377, Parameters};
 use ark_ec::bls12::Bls12;
 use crate::discovery_info::DiscoveryInfo;
+use crate::config::Config;
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::messages::{AccountStatus, Request, Response};
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize, SerializationError};
-use ark_std::io::{Write, Read, Cursor};
+use ark_std::io::{Read, Cursor};
 use serde::{Serialize, Deserialize};
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncReadExt;
+use arke_common::durable_file::{read_json_list, write_json_list};
 use std::fs::File;
 type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
 // Maximum number of dishonest pariticipants that the system can tolerate
@@ -47,26 +49,15 @@ struct User {
     id_string: String,
 }
 
-pub async fn contactDiscovery() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn contactDiscovery(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let friends_path = config.friends_path();
     let want_contact_discovery_id_string = dialoguer::Input::<String>::new()
         .with_prompt("Which contact do you want to discover?")
         .interact()
         .unwrap();
 
-    // Read friends.json
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/friends.json")
-        .await?;
-    // Derialize friends.json to read friend objects 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).await?;
-    // Convert each friend to a string representation and collect them into a vector
-    let friends: Vec<Friend> = match serde_json::from_str(&contents) {
-        Ok(friends) => friends,
-        Err(_) => Vec::new(),
-    };
+    // Read friends.json, a corrupt file is an error rather than an empty friend list
+    let friends: Vec<Friend> = read_json_list(&friends_path)?;
     
     // Check whether the target user is in user's friend list
     let friend = friends.iter().find(|&c| c.id_string == want_contact_discovery_id_string);
@@ -80,9 +71,12 @@ pub async fn contactDiscovery() -> Result<(), Box<dyn std::error::Error>> {
         None => {},
     };
 
+    // Only discover accounts that are in use
+    check_discoverable(config, &want_contact_discovery_id_string).await?;
+
 
     // Read my_info.bin
-    let mut my_info_file = File::open("src/my_info.bin")?;
+    let mut my_info_file = File::open(config.my_info_path())?;
     let mut deserialized: Vec<u8> = Vec::new();
     my_info_file.read_to_end(&mut deserialized)?;
     // Derialize my_info.bin to read my_info object
@@ -120,25 +114,24 @@ pub async fn contactDiscovery() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Read then write to friends.json
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/friends.json")
-        .await?;
-    // Derialize friends.json to read friend objects 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).await?;
-    // Convert each friend to a string representation and collect them into a vector
-    let mut friends: Vec<Friend> = match serde_json::from_str(&contents) {
-        Ok(friends) => friends,
-        Err(_) => Vec::new(),
-    };
+    let mut friends: Vec<Friend> = read_json_list(&friends_path)?;
     // Append the new friend to the vector
     friends.push(new_friend);
-    // Write friends back to the file
-    let contacts_json = serde_json::to_string(&friends)?; 
-    let mut file = File::create("src/friends.json")?;
-    file.write_all(contacts_json.as_bytes())?;
+    // Write friends back to the file, atomically so that a crash cannot lose the friend list
+    write_json_list(&friends_path, &friends)?;
 
     Ok(())
+}
+
+/// Ask the database server whether the target user has an active account
+async fn check_discoverable(config: &Config, id_string: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut db_connection = Connection::connect_pinned(&config.database_server, &config.pins).await?;
+    match db_connection.call(Request::GetUserStatus { id_string: id_string.to_string() }).await? {
+        Response::UserStatus { status: AccountStatus::Active } => Ok(()),
+        Response::UserStatus { status: AccountStatus::Suspended } => Err(format!("{} is suspended", id_string).into()),
+        Response::UserStatus { status: AccountStatus::Deleted } | Response::UserNotFound => {
+            Err(format!("there is no user with ID {}", id_string).into())
+        },
+        other => Err(Box::new(ProtocolError::unexpected("get_user_status", other))),
+    }
 } 
\ No newline at end of file
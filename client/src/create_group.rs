@@ -13,12 +13,12 @@ use ark_bw6_761::BW6_761;
 use ark_bls12_377::{Bls12_377, Parameters};
 use ark_ec::bls12::Bls12;
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize, SerializationError};
-use ark_std::io::{Write, Read, Cursor};
+use ark_std::io::{Read, Cursor};
 use serde::{Serialize, Deserialize};
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncReadExt;
+use arke_common::durable_file::{read_json_list, write_json_list};
 use std::fs::File;
 use crate::discovery_info::DiscoveryInfo;
+use crate::config::Config;
 type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
 // Maximum number of dishonest participants that the system can tolerate
 const THRESHOLD: usize = 3;
@@ -47,26 +47,15 @@ struct User {
     id_string: String,
 }
 
-pub async fn createGroup() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn createGroup(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let groups_path = config.groups_path();
     let want_create_group_id_string = dialoguer::Input::<String>::new()
         .with_prompt("What is the group name?")
         .interact()
         .unwrap();
 
-    // Read groups.json
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/groups.json")
-        .await?;
-    // Derialize groups.json to read group objects 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).await?;
-    // Convert each group to a string representation and collect them into a vector
-    let groups: Vec<Group> = match serde_json::from_str(&contents) {
-        Ok(groups) => groups,
-        Err(_) => Vec::new(),
-    };
+    // Read groups.json, a corrupt file is an error rather than an empty group list
+    let groups: Vec<Group> = read_json_list(&groups_path)?;
     
     // Check whether the group the user want to create has already been created
     let group = groups.iter().find(|&c| c.id_string == want_create_group_id_string);
@@ -95,7 +84,7 @@ pub async fn createGroup() -> Result<(), Box<dyn std::error::Error>> {
 
 
     // Read my_info.bin
-    let mut my_info_file = File::open("src/my_info.bin")?;
+    let mut my_info_file = File::open(config.my_info_path())?;
     let mut deserialized: Vec<u8> = Vec::new();
     my_info_file.read_to_end(&mut deserialized)?;
     // Derialize my_info.bin to read my_info object
@@ -133,25 +122,11 @@ pub async fn createGroup() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Read then write to groups.json
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/groups.json")
-        .await?;
-    // Derialize groups.json to read group objects 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).await?;
-    // Convert each group to a string representation and collect them into a vector
-    let mut groups: Vec<Group> = match serde_json::from_str(&contents) {
-        Ok(groups) => groups,
-        Err(_) => Vec::new(),
-    };
+    let mut groups: Vec<Group> = read_json_list(&groups_path)?;
     // Append the new group to the vector
     groups.push(new_group);
-    // Write groups back to the file
-    let contacts_json = serde_json::to_string(&groups)?; 
-    let mut file = File::create("src/groups.json")?;
-    file.write_all(contacts_json.as_bytes())?;
+    // Write groups back to the file, atomically so that a crash cannot lose the group list
+    write_json_list(&groups_path, &groups)?;
 
     Ok(())
 } 
\ No newline at end of file
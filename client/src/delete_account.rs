@@ -0,0 +1,94 @@
+// ---------------------------------------
+// File: delete_account.rs
+// Date: 18 Oct 2026
+// Description: Delete the account of the user (client-side)
+// ---------------------------------------
+#![allow(non_snake_case)]
+
+use std::fs::File;
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize, SerializationError};
+use ark_std::io::{Read, Cursor};
+use ark_ec::bls12::Bls12;
+use ark_bls12_377::Parameters;
+use arke_core::UserSecretKey;
+use crate::config::Config;
+use crate::id_verification::call_verified;
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::durable_file::{corrupt, discard_backup, write_atomic_private};
+use arke_common::messages::{validate_id_string, Request, Response};
+
+#[derive(CanonicalSerialize, CanonicalDeserialize, Debug)]
+struct MyInfo {
+    id_string: String,
+    eth_addr: String,
+    sk: UserSecretKey<Bls12<Parameters>>,
+}
+
+pub async fn deleteAccount(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    // Read my_info.bin
+    let mut my_info_file = File::open(config.my_info_path())?;
+    let mut deserialized: Vec<u8> = Vec::new();
+    my_info_file.read_to_end(&mut deserialized)?;
+
+    // If signed up, delete the account of this profile.
+    // If not, my_info.bin may have been lost, ask which ID to delete
+    let signed_up = !deserialized.is_empty();
+    let id_string = if signed_up {
+        let mut cursor = Cursor::new(&deserialized);
+        MyInfo::deserialize(&mut cursor).map_err(|e| corrupt(&config.my_info_path(), format!("{:?}", e)))?.id_string
+    } else {
+        let id_string = dialoguer::Input::<String>::new()
+            .with_prompt("This profile has not signed up. Which ID do you want to delete")
+            .interact()
+            .unwrap();
+        validate_id_string(&id_string).map_err(|e| format!("invalid ID, {}", e.message))?;
+        id_string
+    };
+
+    let confirmed = dialoguer::Confirm::new()
+        .with_prompt(format!("Delete the account of {}? Nobody can sign up for this ID until the reuse cooldown of the database server has passed, then anybody can", id_string))
+        .default(false)
+        .interact()
+        .unwrap();
+    if !confirmed {
+        return Ok(());
+    }
+
+    // ==================================
+    // Contact the registration authority
+    // ==================================
+    println!("About to connect to the registration authority...");
+    let mut r_authority_connection = Connection::connect_pinned(&config.registration_authority, &config.pins).await?;
+    println!("Successfully connected to the registration authority.");
+    // Send the request for authorize_deletion, with the code sent to the contact the ID is bound to.
+    // The token proves to the database server that the ID is ours
+    let response = call_verified(&mut r_authority_connection, &id_string, None, |verification| {
+        Request::AuthorizeDeletion { id_string: id_string.clone(), verification: Some(verification) }
+    }).await?;
+    let (deletion_token, issued_at) = match response {
+        Response::DeletionToken { deletion_token, issued_at } => (deletion_token, issued_at),
+        other => return Err(Box::new(ProtocolError::unexpected("authorize_deletion", other))),
+    };
+    drop(r_authority_connection);
+
+    // ============================
+    // Contact the database server
+    // ============================
+    println!("About to connect to the database server for deleting the account...");
+    let mut db_connection = Connection::connect_pinned(&config.database_server, &config.pins).await?;
+    println!("Successfully connected to the database server for deleting the account.");
+    match db_connection.call(Request::DeleteUser { id_string: id_string.clone(), deletion_token, issued_at }).await? {
+        Response::UserDeleted => println!("✓ Account of {} deleted", id_string),
+        Response::UserNotFound => return Err(format!("there is no active account with ID {}", id_string).into()),
+        other => return Err(Box::new(ProtocolError::unexpected("delete_user", other))),
+    }
+    drop(db_connection);
+
+    // Empty my_info.bin, so this profile can sign up again, and do not leave the secret key of the deleted account in my_info.bin.bak
+    if signed_up {
+        write_atomic_private(&config.my_info_path(), &[])?;
+        discard_backup(&config.my_info_path())?;
+    }
+
+    Ok(())
+}
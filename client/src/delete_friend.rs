@@ -4,11 +4,11 @@ use web3::types::H160;
 use arke_core::{StoreKey, UserSecretKey};
 use dialoguer::{theme::ColorfulTheme, FuzzySelect};
 use serde::{Serialize, Deserialize};
-use std::fs::{OpenOptions, File};
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize, SerializationError};
-use ark_std::io::{Write, Read};
+use arke_common::durable_file::{read_json_list, write_json_list};
 use ark_ec::bls12::Bls12;
 use ark_bls12_377::Parameters;
+use crate::config::Config;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Friend {
@@ -27,23 +27,16 @@ struct MyInfo {
     sk: UserSecretKey<Bls12<Parameters>>,
 }
 
-pub async fn deleteFriend() -> Result<(), Box<dyn std::error::Error>>{
-    // Read friends.json
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/friends.json")
-        .unwrap();
-    // Check whether friends.json is empty or not, i.e. whether there are friends or not
-    let metadata = file.metadata().unwrap();
-    // If empty, return to the main menu
-    if metadata.len() == 0 {
+pub async fn deleteFriend(config: &Config) -> Result<(), Box<dyn std::error::Error>>{
+    let friends_path = config.friends_path();
+    // Read friends.json, a corrupt file is an error rather than an empty friend list
+    let friends: Vec<Friend> = read_json_list(&friends_path)?;
+    // If there are no friends, return to the main menu
+    if friends.is_empty() {
         println!("No friends");
         return Ok(());
     }
 
-    // Derialize friends.json to read friend objects 
-    let friends: Vec<Friend> = serde_json::from_reader(file).unwrap();
     // Convert each friend to a string representation and collect them into a vector
     let mut FriendsMenu: Vec<String> = friends.iter()
         .map(|contact| { format!("ID string: {}", contact.id_string)}).collect();
@@ -62,23 +55,11 @@ pub async fn deleteFriend() -> Result<(), Box<dyn std::error::Error>>{
             index if index < friends.len() => {
                 // Delete in the saved friends
                 // Read then write to friends.json
-                let mut file = OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .open("src/friends.json").unwrap();
-                // Derialize friends.json to read friend objects 
-                let mut contents = String::new();
-                file.read_to_string(&mut contents).unwrap();
-                // Convert each friend to a string representation and collect them into a vector
-                let mut friends: Vec<Friend> = match serde_json::from_str(&contents) {
-                    Ok(friends) => friends,
-                    Err(_) => Vec::new(),
-                };
+                let mut friends: Vec<Friend> = read_json_list(&friends_path)?;
                 // remove the contact from the vector
                 friends.remove(index);
-                // Write friends back to the file
-                let file = File::create("src/friends.json").unwrap();
-                serde_json::to_writer(&file, &friends).unwrap();
+                // Write friends back to the file, atomically so that a crash cannot lose the friend list
+                write_json_list(&friends_path, &friends)?;
 
                 // Return to the main menu
                 return Ok(());
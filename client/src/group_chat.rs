@@ -12,7 +12,8 @@
 use web3::types::{Address, H160, U256, FilterBuilder, Log};
 use web3::futures::StreamExt;
 use std::str::FromStr;
-use crate::key_value_store_frontend::KeyValueStore;
+use crate::key_value_store_frontend::{self, KeyValueStore};
+use crate::config::Config;
 use rand::{distributions::Alphanumeric, Rng, thread_rng};
 use arke_core::{UnlinkableHandshake, UserSecretKey, StoreKey};
 use ark_ec::bls12::Bls12;
@@ -22,6 +23,7 @@ use ark_std::io::{Write, Read, Cursor};
 use dialoguer::{theme::ColorfulTheme, FuzzySelect};
 use serde::{Serialize, Deserialize};
 use std::fs::{OpenOptions, File};
+use arke_common::durable_file::read_json_list;
 use tokio::sync::mpsc;
 use std::sync::Arc;
 use tokio::net::TcpStream;
@@ -37,8 +39,6 @@ use std::io::{Seek, SeekFrom};
 use tokio::task::JoinHandle;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-const CONTRACT_ADDR: &str = "0xc23EDB04DebB123CDB1ac96a28eA18E8403a34d6";
-
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct Group {
     id_string: String,
@@ -74,34 +74,21 @@ fn print_chatbox(message: &str) {
     println!("{}", time_str);
 }
 
-pub async fn groupChat() -> Result<(), Box<dyn std::error::Error>>{   
+pub async fn groupChat(config: &Config) -> Result<(), Box<dyn std::error::Error>>{   
     // Setup the contract and an interface to access it's functionality 
-    let transport = web3::transports::WebSocket::new("ws://127.0.0.1:9545").await?;
-    let web3 = web3::Web3::new(transport);
-    let web3 = Arc::new(web3);
-    let Store = KeyValueStore::new(
-        &web3,
-        // Update to match the deployed contract address on ganache
-        CONTRACT_ADDR.to_string(),
-        ).await;     
-    let Store = Arc::new(Store);
-
-    // Read groups.json
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/groups.json")
-        .unwrap();
-    // Check whether groups.json is empty or not, i.e. whether there are groups or not
-    let metadata = file.metadata().unwrap();
-    // If empty, return to the main menu
-    if metadata.len() == 0 {
+    let (web3, Store) = key_value_store_frontend::connect(config).await?;
+    let contract_address = config.contract_address;
+    let my_info_path = config.my_info_path();
+    let groups_path = config.groups_path();
+
+    // Read groups.json, a corrupt file is an error rather than an empty group list
+    let groups: Vec<Group> = read_json_list(&groups_path)?;
+    // If there are no groups, return to the main menu
+    if groups.is_empty() {
         println!("No groups");
         return Ok(());
     }
 
-    // Derialize groups.json to read group objects 
-    let groups: Vec<Group> = serde_json::from_reader(file).unwrap();
     // Convert each group to a string representation and collect them into a vector
     let mut GroupsMenu: Vec<String> = groups.iter()
         .map(|group| { format!("Group ID string: {}", group.id_string)}).collect();
@@ -170,9 +157,10 @@ pub async fn groupChat() -> Result<(), Box<dyn std::error::Error>>{
                         let Store_clone1 = Arc::clone(&Store);
                         let web3_clone = Arc::clone(&web3);
                         let should_terminate_clone2 = Arc::clone(&should_terminate);
+                        let my_info_path_clone = my_info_path.clone();
                         handle2 = Some(tokio::spawn(async move {
                             let filter = FilterBuilder::default()
-                                .address(vec![CONTRACT_ADDR.parse().unwrap()])
+                                .address(vec![contract_address])
                                 .build();
                             loop {
                                 if should_terminate_clone2.load(Ordering::Relaxed) {
@@ -180,7 +168,7 @@ pub async fn groupChat() -> Result<(), Box<dyn std::error::Error>>{
                                     break;
                                 }
                                 let filter_clone = filter.clone();
-                                let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                                let mut my_info_file = File::open(&my_info_path_clone).unwrap();
                                 let mut deserialized: Vec<u8> = Vec::new();
                                 my_info_file.read_to_end(&mut deserialized).unwrap();
                                 let mut cursor = Cursor::new(&deserialized);
@@ -236,7 +224,7 @@ pub async fn groupChat() -> Result<(), Box<dyn std::error::Error>>{
                                 //println!("dropped handles");
                             }
                             else {
-                                let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                                let mut my_info_file = File::open(&my_info_path).unwrap();
                                 let mut deserialized: Vec<u8> = Vec::new();
                                 my_info_file.read_to_end(&mut deserialized).unwrap();
                                 let mut cursor = Cursor::new(&deserialized);
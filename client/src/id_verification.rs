@@ -0,0 +1,44 @@
+// ---------------------------------------
+// File: id_verification.rs
+// Date: 18 Oct 2026
+// Description: Prove to the registration authority that the ID is ours (client-side)
+// ---------------------------------------
+
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::error::ErrorCode;
+use arke_common::messages::{Request, Response, Verification};
+
+/// Have the registration authority send a one-time code for the ID, read it from the user and send
+/// the request made with it. contact is only used by an ID that is not bound to a contact yet.
+/// A wrong code is asked for again as long as the registration authority allows more attempts.
+/// id_taken is returned as is, no code is sent for an ID somebody else already has.
+pub async fn call_verified<F>(connection: &mut Connection, id_string: &str, contact: Option<String>, request: F)
+    -> Result<Response, ProtocolError>
+where
+    F: Fn(Verification) -> Request,
+{
+    let start = Request::StartVerification { id_string: id_string.to_string(), contact };
+    let (challenge_id, attempts) = match connection.call(start).await? {
+        Response::VerificationStarted { challenge_id, sent_to, expires_in_secs, attempts } => {
+            println!("✉ A code was sent to {}, it expires in {} minutes", sent_to, expires_in_secs.div_ceil(60));
+            (challenge_id, attempts)
+        },
+        Response::IdTaken => return Ok(Response::IdTaken),
+        other => return Err(ProtocolError::unexpected("start_verification", other)),
+    };
+
+    let mut attempt = 1;
+    loop {
+        let code = dialoguer::Input::<String>::new()
+            .with_prompt("Enter the code")
+            .interact()
+            .unwrap();
+        match connection.call(request(Verification { challenge_id: challenge_id.clone(), code })).await {
+            Err(ProtocolError::Server { code: ErrorCode::Unverified, message }) if attempt < attempts => {
+                println!("X {}", message);
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
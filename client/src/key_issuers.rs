@@ -0,0 +1,506 @@
+// ---------------------------------------
+// File: key_issuers.rs
+// Date: 18 Oct 2026
+// Description: Get pp_zk, the issuer public keys and the partial user keys, either from the
+//              key-issuing authority or from separate key issuer nodes
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::io::Cursor;
+use crate::config::Config;
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::error::ErrorCode;
+use arke_common::compression::decompress;
+use arke_common::download::{download_pp_zk, DownloadError, DownloadOptions, CHUNK_LEN};
+use arke_common::frame::MAX_FRAME_LEN;
+use arke_common::messages::{Blob, Compression, Request, Response};
+use arke_common::params_cache::{digest, ParamsCache};
+use arke_common::tls::Pins;
+use arke_core::{BlindIDCircuitParameters, BlindPartialSecretKey, IssuerPublicKey, PartialSecretKey};
+use ark_ec::bls12::Bls12;
+use ark_ec::bw6::BW6;
+use ark_bls12_377::{Bls12_377, Parameters};
+use ark_bw6_761::Parameters as Parameters761;
+/// Maximum number of dishonest participants that the system can tolerate,
+/// so Combine needs the keys of THRESHOLD + 1 issuers
+pub const THRESHOLD: usize = 3;
+// Wait before the first retry, doubled for every further one
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+// Wait between two get_job_status calls
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Name of pp_zk in the parameter cache
+const PP_ZK_CACHE_NAME: &str = "pp_zk";
+
+// Why an issuer did not give a key
+enum Failure {
+    /// Could not connect, the connection broke or no answer in time, even after the retries
+    Unreachable(String),
+    /// The issuer answered with an error or with something else than a key
+    Refused(ProtocolError),
+}
+
+/// Get pp_zk from the key-issuing authority, or from the first key issuer node that answers.
+/// pp_zk is only downloaded when the cached copy is missing or its digest differs from the published one.
+pub async fn get_pp_zk(config: &Config) -> Result<BlindIDCircuitParameters<BW6<Parameters761>>, Box<dyn std::error::Error>> {
+    let cache = ParamsCache::new(&config.params_cache_dir());
+    let compression = if config.compress_pp_zk { Some(Compression::Zstd) } else { None };
+    // Every chunk gets the timeout and the retries of a call, a download cut on one key issuer goes on
+    // from the next one, which serves the same pp_zk
+    let options = DownloadOptions { chunk_len: CHUNK_LEN, call_timeout: config.issuer_timeout, retries: config.issuer_retries };
+    let mut last_error: Option<Box<dyn std::error::Error>> = None;
+    for endpoint in endpoints(config) {
+        match pp_zk_from(endpoint, &config.pins, &cache, compression, options).await {
+            Ok(pp_zk) => return Ok(pp_zk),
+            Err(e) => {
+                println!("X Could not get pp_zk from {}: {}", endpoint, e);
+                last_error = Some(e);
+            },
+        }
+    }
+    Err(last_error.unwrap_or_else(|| "no key issuer configured".into()))
+}
+
+async fn pp_zk_from(endpoint: &str, pins: &Pins, cache: &ParamsCache, compression: Option<Compression>, options: DownloadOptions)
+    -> Result<BlindIDCircuitParameters<BW6<Parameters761>>, Box<dyn std::error::Error>> {
+    println!("About to connect to {} for getting the digest of pp_zk...", endpoint);
+    let published = match call(endpoint, pins, Request::GetPpZkDigest).await {
+        Ok(Response::PpZkDigest { version, digest }) => Some((version, digest)),
+        // Key issuers publishing no digest get pp_zk downloaded every time
+        Err(e) if e.code() == Some(ErrorCode::UnsupportedAction) => None,
+        Ok(other) => return Err(Box::new(ProtocolError::unexpected("get_pp_zk_digest", other))),
+        Err(e) => return Err(Box::new(e)),
+    };
+
+    if let Some((version, digest)) = &published {
+        match cache.load(PP_ZK_CACHE_NAME, *version, digest) {
+            Ok(Some(cached)) => {
+                println!("- Loading pp_zk from the cache");
+                // Checked when it was downloaded, and the digests show it is unchanged since, so the
+                // expensive checks of every curve point can be skipped
+                let mut pp_zk_cursor = Cursor::new(&cached);
+                match BlindIDCircuitParameters::<BW6<Parameters761>>::deserialize_unchecked(&mut pp_zk_cursor) {
+                    Ok(pp_zk) => return Ok(pp_zk),
+                    Err(e) => println!("X Could not load the cached pp_zk ({:?}), downloading it again", e),
+                }
+            },
+            Ok(None) => println!("- No cached pp_zk with digest {}", digest),
+            Err(e) => println!("X {}, downloading pp_zk again", e),
+        }
+    }
+
+    println!("About to connect to {} for getting pp_zk...", endpoint);
+    let pp_zk_bytes = match &published {
+        // In chunks, resuming an earlier download that was cut
+        Some((_, digest)) => match download_pp_zk(endpoint, pins, cache, PP_ZK_CACHE_NAME, digest, compression, options).await {
+            Ok(pp_zk_bytes) => pp_zk_bytes,
+            Err(DownloadError::Protocol(e)) if e.code() == Some(ErrorCode::UnsupportedAction) => whole_pp_zk(endpoint, pins, compression).await?,
+            Err(e) => return Err(Box::new(e)),
+        },
+        None => whole_pp_zk(endpoint, pins, compression).await?,
+    };
+    println!("- Deserializing pp_zk");
+    // CanonicalDeserialize 
+    let mut pp_zk_cursor = Cursor::new(&pp_zk_bytes);
+    let pp_zk = BlindIDCircuitParameters::<BW6<Parameters761>>::deserialize(&mut pp_zk_cursor)?;
+
+    if let Some((version, published_digest)) = published {
+        if digest(&pp_zk_bytes) != published_digest {
+            // The setup was replaced between the two requests, the next download caches the new one
+            println!("X pp_zk changed while it was downloaded, not caching it");
+        } else {
+            // Cached in the uncompressed encoding, the one deserialize_unchecked reads
+            let mut cached = Vec::new();
+            pp_zk.serialize_unchecked(&mut cached)?;
+            match cache.store(PP_ZK_CACHE_NAME, version, &published_digest, &cached) {
+                Ok(()) => println!("✓ Cached pp_zk"),
+                Err(e) => println!("X Could not cache pp_zk: {}", e),
+            }
+        }
+    }
+    Ok(pp_zk)
+}
+
+// pp_zk in a single response, from key issuers that do not serve it in chunks
+async fn whole_pp_zk(endpoint: &str, pins: &Pins, compression: Option<Compression>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut connection = Connection::connect_pinned(endpoint, pins).await?;
+    // The only response allowed to be this large, the frame is still read as it arrives
+    match connection.call_with_limit(Request::GetPpZk { compression }, MAX_FRAME_LEN).await? {
+        Response::PpZk { pp_zk, compression: None } => Ok(pp_zk.0),
+        Response::PpZk { pp_zk, compression: Some(compression) } => {
+            println!("- Decompressing pp_zk");
+            Ok(decompress(compression, &pp_zk.0, MAX_FRAME_LEN)?)
+        },
+        other => Err(Box::new(ProtocolError::unexpected("get_pp_zk", other))),
+    }
+}
+
+/// Get the public key of every issuer, by issuer index.
+/// With separate nodes, the keys are only trusted once THRESHOLD + 1 nodes returned the same list.
+pub async fn get_issuer_public_keys(config: &Config) -> Result<Vec<IssuerPublicKey<Bls12<Parameters>>>, Box<dyn std::error::Error>> {
+    let needed = if config.key_issuers.is_empty() { 1 } else { THRESHOLD + 1 };
+    let mut answers: Vec<(Vec<Blob>, usize)> = Vec::new();
+    for endpoint in endpoints(config) {
+        println!("About to connect to {} for getting the issuer public keys...", endpoint);
+        let issuer_public_keys = match call(endpoint, &config.pins, Request::GetIssuerPublicKeys).await {
+            Ok(Response::IssuerPublicKeys { issuer_public_keys }) => issuer_public_keys,
+            Ok(other) => {
+                println!("X Could not get the issuer public keys from {}: {}", endpoint,
+                         ProtocolError::unexpected("get_issuer_public_keys", other));
+                continue;
+            },
+            Err(e) => {
+                println!("X Could not get the issuer public keys from {}: {}", endpoint, e);
+                continue;
+            },
+        };
+        let votes = match answers.iter_mut().find(|(answer, _)| *answer == issuer_public_keys) {
+            Some((_, votes)) => { *votes += 1; *votes },
+            None => { answers.push((issuer_public_keys.clone(), 1)); 1 },
+        };
+        if votes == needed {
+            // CanonicalDeserialize each of them
+            return issuer_public_keys.iter().map(|issuer_public_key| {
+                let mut issuer_public_key_cursor = Cursor::new(&issuer_public_key.0);
+                Ok(IssuerPublicKey::<Bls12<Parameters>>::deserialize(&mut issuer_public_key_cursor)?)
+            }).collect();
+        }
+    }
+    Err(format!("fewer than {} key issuers agree on the issuer public keys", needed).into())
+}
+
+/// Send the request for ID-NIKE.VerifyID and ID-NIKE.BlindPartialExtract and return the partial user keys of
+/// THRESHOLD + 1 distinct issuers, by ascending issuer index as Combine expects them.
+/// check unblinds the key of an issuer and verifies it, keys failing the check are reported and left out.
+/// The key issuer nodes are all asked in parallel, each with its own timeout and retries,
+/// and the keys are used as soon as THRESHOLD + 1 issuers gave a valid one.
+pub async fn get_partial_user_keys<F>(config: &Config, request: Request, check: F)
+    -> Result<Vec<PartialSecretKey<Bls12_377>>, Box<dyn std::error::Error>>
+where
+    F: Fn(usize, &BlindPartialSecretKey<Bls12<Parameters>>) -> Result<PartialSecretKey<Bls12_377>, String>,
+{
+    // Valid keys by issuer index, two nodes claiming the same index only count once
+    let mut partial_user_keys: BTreeMap<usize, PartialSecretKey<Bls12_377>> = BTreeMap::new();
+    // Issuers whose answer was wrong, with the reason
+    let mut misbehaving: Vec<(usize, String)> = Vec::new();
+    let mut accept = |issuer_index: usize, blob: &Blob, partial_user_keys: &mut BTreeMap<usize, PartialSecretKey<Bls12_377>>| {
+        if partial_user_keys.contains_key(&issuer_index) {
+            println!("X Issuer {} answered more than once, only its first key is used", issuer_index);
+            return;
+        }
+        let checked = deserialize_blind_partial_user_key(blob)
+            .and_then(|blind_partial_user_key| check(issuer_index, &blind_partial_user_key));
+        match checked {
+            Ok(partial_user_key) => { partial_user_keys.insert(issuer_index, partial_user_key); },
+            Err(reason) => {
+                println!("X Issuer {} misbehaved: {}", issuer_index, reason);
+                misbehaving.push((issuer_index, reason));
+            },
+        }
+    };
+
+    // Key issuers that could not be reached, with the reason
+    let mut unreachable: Vec<(String, String)> = Vec::new();
+
+    // A single key-issuing authority answers for every issuer at once
+    if config.key_issuers.is_empty() {
+        println!("About to connect to the key-issuing authority...");
+        let response = match extract_with_retries(&config.key_issuing_authority, &config.pins, request,
+                                                  config.issuer_timeout, config.issuer_retries, config.extract_timeout).await {
+            Ok(response) => response,
+            Err(Failure::Unreachable(reason)) => return Err(reason.into()),
+            Err(Failure::Refused(e)) => return Err(Box::new(e)),
+        };
+        let blind_partial_user_keys = match response {
+            Response::BlindPartialUserKeys { blind_partial_user_keys } => blind_partial_user_keys,
+            other => return Err(Box::new(ProtocolError::unexpected("to_VerifyID_and_BlindPartialExtract", other))),
+        };
+        // One blob per issuer, by issuer index
+        for (issuer_index, blind_partial_user_key) in blind_partial_user_keys.iter().enumerate() {
+            accept(issuer_index, blind_partial_user_key, &mut partial_user_keys);
+        }
+    } else {
+        println!("About to ask {} key issuers in parallel...", config.key_issuers.len());
+        let (results_sender, mut results) = mpsc::unbounded_channel();
+        let tasks: Vec<_> = config.key_issuers.iter().map(|key_issuer| {
+            let results_sender = results_sender.clone();
+            let key_issuer = key_issuer.clone();
+            let request = request.clone();
+            let pins = config.pins.clone();
+            let (issuer_timeout, issuer_retries, extract_timeout) = (config.issuer_timeout, config.issuer_retries, config.extract_timeout);
+            tokio::spawn(async move {
+                let result = extract_with_retries(&key_issuer, &pins, request, issuer_timeout, issuer_retries, extract_timeout).await;
+                let _ = results_sender.send((key_issuer, result));
+            })
+        }).collect();
+        drop(results_sender);
+
+        // Key issuers that did not answer yet
+        let mut pending = config.key_issuers.clone();
+        while partial_user_keys.len() < THRESHOLD + 1 {
+            let (key_issuer, result) = match results.recv().await {
+                Some(result) => result,
+                // Every key issuer answered or gave up
+                None => break,
+            };
+            pending.retain(|pending_key_issuer| *pending_key_issuer != key_issuer);
+            match result {
+                Ok(Response::BlindPartialUserKey { issuer_index, blind_partial_user_key }) => {
+                    accept(issuer_index as usize, &blind_partial_user_key, &mut partial_user_keys);
+                    println!("- {}/{} issuers gave a valid partial user key", partial_user_keys.len(), THRESHOLD + 1);
+                },
+                Ok(other) => println!("X Key issuer {} failed: {}", key_issuer,
+                                      ProtocolError::unexpected("to_VerifyID_and_BlindPartialExtract", other)),
+                Err(Failure::Refused(e)) => println!("X Key issuer {} failed: {}", key_issuer, e),
+                Err(Failure::Unreachable(reason)) => {
+                    println!("X Key issuer {} is unreachable: {}", key_issuer, reason);
+                    unreachable.push((key_issuer, reason));
+                },
+            }
+        }
+
+        // Enough keys, the slower key issuers are not needed any more
+        for task in tasks {
+            task.abort();
+        }
+        if !unreachable.is_empty() {
+            let unreachable: Vec<&str> = unreachable.iter().map(|(key_issuer, _)| key_issuer.as_str()).collect();
+            println!("X Unreachable key issuers: {}", unreachable.join(", "));
+        }
+        if !pending.is_empty() {
+            println!("- Did not wait for key issuers: {}", pending.join(", "));
+        }
+    }
+
+    if partial_user_keys.len() < THRESHOLD + 1 {
+        let mut report: Vec<String> = misbehaving.iter()
+            .map(|(issuer_index, reason)| format!("issuer {} misbehaved: {}", issuer_index, reason))
+            .collect();
+        report.extend(unreachable.iter().map(|(key_issuer, reason)| format!("{} unreachable: {}", key_issuer, reason)));
+        return Err(format!("only {} of the {} issuers needed gave a valid partial user key [{}]",
+                           partial_user_keys.len(), THRESHOLD + 1, report.join("; ")).into());
+    }
+    Ok(partial_user_keys.into_values().take(THRESHOLD + 1).collect())
+}
+
+// Where to get pp_zk and the issuer public keys from, in order of preference
+fn endpoints(config: &Config) -> Vec<&String> {
+    if config.key_issuers.is_empty() {
+        vec![&config.key_issuing_authority]
+    } else {
+        config.key_issuers.iter().collect()
+    }
+}
+
+// One request on a fresh connection
+async fn call(endpoint: &str, pins: &Pins, request: Request) -> Result<Response, ProtocolError> {
+    let mut connection = Connection::connect_pinned(endpoint, pins).await?;
+    connection.call(request).await
+}
+
+// extract, giving up on the key issuer once deadline has passed since the first attempt, however
+// far the job got. A key issuer that keeps a job pending or running for ever counts as unreachable.
+async fn extract_with_retries(endpoint: &str, pins: &Pins, request: Request, issuer_timeout: Duration, retries: u32, deadline: Duration)
+                              -> Result<Response, Failure> {
+    timeout(deadline, extract(endpoint, pins, request, issuer_timeout, retries))
+        .await
+        .unwrap_or_else(|_| Err(Failure::Unreachable(format!("no partial user key within {} s", deadline.as_secs()))))
+}
+
+// Queue the extraction as a job on the key issuer and poll it until it is done. issuer_timeout applies
+// to every call on its own, the deadline of extract_with_retries to the extraction as a whole. Key issuers
+// without a job queue get the request in a single call instead.
+async fn extract(endpoint: &str, pins: &Pins, request: Request, issuer_timeout: Duration, retries: u32) -> Result<Response, Failure> {
+    let submit = match request.clone() {
+        Request::VerifyIdAndBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } =>
+            Request::SubmitBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation },
+        other => return call_with_retries(endpoint, pins, other, issuer_timeout, retries).await,
+    };
+    // A job lost by the key issuer, e.g. because it restarted, is submitted again
+    let mut resubmissions = 0;
+    'submit: loop {
+        let job_id = match call_with_retries(endpoint, pins, submit.clone(), issuer_timeout, retries).await {
+            Ok(Response::JobQueued { job_id, queue_position }) => {
+                println!("- Queued by {} behind {} jobs", endpoint, queue_position);
+                job_id
+            },
+            Ok(other) => return Ok(other),
+            Err(Failure::Refused(e)) if e.code() == Some(ErrorCode::UnsupportedAction) => {
+                return call_with_retries(endpoint, pins, request, issuer_timeout, retries).await;
+            },
+            Err(failure) => return Err(failure),
+        };
+        loop {
+            sleep(POLL_INTERVAL).await;
+            match call_with_retries(endpoint, pins, Request::GetJobStatus { job_id: job_id.clone() }, issuer_timeout, retries).await {
+                Ok(Response::JobPending { .. }) | Ok(Response::JobRunning) => continue,
+                Err(Failure::Refused(e)) if e.code() == Some(ErrorCode::UnknownJob) && resubmissions < retries => {
+                    println!("X {} lost the job, submitting it again", endpoint);
+                    resubmissions += 1;
+                    continue 'submit;
+                },
+                // The result of the job, keys or error
+                result => return result,
+            }
+        }
+    }
+}
+
+// The request with a timeout, on a fresh connection for every attempt.
+// Only failures to reach the endpoint and a full job queue are retried, any other error response would just come back again.
+async fn call_with_retries(endpoint: &str, pins: &Pins, request: Request, issuer_timeout: Duration, retries: u32) -> Result<Response, Failure> {
+    let mut attempt = 0;
+    loop {
+        let reason = match timeout(issuer_timeout, call(endpoint, pins, request.clone())).await {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e @ ProtocolError::Connect { .. })) | Ok(Err(e @ ProtocolError::Frame(_))) => e.to_string(),
+            Ok(Err(e)) if e.code() == Some(ErrorCode::Busy) => e.to_string(),
+            Ok(Err(e)) => return Err(Failure::Refused(e)),
+            Err(_) => format!("no answer within {} s", issuer_timeout.as_secs()),
+        };
+        if attempt == retries {
+            return Err(Failure::Unreachable(format!("{} (after {} attempts)", reason, attempt + 1)));
+        }
+        sleep(RETRY_DELAY * 2u32.pow(attempt)).await;
+        attempt += 1;
+    }
+}
+
+fn deserialize_blind_partial_user_key(blob: &Blob) -> Result<BlindPartialSecretKey<Bls12<Parameters>>, String> {
+    // CanonicalDeserialize 
+    let mut blind_partial_user_key_cursor = Cursor::new(&blob.0);
+    BlindPartialSecretKey::<Bls12<Parameters>>::deserialize(&mut blind_partial_user_key_cursor)
+        .map_err(|e| format!("malformed blind partial user key: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use ark_bw6_761::BW6_761;
+    use arke_core::ThresholdObliviousIdNIKE;
+    use arke_common::download::serve_chunk;
+    use arke_common::params_cache::PP_ZK_VERSION;
+    use arke_common::server::serve_connection;
+
+    type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
+
+    // A real pp_zk, for a circuit much smaller than the one of the key-issuing authority so the setup is quick
+    fn small_pp_zk() -> Vec<u8> {
+        let pp_zk = ArkeIdNIKE::setup_blind_id_proof(1, 1, 8, &mut rand::thread_rng()).unwrap();
+        let mut bytes = Vec::new();
+        // Encoded the way the key issuers serve it
+        pp_zk.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    // Serve pp_zk and its digest like a key issuer, and record the offset of every chunk asked for
+    async fn start_server(pp_zk: Arc<Vec<u8>>) -> (SocketAddr, Arc<Mutex<Vec<u64>>>) {
+        let digest = digest(&pp_zk);
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = Arc::clone(&offsets);
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let (pp_zk, digest, served) = (Arc::clone(&pp_zk), digest.clone(), Arc::clone(&served));
+                tokio::spawn(serve_connection(socket, move |request| {
+                    let response = match request {
+                        Request::GetPpZkDigest => Ok(Response::PpZkDigest { version: PP_ZK_VERSION, digest: digest.clone() }),
+                        Request::GetPpZkChunk { offset, length, compression: None } => {
+                            served.lock().unwrap().push(offset);
+                            serve_chunk(&pp_zk, &digest, None, offset, length)
+                        },
+                        other => panic!("unexpected request {}", other.action()),
+                    };
+                    async move { response }
+                }));
+            }
+        });
+        (addr, offsets)
+    }
+
+    // Forward connections to server, cutting the second one, the first download, once cut_after bytes of responses went through
+    async fn start_proxy(server: SocketAddr, cut_after: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            loop {
+                let (client, _) = listener.accept().await.unwrap();
+                let cut = connections.fetch_add(1, Ordering::SeqCst) == 1;
+                tokio::spawn(async move {
+                    let upstream = TcpStream::connect(server).await.unwrap();
+                    let (mut client_read, mut client_write) = client.into_split();
+                    let (mut upstream_read, mut upstream_write) = upstream.into_split();
+                    let requests = tokio::spawn(async move { tokio::io::copy(&mut client_read, &mut upstream_write).await });
+                    let mut budget = if cut { cut_after } else { usize::MAX };
+                    let mut buffer = vec![0; 64 << 10];
+                    loop {
+                        let read = match upstream_read.read(&mut buffer).await {
+                            Ok(0) | Err(_) => break,
+                            Ok(read) => read,
+                        };
+                        let forwarded = read.min(budget);
+                        if client_write.write_all(&buffer[..forwarded]).await.is_err() {
+                            break;
+                        }
+                        budget -= forwarded;
+                        if budget == 0 {
+                            break;
+                        }
+                    }
+                    requests.abort();
+                });
+            }
+        });
+        addr
+    }
+
+    fn encoded(pp_zk: &BlindIDCircuitParameters<BW6<Parameters761>>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        pp_zk.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn pp_zk_downloaded_over_a_cut_connection_is_cached_and_reloaded_unchanged() {
+        let served = Arc::new(small_pp_zk());
+        let (server, offsets) = start_server(Arc::clone(&served)).await;
+        let proxy = start_proxy(server, served.len() / 2).await.to_string();
+        let dir = std::env::temp_dir().join(format!("arke_client_pp_zk_test_{:016x}", rand::random::<u64>()));
+        let cache = ParamsCache::new(&dir);
+        let pins = Pins::new(BTreeMap::new()).allow_plaintext();
+        // Several chunks, so the cut falls between two of them
+        let chunk_len = (served.len() / 8).max(1) as u32;
+        let options = DownloadOptions { chunk_len, call_timeout: Duration::from_secs(10), retries: 2 };
+
+        let downloaded = pp_zk_from(&proxy, &pins, &cache, None, options).await.unwrap();
+        assert!(encoded(&downloaded) == *served);
+        // The download went on after the cut instead of starting over
+        let resumed = offsets.lock().unwrap().clone();
+        assert_eq!(resumed.iter().filter(|offset| **offset == 0).count(), 1, "offsets asked for: {:?}", resumed);
+        assert!(!dir.join("pp_zk.part").exists());
+
+        // The cached copy is the serialize_unchecked encoding, read back with deserialize_unchecked
+        let cached = cache.load(PP_ZK_CACHE_NAME, PP_ZK_VERSION, &digest(&served)).unwrap().expect("pp_zk was cached");
+        let mut cached_cursor = Cursor::new(&cached);
+        let reloaded = BlindIDCircuitParameters::<BW6<Parameters761>>::deserialize_unchecked(&mut cached_cursor).unwrap();
+        assert!(encoded(&reloaded) == *served);
+
+        // The next session loads it from the cache without downloading a chunk
+        let chunks_asked = offsets.lock().unwrap().len();
+        let loaded = pp_zk_from(&proxy, &pins, &ParamsCache::new(&dir), None, options).await.unwrap();
+        assert!(encoded(&loaded) == *served);
+        assert_eq!(offsets.lock().unwrap().len(), chunks_asked);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
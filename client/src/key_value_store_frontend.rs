@@ -10,19 +10,31 @@ use web3::{
     contract::{Contract, Options},
     types::{Address, U256}
 };
-use std::str::FromStr;
+use std::sync::Arc;
 use arke_core::{UnlinkableHandshake, StoreKey,};
 use crossterm::terminal;
 use chrono::{Local, Timelike};
 use textwrap::wrap;
+use crate::config::Config;
 
 
+// Connect to the Ethereum node and check that it runs the chain the config expects
+pub async fn connect(config: &Config) -> Result<(Arc<web3::Web3<WebSocket>>, Arc<KeyValueStore>), Box<dyn std::error::Error>> {
+    let transport = WebSocket::new(&config.rpc_url).await?;
+    let web3 = web3::Web3::new(transport);
+    let chain_id = web3.eth().chain_id().await?;
+    if chain_id != U256::from(config.chain_id) {
+        return Err(format!("{} runs chain {} but chain {} is configured", config.rpc_url, chain_id, config.chain_id).into());
+    }
+    let store = KeyValueStore::new(&web3, config.contract_address).await;
+    Ok((Arc::new(web3), Arc::new(store)))
+}
+
 #[derive(Clone)]
 pub struct KeyValueStore(Contract<WebSocket>);
 
 impl KeyValueStore {
-    pub async fn new(web3: &web3::Web3<web3::transports::WebSocket>, contract_address: String) -> Self {
-        let contract_address = Address::from_str(&contract_address).unwrap();
+    pub async fn new(web3: &web3::Web3<web3::transports::WebSocket>, contract_address: Address) -> Self {
         let contract =
             Contract::from_json(web3.eth(), contract_address, include_bytes!("key_value_store.abi")).unwrap();
         KeyValueStore(contract)
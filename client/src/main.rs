@@ -10,8 +10,16 @@ mod discovery_info;
 // Libs for UI
 use dialoguer::{theme::ColorfulTheme, FuzzySelect};
 
+mod config;
+use config::Config;
+mod profile;
+use profile::profiles;
+
+mod key_issuers;
 mod user;
 use user::user;
+mod verification;
+mod id_verification;
 mod private_chat_and_pay;
 use private_chat_and_pay::privateChatAndPay;
 mod contact_discovery;
@@ -22,10 +30,19 @@ mod group_chat;
 use group_chat::groupChat;
 mod create_group;
 use create_group::createGroup;
+mod delete_account;
+use delete_account::deleteAccount;
 
 
 #[tokio::main]
 pub async fn main() -> std::io::Result<()> {
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+    };
     let MainMenu = &[
         "My info",
         "Friends",
@@ -33,55 +50,71 @@ pub async fn main() -> std::io::Result<()> {
         "Delete Friend",
         "Groups",
         "Start Group",
+        "Profiles",
+        "Delete Account",
         "Exit",
     ];
 
     // Display the main menu
     loop {
         let MainMenuSelection = FuzzySelect::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like to do?")
+            .with_prompt(format!("What would you like to do? (profile: {})", config.profile))
             .default(0)
             .items(&MainMenu[..])
             .interact()
             .unwrap();
 
-        match MainMenuSelection {
+        let result = match MainMenuSelection {
             0 => {
                 // Sign up and initialize my info if not signed up
                 // Print my info if signed up
-                user().await; 
+                user(&config).await
             }
             1 => {
                 // Print the friend list
                 // Select a friend in the friend list to private chat or pay
-                privateChatAndPay().await; 
+                privateChatAndPay(&config).await
             }
             2 => {
                 // Perform contact discovery with a target user
                 // Add the discovered user into the friend list
-                contactDiscovery().await; 
+                contactDiscovery(&config).await
             }
             3 => {
                 // Delete a friend from the friend list
-                deleteFriend().await; 
+                deleteFriend(&config).await
             }
             4 => {
                 // Print the group list
                 // Select a group in the group list to group chat
-                groupChat().await;
+                groupChat(&config).await
             }
             5 => {
                 // Perform contact discovery with a desired group ID to create the group
                 // Add the created group into the group list
-                createGroup().await;
+                createGroup(&config).await
             }
             6 => {
+                // List, create or switch profiles, i.e. local users
+                profiles(&mut config).await
+            }
+            7 => {
+                // Delete the account of the user, or of a lost ID
+                deleteAccount(&config).await
+            }
+            8 => {
                 // Exit the application
                 break; 
             }
             _ => {
                 println!("Invalid selection");
+                Ok(())
             }
+        };
+
+        // Show what went wrong (including the rejection reason given by a server) and return to the main menu
+        if let Err(e) = result {
+            println!("X {}", e);
         }
     } 
 
@@ -13,7 +13,8 @@ use web3::types::{H160, U256};
 use web3::futures::StreamExt;
 use web3::types::{FilterBuilder, Log};
 use std::str::FromStr;
-use crate::key_value_store_frontend::KeyValueStore;
+use crate::key_value_store_frontend::{self, KeyValueStore};
+use crate::config::Config;
 use rand::{distributions::Alphanumeric, Rng, thread_rng};
 use arke_core::{UnlinkableHandshake, UserSecretKey, StoreKey};
 use ark_ec::bls12::Bls12;
@@ -24,6 +25,7 @@ use dialoguer::{theme::ColorfulTheme, FuzzySelect};
 use serde::{Serialize, Deserialize};
 use std::fs::OpenOptions;
 use std::fs::File;
+use arke_common::durable_file::{read_json_list, write_json_list};
 use tokio::sync::mpsc;
 use std::sync::Arc;
 use tokio::net::TcpStream;
@@ -39,8 +41,6 @@ use std::io::{Seek, SeekFrom};
 use tokio::task::JoinHandle;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-const CONTRACT_ADDR: &str = "0xc23EDB04DebB123CDB1ac96a28eA18E8403a34d6";
-
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct Friend {
     id_string: String,
@@ -76,34 +76,21 @@ fn print_chatbox(message: &str) {
     println!("{}", time_str);
 }
 
-pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{   
+pub async fn privateChatAndPay(config: &Config) -> Result<(), Box<dyn std::error::Error>>{   
     // Setup the contract and an interface to access it's functionality 
-    let transport = web3::transports::WebSocket::new("ws://127.0.0.1:9545").await?;
-    let web3 = web3::Web3::new(transport);
-    let web3 = Arc::new(web3);
-    let Store = KeyValueStore::new(
-        &web3,
-        // Update to match the deployed contract address on ganache
-        CONTRACT_ADDR.to_string(),
-        ).await;     
-    let Store = Arc::new(Store);
+    let (web3, Store) = key_value_store_frontend::connect(config).await?;
+    let contract_address = config.contract_address;
+    let my_info_path = config.my_info_path();
+    let friends_path = config.friends_path();
 
-    // Read friends.json
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("src/friends.json")
-        .unwrap();
-    // Check whether friends.json is empty or not, i.e. whether there are friends or not
-    let metadata = file.metadata().unwrap();
-    // If empty, return to the main menu
-    if metadata.len() == 0 {
+    // Read friends.json, a corrupt file is an error rather than an empty friend list
+    let friends: Vec<Friend> = read_json_list(&friends_path)?;
+    // If there are no friends, return to the main menu
+    if friends.is_empty() {
         println!("No friends");
         return Ok(());
     }
 
-    // Derialize friends.json to read friend objects 
-    let friends: Vec<Friend> = serde_json::from_reader(file).unwrap();
     // Convert each friend object to a string representation and collect them into a vector
     let mut FriendsMenu: Vec<String> = friends.iter()
         .map(|friend| { format!("ID string: {}", friend.id_string)}).collect();
@@ -173,10 +160,11 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                         let Store_clone1 = Arc::clone(&Store);
                         let web3_clone = Arc::clone(&web3);
                         let should_terminate_clone2 = Arc::clone(&should_terminate);
+                        let my_info_path_clone = my_info_path.clone();
                         handle2 = Some(tokio::spawn(async move {
                             // Subscribe to the smart contract
                             let filter = FilterBuilder::default()
-                                .address(vec![CONTRACT_ADDR.parse().unwrap()])
+                                .address(vec![contract_address])
                                 .build();
                             loop {
                                 if should_terminate_clone2.load(Ordering::Relaxed) {
@@ -184,7 +172,7 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                                     break;
                                 }
                                 let filter_clone = filter.clone();
-                                let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                                let mut my_info_file = File::open(&my_info_path_clone).unwrap();
                                 let mut deserialized: Vec<u8> = Vec::new();
                                 my_info_file.read_to_end(&mut deserialized).unwrap();
                                 let mut cursor = Cursor::new(&deserialized);
@@ -241,7 +229,7 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                                 }
                             }
                             else {
-                                let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                                let mut my_info_file = File::open(&my_info_path).unwrap();
                                 let mut deserialized: Vec<u8> = Vec::new();
                                 my_info_file.read_to_end(&mut deserialized).unwrap();
                                 let mut cursor = Cursor::new(&deserialized);
@@ -267,23 +255,12 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                                 .with_prompt("What is the recipient ethereum address?")
                                 .interact()
                                 .unwrap();
-                            let mut file = OpenOptions::new()
-                                .read(true)
-                                .write(true)
-                                .open("src/friends.json")
-                                .unwrap();
-                            // Derialize friends.json to read friend objects 
-                            let mut friends: Vec<Friend> = serde_json::from_reader(&file).unwrap();
-                            for friend in &mut friends {
-                                if friend.id_string == selected_friend.id_string {
-                                    friend.eth_addr = recepient_eth_addr.clone();
-                                    // Truncate the file and rewind to the beginning
-                                    file.set_len(0).unwrap();
-                                    file.seek(SeekFrom::Start(0)).unwrap();
-                                    // Write the updated friends back to the file
-                                    serde_json::to_writer(&file, &friends).unwrap();
-                                    break;
-                                }
+                            // Read then write to friends.json
+                            let mut friends: Vec<Friend> = read_json_list(&friends_path)?;
+                            if let Some(friend) = friends.iter_mut().find(|friend| friend.id_string == selected_friend.id_string) {
+                                friend.eth_addr = recepient_eth_addr.clone();
+                                // Write the updated friends back to the file, atomically so that a crash cannot lose the friend list
+                                write_json_list(&friends_path, &friends)?;
                             }
                             let amount = dialoguer::Input::<String>::new()
                                 .with_prompt("How much Ether do you want to transfer?")
@@ -294,7 +271,7 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                             let amount_in_wei = U256::from_dec_str(&(amount_in_ether * 1e18).to_string()).expect("Failed to convert to wei");
                             let Store_clone = Arc::clone(&Store);
 
-                            let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                            let mut my_info_file = File::open(&my_info_path).unwrap();
                             let mut deserialized: Vec<u8> = Vec::new();
                             my_info_file.read_to_end(&mut deserialized).unwrap();
                             let mut cursor = Cursor::new(&deserialized);
@@ -314,7 +291,7 @@ pub async fn privateChatAndPay() -> Result<(), Box<dyn std::error::Error>>{
                             let amount_in_wei = U256::from_dec_str(&(amount_in_ether * 1e18).to_string()).expect("Failed to convert to wei");
                             let Store_clone = Arc::clone(&Store);
 
-                            let mut my_info_file = File::open("src/my_info.bin").unwrap();
+                            let mut my_info_file = File::open(&my_info_path).unwrap();
                             let mut deserialized: Vec<u8> = Vec::new();
                             my_info_file.read_to_end(&mut deserialized).unwrap();
                             let mut cursor = Cursor::new(&deserialized);
@@ -0,0 +1,136 @@
+// ---------------------------------------
+// File: profile.rs
+// Date: 18 Oct 2026
+// Description: Profiles, i.e. several local users sharing one client
+// ---------------------------------------
+
+// Every profile keeps its own my_info.bin, friends.json and groups.json.
+// The "default" profile lives directly in the data directory (where a single user kept them before),
+// every other profile lives in <data dir>/profiles/<name>.
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use arke_common::durable_file::{write_atomic, DurableFileError};
+use crate::config::Config;
+
+pub const DEFAULT_PROFILE: &str = "default";
+// File in the data directory remembering the profile selected last
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+
+/// Check that a profile name is usable as a directory name
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("a profile name cannot be empty".to_string());
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("profile name {:?} may only contain letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+pub fn profile_dir(data_dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        data_dir.to_path_buf()
+    } else {
+        data_dir.join("profiles").join(name)
+    }
+}
+
+/// Create the profile directory and its empty state files, which the rest of the client reads as "nothing saved yet".
+/// Existing files are left untouched.
+pub fn create_profile(data_dir: &Path, name: &str) -> std::io::Result<PathBuf> {
+    let dir = profile_dir(data_dir, name);
+    std::fs::create_dir_all(&dir)?;
+    for file in ["my_info.bin", "friends.json", "groups.json"] {
+        OpenOptions::new().create(true).append(true).open(dir.join(file))?;
+    }
+    Ok(dir)
+}
+
+/// Names of all profiles, the default profile first
+pub fn list_profiles(data_dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let profiles_dir = data_dir.join("profiles");
+    if profiles_dir.is_dir() {
+        for entry in std::fs::read_dir(profiles_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    Ok(names)
+}
+
+pub fn read_active_profile(data_dir: &Path) -> Option<String> {
+    let name = std::fs::read_to_string(data_dir.join(ACTIVE_PROFILE_FILE)).ok()?;
+    let name = name.trim().to_string();
+    validate_profile_name(&name).ok()?;
+    Some(name)
+}
+
+pub fn write_active_profile(data_dir: &Path, name: &str) -> Result<(), DurableFileError> {
+    write_atomic(&data_dir.join(ACTIVE_PROFILE_FILE), name.as_bytes())
+}
+
+/// List, create and switch profiles
+pub async fn profiles(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let ProfilesMenu = &[
+        "List profiles",
+        "Create profile",
+        "Switch profile",
+        "Go back",
+    ];
+    let ProfilesMenuSelection = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Current profile: {}", config.profile))
+        .default(0)
+        .items(&ProfilesMenu[..])
+        .interact()
+        .unwrap();
+
+    match ProfilesMenuSelection {
+        0 => {
+            for name in list_profiles(&config.data_dir)? {
+                let marker = if name == config.profile { "*" } else { " " };
+                println!("{} {} ({})", marker, name, profile_dir(&config.data_dir, &name).display());
+            }
+        }
+        1 => {
+            let name = dialoguer::Input::<String>::new()
+                .with_prompt("Name of the new profile")
+                .interact()
+                .unwrap();
+            validate_profile_name(&name)?;
+            if list_profiles(&config.data_dir)?.contains(&name) {
+                return Err(format!("profile {} already exists", name).into());
+            }
+            let dir = create_profile(&config.data_dir, &name)?;
+            println!("✓ Created profile {} in {}", name, dir.display());
+            switch_profile(config, &name)?;
+        }
+        2 => {
+            let names = list_profiles(&config.data_dir)?;
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Which profile would you like to use?")
+                .default(0)
+                .items(&names[..])
+                .interact()
+                .unwrap();
+            switch_profile(config, &names[selection])?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn switch_profile(config: &mut Config, name: &str) -> std::io::Result<()> {
+    config.profile = name.to_string();
+    // Also the profile to start with next time, unless --profile says otherwise
+    write_active_profile(&config.data_dir, name)?;
+    println!("✓ Switched to profile {}", name);
+    Ok(())
+}
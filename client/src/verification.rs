@@ -0,0 +1,30 @@
+// ---------------------------------------
+// File: verification.rs
+// Date: 18 Oct 2026
+// Description: Check what the authorities return before using it
+// ---------------------------------------
+
+use arke_core::{ThresholdObliviousIdNIKE, RegistrarPublicKey, RegistrationAttestation,
+                IssuerPublicKey, PartialSecretKey, UserID};
+use ark_ec::bls12::Bls12;
+use ark_bls12_377::{Bls12_377, Parameters};
+use ark_bw6_761::BW6_761;
+type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
+// Domain identifier for the registration authority of this example
+const REGISTRAR_DOMAIN: &'static [u8] = b"registration";
+
+/// Whether reg_attestation is the registrar's signature on user_id.
+/// Blinding an attestation that does not verify would only make every key issuer reject the request.
+pub fn verify_reg_attestation(registrar_public_key: &RegistrarPublicKey<Bls12<Parameters>>,
+                              user_id: &UserID,
+                              reg_attestation: &RegistrationAttestation<Bls12<Parameters>>) -> bool {
+    matches!(ArkeIdNIKE::verify_registration(registrar_public_key, user_id, REGISTRAR_DOMAIN, reg_attestation), Ok(true))
+}
+
+/// Whether an unblinded partial key is the share of user_id of the issuer holding issuer_public_key.
+/// A wrong share would make Combine return an unusable user key without any error.
+pub fn verify_partial_user_key(issuer_public_key: &IssuerPublicKey<Bls12<Parameters>>,
+                               user_id: &UserID,
+                               partial_user_key: &PartialSecretKey<Bls12_377>) -> bool {
+    matches!(ArkeIdNIKE::verify_partial_secret_key(issuer_public_key, user_id, REGISTRAR_DOMAIN, partial_user_key), Ok(true))
+}
@@ -0,0 +1,158 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Runtime configuration of the database server
+// ---------------------------------------
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use arke_common::config::{load_file, parse_socket_addr, prepare_data_dir, tls_files, ConfigError, TlsFiles};
+use crate::store::{self, StorageBackend};
+
+const DEFAULT_BIND: &str = "127.0.0.1:8080";
+// The admin endpoint only listens on localhost unless configured otherwise
+const DEFAULT_ADMIN_BIND: &str = "127.0.0.1:9080";
+const DEFAULT_DATA_DIR: &str = "src";
+// Long enough for registration and the key extraction, including a first download of pp_zk
+const DEFAULT_LEASE_TTL_SECS: u64 = 600;
+// 30 days, so contacts notice the account is gone before somebody else can take its ID
+const DEFAULT_REUSE_COOLDOWN_SECS: u64 = 30 * 24 * 60 * 60;
+// Where the registration authority writes it when both run from this repository with their default data directories
+const DEFAULT_ENROLLMENT_KEY: &str = "../registration_authority/data/enrollment_public_key.txt";
+
+/// Command line flags, each of them can also be set through its environment variable
+#[derive(Parser, Debug)]
+#[command(about = "Arke database server")]
+struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ARKE_DB_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, env = "ARKE_DB_BIND")]
+    bind: Option<String>,
+    /// Directory holding the user directory
+    #[arg(long, env = "ARKE_DB_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Backend keeping the user directory
+    #[arg(long, env = "ARKE_DB_STORAGE", value_enum)]
+    storage: Option<StorageBackend>,
+    /// Seconds an ID reserved by reserve_id stays held before it is released
+    #[arg(long, env = "ARKE_DB_LEASE_TTL")]
+    lease_ttl: Option<u64>,
+    /// Seconds after an account is deleted before its ID can be signed up for again
+    #[arg(long, env = "ARKE_DB_REUSE_COOLDOWN")]
+    reuse_cooldown: Option<u64>,
+    /// File with the public key of the registration authority for enrollment tokens
+    #[arg(long, env = "ARKE_DB_ENROLLMENT_KEY")]
+    enrollment_key: Option<PathBuf>,
+    /// Address of the admin endpoint for arke-admin
+    #[arg(long, env = "ARKE_DB_ADMIN_BIND")]
+    admin_bind: Option<String>,
+    /// Do not start the admin endpoint
+    #[arg(long)]
+    no_admin: bool,
+    /// File with the admin key, created if it does not exist. admin.key in the data directory by default
+    #[arg(long, env = "ARKE_DB_ADMIN_KEY")]
+    admin_key: Option<PathBuf>,
+    /// PEM certificate chain to serve clients over TLS with, together with --tls-key
+    #[arg(long, env = "ARKE_DB_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of the TLS certificate
+    #[arg(long, env = "ARKE_DB_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Import the users of a JSON user directory into the configured storage backend, then exit
+    Migrate {
+        /// JSON file to import, all_users.json in the data directory by default
+        #[arg(long)]
+        from: Option<PathBuf>,
+    },
+    /// Revoke an ID enrolled fraudulently by suspending it, then exit. The ID stays taken so that it cannot be enrolled again.
+    Revoke {
+        id_string: String,
+    },
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<String>,
+    data_dir: Option<PathBuf>,
+    storage: Option<StorageBackend>,
+    lease_ttl: Option<u64>,
+    reuse_cooldown: Option<u64>,
+    enrollment_key: Option<PathBuf>,
+    admin_bind: Option<String>,
+    admin_key: Option<PathBuf>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub data_dir: PathBuf,
+    pub storage: StorageBackend,
+    pub lease_ttl: Duration,
+    pub reuse_cooldown: Duration,
+    pub enrollment_key: PathBuf,
+    /// None if the admin endpoint is off
+    pub admin_bind: Option<SocketAddr>,
+    admin_key: Option<PathBuf>,
+    /// None if clients connect over plain TCP
+    pub tls: Option<TlsFiles>,
+    pub command: Option<Command>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file: FileConfig = load_file(cli.config.as_deref())?;
+
+        let bind = cli.bind.or(file.bind).unwrap_or_else(|| DEFAULT_BIND.to_string());
+        let data_dir = cli.data_dir.or(file.data_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+
+        let storage = cli.storage.or(file.storage).unwrap_or(StorageBackend::Json);
+        let lease_ttl = cli.lease_ttl.or(file.lease_ttl).unwrap_or(DEFAULT_LEASE_TTL_SECS);
+        let reuse_cooldown = cli.reuse_cooldown.or(file.reuse_cooldown).unwrap_or(DEFAULT_REUSE_COOLDOWN_SECS);
+        let enrollment_key = cli.enrollment_key.or(file.enrollment_key).unwrap_or_else(|| PathBuf::from(DEFAULT_ENROLLMENT_KEY));
+        let admin_bind = cli.admin_bind.or(file.admin_bind).unwrap_or_else(|| DEFAULT_ADMIN_BIND.to_string());
+        let admin_key = cli.admin_key.or(file.admin_key);
+
+        if lease_ttl == 0 {
+            return Err(ConfigError::invalid("lease_ttl", "0", "must be at least 1 second"));
+        }
+
+        Ok(Config {
+            bind: parse_socket_addr("bind", &bind)?,
+            data_dir: prepare_data_dir("data_dir", data_dir)?,
+            storage,
+            lease_ttl: Duration::from_secs(lease_ttl),
+            reuse_cooldown: Duration::from_secs(reuse_cooldown),
+            enrollment_key,
+            admin_bind: if cli.no_admin { None } else { Some(parse_socket_addr("admin_bind", &admin_bind)?) },
+            admin_key,
+            tls: tls_files(cli.tls_cert.or(file.tls_cert), cli.tls_key.or(file.tls_key))?,
+            command: cli.command,
+        })
+    }
+
+    /// JSON file migrate imports from
+    pub fn migrate_source(&self, from: Option<&PathBuf>) -> PathBuf {
+        from.cloned().unwrap_or_else(|| store::json_path(&self.data_dir))
+    }
+
+    /// File with the key arke-admin logs in with
+    pub fn admin_key_path(&self) -> PathBuf {
+        self.admin_key.clone().unwrap_or_else(|| self.data_dir.join("admin.key"))
+    }
+}
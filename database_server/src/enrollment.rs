@@ -0,0 +1,142 @@
+// ---------------------------------------
+// File: enrollment.rs
+// Date: 18 Oct 2026
+// Description: Check that a user enrolling an ID was registered for it
+// ---------------------------------------
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use ed25519_dalek::{Signature, VerifyingKey, PUBLIC_KEY_LENGTH};
+use rand::RngCore;
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{deletion_message, enrollment_message, registrar_login_message, Blob};
+use crate::store::now_secs;
+
+/// How long an enrollment token stays good after the registration authority issued it, it is used right away
+const ENROLLMENT_TOKEN_LIFETIME_SECS: u64 = 120;
+/// How long a deletion token stays good after the registration authority issued it
+const DELETION_TOKEN_LIFETIME_SECS: u64 = 600;
+/// How far ahead of this server the clock of the registration authority may be
+const MAX_CLOCK_SKEW_SECS: u64 = 60;
+/// Length of the nonce the registration authority signs to log in
+const NONCE_LEN: usize = 32;
+
+/// Verifies the enrollment tokens the registration authority hands out with each registration
+pub struct EnrollmentVerifier {
+    public_key_path: PathBuf,
+    // Read once at startup and again on admin_reload, not on every check. The registration authority
+    // has to be started once before the database server, and a regenerated key needs an admin_reload.
+    public_key: RwLock<VerifyingKey>,
+}
+
+impl EnrollmentVerifier {
+    /// Fails if there is no usable public key at public_key_path
+    pub fn new(public_key_path: PathBuf) -> Result<Self, ServiceError> {
+        let public_key = RwLock::new(read_public_key(&public_key_path)?);
+        Ok(Self { public_key_path, public_key })
+    }
+
+    pub fn public_key_path(&self) -> &Path {
+        &self.public_key_path
+    }
+
+    /// Read the public key again, e.g. after the registration authority regenerated it. The old key
+    /// stays in use if the file is not usable.
+    pub fn reload(&self) -> Result<(), ServiceError> {
+        let public_key = read_public_key(&self.public_key_path)?;
+        *self.public_key.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = public_key;
+        Ok(())
+    }
+
+    fn public_key(&self) -> VerifyingKey {
+        // Only ever replaced as a whole, a panic cannot leave it half-written
+        *self.public_key.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Check that enrollment_token is a recent signature of the registration authority for id_string,
+    /// and for the lease on it if it was reserved
+    pub fn verify(&self, id_string: &str, lease_token: Option<&str>, enrollment_token: &Blob, issued_at: u64) -> Result<(), ServiceError> {
+        check_fresh(issued_at, ENROLLMENT_TOKEN_LIFETIME_SECS, "enrollment")?;
+        let signature = Signature::from_slice(&enrollment_token.0)
+            .map_err(|e| ServiceError::bad_encoding("enrollment_token", e))?;
+        self.public_key()
+            .verify_strict(&enrollment_message(id_string, lease_token, issued_at), &signature)
+            .map_err(|_| ServiceError::new(ErrorCode::InvalidProof,
+                "the enrollment token was not issued for this ID and lease by the registration authority"))
+    }
+
+    /// Check that signature is the one of the registration authority on the login nonce
+    pub fn verify_registrar_login(&self, nonce: &[u8], signature: &Blob) -> Result<(), ServiceError> {
+        let signature = Signature::from_slice(&signature.0)
+            .map_err(|e| ServiceError::bad_encoding("signature", e))?;
+        self.public_key()
+            .verify_strict(&registrar_login_message(nonce), &signature)
+            .map_err(|_| ServiceError::new(ErrorCode::Unauthorized, "the login was not signed by the registration authority"))
+    }
+
+    /// Check that deletion_token is a recent signature of the registration authority for id_string
+    pub fn verify_deletion(&self, id_string: &str, deletion_token: &Blob, issued_at: u64) -> Result<(), ServiceError> {
+        check_fresh(issued_at, DELETION_TOKEN_LIFETIME_SECS, "deletion")?;
+        let signature = Signature::from_slice(&deletion_token.0)
+            .map_err(|e| ServiceError::bad_encoding("deletion_token", e))?;
+        self.public_key()
+            .verify_strict(&deletion_message(id_string, issued_at), &signature)
+            .map_err(|_| ServiceError::new(ErrorCode::InvalidProof,
+                "the deletion token was not issued for this ID by the registration authority"))
+    }
+}
+
+fn read_public_key(path: &Path) -> Result<VerifyingKey, ServiceError> {
+    let unavailable = |reason: String| ServiceError::new(ErrorCode::Internal,
+        format!("the enrollment public key in {} is unavailable: {}", path.display(), reason));
+    let encoded = std::fs::read_to_string(path).map_err(|e| unavailable(e.to_string()))?;
+    let bytes = base64::decode(encoded.trim()).map_err(|e| unavailable(e.to_string()))?;
+    let bytes: [u8; PUBLIC_KEY_LENGTH] = bytes.as_slice().try_into()
+        .map_err(|_| unavailable(format!("expected {} bytes", PUBLIC_KEY_LENGTH)))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| unavailable(e.to_string()))
+}
+
+/// Refuse a token issued more than lifetime_secs ago, or too far in the future
+fn check_fresh(issued_at: u64, lifetime_secs: u64, kind: &str) -> Result<(), ServiceError> {
+    let now = now_secs();
+    if issued_at > now + MAX_CLOCK_SKEW_SECS || now.saturating_sub(issued_at) > lifetime_secs {
+        return Err(ServiceError::new(ErrorCode::InvalidProof, format!("the {} token has expired, get a new one", kind)));
+    }
+    Ok(())
+}
+
+/// Whether a connection logged in as the registration authority, the only one allowed to reserve
+/// and enroll IDs
+#[derive(Default)]
+pub struct RegistrarSession {
+    // The nonce handed out by the last registrar_challenge, good for one login attempt
+    nonce: Option<Vec<u8>>,
+    logged_in: bool,
+}
+
+impl RegistrarSession {
+    /// A new nonce for the registration authority to sign
+    pub fn challenge(&mut self) -> Vec<u8> {
+        let mut nonce = vec![0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        self.nonce = Some(nonce.clone());
+        nonce
+    }
+
+    pub fn login(&mut self, verifier: &EnrollmentVerifier, signature: &Blob) -> Result<(), ServiceError> {
+        let nonce = self.nonce.take()
+            .ok_or_else(|| ServiceError::new(ErrorCode::Unauthorized, "ask for a registrar_challenge first"))?;
+        verifier.verify_registrar_login(&nonce, signature)?;
+        self.logged_in = true;
+        Ok(())
+    }
+
+    /// Refuse action unless the connection logged in as the registration authority
+    pub fn require(&self, action: &str) -> Result<(), ServiceError> {
+        if self.logged_in {
+            Ok(())
+        } else {
+            Err(ServiceError::new(ErrorCode::Unauthorized, format!("only the registration authority may use {}", action)))
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// ---------------------------------------
+// File: leases.rs
+// Date: 18 Oct 2026
+// Description: Time-limited reservations of IDs during sign-up
+// ---------------------------------------
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use arke_common::error::{ErrorCode, ServiceError};
+use crate::store::{now_secs, User, UserStore};
+
+struct Lease {
+    token: String,
+    expires_at: Instant,
+}
+
+/// IDs reserved by users in the middle of signing up. Kept in memory only, a restart releases them all.
+///
+/// Every change to the user directory goes through here, so that taking the lock
+/// makes "is the ID free" and "take the ID" one step.
+pub struct Leases {
+    ttl: Duration,
+    /// How long the ID of a deleted account stays taken
+    reuse_cooldown: Duration,
+    leases: Mutex<HashMap<String, Lease>>,
+}
+
+impl Leases {
+    pub fn new(ttl: Duration, reuse_cooldown: Duration) -> Self {
+        Self { ttl, reuse_cooldown, leases: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn reuse_cooldown(&self) -> Duration {
+        self.reuse_cooldown
+    }
+
+    /// Number of IDs reserved right now
+    pub fn active(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Lease>> {
+        // A panic cannot leave a half-written entry in the map
+        let mut leases = self.leases.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Expired leases release their ID here, before anyone looks at the map
+        let now = Instant::now();
+        leases.retain(|_, lease| lease.expires_at > now);
+        leases
+    }
+
+    /// Accounts deleted at or before this time have given up their ID
+    fn deleted_before(&self) -> u64 {
+        now_secs().saturating_sub(self.reuse_cooldown.as_secs())
+    }
+
+    fn is_taken(&self, store: &dyn UserStore, id_string: &str) -> Result<bool, ServiceError> {
+        let deleted_before = self.deleted_before();
+        Ok(store.get(id_string)?.is_some_and(|user| user.holds_id(deleted_before)))
+    }
+
+    /// Whether the ID can be signed up for: no account holds it and no lease reserves it
+    pub fn is_available(&self, store: &dyn UserStore, id_string: &str) -> Result<bool, ServiceError> {
+        let leases = self.lock();
+        Ok(!leases.contains_key(id_string) && !self.is_taken(store, id_string)?)
+    }
+
+    /// Reserve the ID unless it is taken or reserved. Returns the lease token.
+    pub fn reserve(&self, store: &dyn UserStore, id_string: &str) -> Result<Option<String>, ServiceError> {
+        let mut leases = self.lock();
+        if leases.contains_key(id_string) || self.is_taken(store, id_string)? {
+            return Ok(None);
+        }
+        let token = format!("{:032x}", rand::random::<u128>());
+        leases.insert(id_string.to_string(), Lease {
+            token: token.clone(),
+            expires_at: Instant::now() + self.ttl,
+        });
+        Ok(Some(token))
+    }
+
+    /// Add the user if lease_token holds the ID. Returns false if the ID was taken without a lease.
+    pub fn commit(&self, store: &dyn UserStore, id_string: &str, lease_token: &str) -> Result<bool, ServiceError> {
+        let mut leases = self.lock();
+        match leases.get(id_string) {
+            Some(lease) if lease.token == lease_token => {},
+            _ => return Err(ServiceError::new(ErrorCode::InvalidLease,
+                "no lease with this token holds the ID, it may have expired")),
+        }
+        let inserted = store.insert(&User::new(id_string), self.deleted_before())?;
+        leases.remove(id_string);
+        Ok(inserted)
+    }
+
+    /// Drop the lease if lease_token holds the ID. Releasing an expired lease is not an error.
+    pub fn release(&self, id_string: &str, lease_token: &str) {
+        let mut leases = self.lock();
+        if leases.get(id_string).is_some_and(|lease| lease.token == lease_token) {
+            leases.remove(id_string);
+        }
+    }
+
+    /// Add the user unless the ID is taken or reserved, for clients that do not reserve first
+    pub fn insert_unreserved(&self, store: &dyn UserStore, id_string: &str) -> Result<bool, ServiceError> {
+        let leases = self.lock();
+        if leases.contains_key(id_string) {
+            return Ok(false);
+        }
+        store.insert(&User::new(id_string), self.deleted_before())
+    }
+}
@@ -1,12 +1,116 @@
 use tokio::runtime::Runtime;
+use arke_common::admin::AdminKey;
+use arke_common::messages::validate_id_string;
+use arke_common::tls::load_acceptor;
+mod config;
+use config::{Command, Config};
 mod server;
 use server::Server; 
+mod enrollment;
+use enrollment::EnrollmentVerifier;
+mod leases;
+use leases::Leases;
+mod store;
+use store::{lock_store, now_secs, open_store, JsonUserStore, StorageBackend};
 
 fn main() {
-    println!("Server running...");
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Held until the process exits, so a migrate cannot write while a server runs on the same data directory
+    let _lock = match lock_store(&config.data_dir) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("X {}", e.message);
+            std::process::exit(1);
+        }
+    };
+
+    let users_db = match open_store(config.storage, &config.data_dir) {
+        Ok(users_db) => users_db,
+        Err(e) => {
+            eprintln!("X Could not open the user directory: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(Command::Migrate { from }) = &config.command {
+        if config.storage == StorageBackend::Json {
+            eprintln!("X migrate imports into another backend, choose one with --storage");
+            std::process::exit(1);
+        }
+        let source = config.migrate_source(from.as_ref());
+        let imported = JsonUserStore::new(&source).load()
+            .and_then(|users| users_db.insert_all(&users).map(|inserted| (users.len(), inserted)));
+        match imported {
+            Ok((total, inserted)) => {
+                println!("✓ Imported {} users from {}, skipped {} already present", inserted, source.display(), total - inserted);
+            },
+            Err(e) => {
+                eprintln!("X Migration failed: {}", e.message);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Run only while no server holds the store, arke-admin suspend does the same on a running server
+    if let Some(Command::Revoke { id_string }) = &config.command {
+        match validate_id_string(id_string).and_then(|()| users_db.suspend(id_string, now_secs())) {
+            Ok(true) => println!("✓ Revoked {}, it is suspended and cannot be enrolled again", id_string),
+            Ok(false) => println!("✓ {} was already suspended", id_string),
+            Err(e) => {
+                eprintln!("X Revocation failed: {}", e.message);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Created on first start, arke-admin needs a copy of it
+    let admin = match config.admin_bind {
+        Some(admin_bind) => match AdminKey::load_or_create(&config.admin_key_path()) {
+            Ok(admin_key) => Some((admin_bind, admin_key)),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let tls = match &config.tls {
+        Some(files) => match load_acceptor(files) {
+            Ok(acceptor) => Some(acceptor),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let enrollment = match EnrollmentVerifier::new(config.enrollment_key.clone()) {
+        Ok(enrollment) => enrollment,
+        Err(e) => {
+            eprintln!("X {}, start the registration authority once first", e.message);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Server running on {}{}...", config.bind, if tls.is_some() { " over TLS" } else { "" });
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
-        let server = Server::new("src/all_users.json").await;
-        server.start().await.unwrap();
+        let server = Server::new(users_db,
+                                 Leases::new(config.lease_ttl, config.reuse_cooldown),
+                                 enrollment,
+                                 config.storage,
+                                 config.data_dir.clone()).await;
+        server.start(config.bind, admin, tls).await.unwrap();
     });
-}
\ No newline at end of file
+}
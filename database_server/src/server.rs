@@ -4,129 +4,266 @@
 // Description: Sign up new user (database server-side)
 // ---------------------------------------
 #![allow(non_camel_case_types)]
-#![allow(private_in_public)]
 #![allow(unused_variables)]
 
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use serde::{Serialize, Deserialize};
 use tokio::net::TcpListener;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use serde_json::Value;
-use std::path::Path;
-use serde_json::json;
-use std::sync::Arc;
-
-#[derive(Serialize, Deserialize, Debug)]
-struct User {
-    id_string: String,
-}
-
-#[derive(Clone)]
-pub struct UserDatabase {
-    path: PathBuf,
-}
-
-impl UserDatabase {
-    pub fn new(path: impl AsRef<Path>) -> Self {
-        Self {
-            path: path.as_ref().to_path_buf(),
-        }
-    }
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use arke_common::admin::{serve_admin, serve_counted_connection, AdminKey, RequestStats};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{validate_id_string, Blob, Request, Response, UserSummary};
+use arke_common::tls::{accept, TlsAcceptor};
+use crate::enrollment::{EnrollmentVerifier, RegistrarSession};
+use crate::leases::Leases;
+use crate::store::{now_secs, open_store, StorageBackend, UserStore};
 
-    pub async fn load(&self) -> Result<Vec<User>, Box<dyn std::error::Error>> {
-        let contents = tokio::fs::read(&self.path).await?;
-        let users: Vec<User> = serde_json::from_slice(&contents)?;
-        Ok(users)
-    }
+// admin_list_users never returns more than this many accounts at once
+const MAX_LIST_LIMIT: u32 = 10_000;
 
-    pub async fn save(&self, users: &[User]) -> Result<(), Box<dyn std::error::Error>> {
-        let contents = serde_json::to_vec(users)?;
-        tokio::fs::write(&self.path, &contents).await?;
-        Ok(())
-    }
-}   
+/// The user directory in use, replaced as a whole by admin_reload
+type SharedStore = Arc<RwLock<Arc<dyn UserStore>>>;
 
 #[derive(Clone)]
 pub struct Server {
-    users_db: Arc<UserDatabase>,
+    users_db: SharedStore,
+    leases: Arc<Leases>,
+    enrollment: Arc<EnrollmentVerifier>,
+    stats: Arc<RequestStats>,
+    // Where admin_reload reopens the user directory from
+    storage: StorageBackend,
+    data_dir: PathBuf,
 }
 
 impl Server {
-    pub async fn new(user_db_path: impl AsRef<Path>) -> Self {
-        let users_db = Arc::new(UserDatabase::new(user_db_path));
-        Self {users_db}
+    pub async fn new(users_db: Box<dyn UserStore>,
+                     leases: Leases,
+                     enrollment: EnrollmentVerifier,
+                     storage: StorageBackend,
+                     data_dir: PathBuf) -> Self {
+        Self {
+            users_db: Arc::new(RwLock::new(Arc::from(users_db))),
+            leases: Arc::new(leases),
+            enrollment: Arc::new(enrollment),
+            stats: Arc::new(RequestStats::new()),
+            storage,
+            data_dir,
+        }
     }
 
-    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
-        let listener = TcpListener::bind("127.0.0.1:8080").await?;
+    /// Serve clients on bind, over TLS if there is an acceptor, and arke-admin on the admin address if there is one
+    pub async fn start(self, bind: SocketAddr, admin: Option<(SocketAddr, AdminKey)>, tls: Option<TlsAcceptor>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((admin_bind, admin_key)) = admin {
+            let server = self.clone();
+            let stats = Arc::clone(&self.stats);
+            let admin_tls = tls.clone();
+            println!("Admin endpoint running on {}{}...", admin_bind, if tls.is_some() { " over TLS" } else { "" });
+            tokio::spawn(async move {
+                let served = serve_admin(admin_bind, Arc::new(admin_key), stats, admin_tls, move |request| {
+                    let server = server.clone();
+                    async move { server.process_admin_request(request) }
+                }).await;
+                if let Err(e) = served {
+                    eprintln!("X Admin endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        let listener = TcpListener::bind(bind).await?;
         loop {
-            let (mut socket, _) = listener.accept().await?;
+            let (socket, peer) = listener.accept().await?;
             let users_db = Arc::clone(&self.users_db);
+            let leases = Arc::clone(&self.leases);
+            let enrollment = Arc::clone(&self.enrollment);
+            let stats = Arc::clone(&self.stats);
+            let tls = tls.clone();
 
             tokio::spawn(async move {
-                let mut buf = vec![0; 1024];
-                loop {
-                    match socket.read(&mut buf).await {
-                        // Connection closed
-                        Ok(n) if n == 0 => return,
-                        // Connection open
-                        Ok(n) => {
-                            let request: Value = serde_json::from_slice(&buf[..n]).unwrap();
-                            // Handle request
-                            let response = process_request(request, users_db.clone()).await;
-                            let response_bytes = serde_json::to_vec(&response).unwrap();
-                            if let Err(e) = socket.write_all(&response_bytes).await {
-                                eprintln!("failed to write to socket; err = {:?}", e);
-                                return;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("failed to read from socket; err = {:?}", e);
-                            return;
-                        }
-                    }
-                }
+                let socket = match accept(tls.as_ref(), socket).await {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        eprintln!("X TLS handshake failed; err = {}", e);
+                        return;
+                    },
+                };
+                // Shared with every request of the connection, the handler cannot lend it to them
+                let session = Arc::new(Mutex::new(RegistrarSession::default()));
+                serve_counted_connection(socket, stats, move |request| {
+                    process_request(request, current_store(&users_db), leases.clone(), enrollment.clone(), session.clone(), peer)
+                }).await
             });
         }
     }
+
+    fn process_admin_request(&self, request: Request) -> Result<Response, ServiceError> {
+        match request {
+            Request::AdminHealth => {
+                let details = BTreeMap::from([
+                    ("storage".to_string(), format!("{:?}", self.storage).to_lowercase()),
+                    ("data_dir".to_string(), self.data_dir.display().to_string()),
+                    ("active_leases".to_string(), self.leases.active().to_string()),
+                    ("lease_ttl_secs".to_string(), self.leases.ttl().as_secs().to_string()),
+                    ("reuse_cooldown_secs".to_string(), self.leases.reuse_cooldown().as_secs().to_string()),
+                ]);
+                Ok(Response::AdminHealth { health: self.stats.health("database_server", details) })
+            },
+
+            Request::AdminListUsers { prefix, status, limit } => {
+                let prefix = prefix.unwrap_or_default();
+                if !prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(ServiceError::malformed_field("prefix", "may only contain ASCII letters and digits"));
+                }
+                let limit = limit.min(MAX_LIST_LIMIT) as usize;
+                // One more than asked for tells whether the list was cut off
+                let mut users = current_store(&self.users_db).list(&prefix, status, limit + 1)?;
+                let truncated = users.len() > limit;
+                users.truncate(limit);
+                let users = users.into_iter()
+                    .map(|user| UserSummary { id_string: user.id_string, status: user.status, status_changed_at: user.status_changed_at })
+                    .collect();
+                Ok(Response::AdminUsers { users, truncated })
+            },
+
+            Request::AdminSuspendUser { id_string } => {
+                validate_id_string(&id_string)?;
+                let changed = current_store(&self.users_db).suspend(&id_string, now_secs())?;
+                println!("✓ {} suspended by an admin", id_string);
+                Ok(Response::AdminUserSuspended { changed })
+            },
+
+            Request::AdminReload => {
+                let reopened = open_store(self.storage, &self.data_dir)?;
+                self.enrollment.reload()?;
+                *self.users_db.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::from(reopened);
+                println!("✓ User directory and enrollment public key reloaded by an admin");
+                Ok(Response::AdminReloaded {
+                    detail: format!("reopened the {} user directory in {} and read the enrollment public key from {}",
+                                    format!("{:?}", self.storage).to_lowercase(), self.data_dir.display(),
+                                    self.enrollment.public_key_path().display()),
+                })
+            },
+
+            other => Err(ServiceError::new(ErrorCode::UnsupportedAction,
+                format!("{} is not an admin action of the database server", other.action()))),
+        }
+    }
+}
+
+fn current_store(users_db: &SharedStore) -> Arc<dyn UserStore> {
+    // Only ever replaced as a whole, a panic cannot leave it half-written
+    Arc::clone(&users_db.read().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+fn lock(session: &Mutex<RegistrarSession>) -> MutexGuard<'_, RegistrarSession> {
+    // A panic cannot leave the session half-written
+    session.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
 }
 
-async fn process_request(request: Value, users_db: Arc<UserDatabase>) -> Value {
-    match request["action"].as_str() {
-        Some("add_user") => {
-            let id_string = request["id_string"].as_str().unwrap().to_string();
+async fn process_request(request: Request,
+                         users_db: Arc<dyn UserStore>,
+                         leases: Arc<Leases>,
+                         enrollment: Arc<EnrollmentVerifier>,
+                         session: Arc<Mutex<RegistrarSession>>,
+                         peer: SocketAddr) -> Result<Response, ServiceError> {
+    if matches!(request, Request::AddUser { .. } | Request::ReserveId { .. } | Request::CommitId { .. } | Request::ReleaseId { .. }) {
+        lock(&session).require(request.action())?;
+    }
+    match request {
+        Request::RegistrarChallenge => {
+            let nonce = lock(&session).challenge();
+            Ok(Response::RegistrarChallenge { nonce: Blob(nonce) })
+        },
+
+        Request::RegistrarLogin { signature } => {
+            lock(&session).login(&enrollment, &signature)?;
+            println!("✓ Registration authority logged in from {}", peer);
+            Ok(Response::RegistrarLoggedIn)
+        },
 
+        Request::AddUser { id_string, enrollment_token, issued_at } => {
+            validate_id_string(&id_string)?;
             println!("- Adding user");
-            let user = User {id_string};
-            // Load users from the JSON file
-            let mut users = users_db.load().await.unwrap();
-            // Add the new user and save the updated list
-            users.push(user);
-            users_db.save(&users).await.unwrap();
+            enrollment.verify(&id_string, None, &enrollment_token, issued_at)?;
+            // Checked again here, another user may have taken or reserved the ID since check_uniqueness
+            if !leases.insert_unreserved(users_db.as_ref(), &id_string)? {
+                println!("X ID taken in the meantime");
+                return Ok(Response::IdTaken);
+            }
             println!("✓ User added");
 
-            Value::String("✓ User added".into())
+            Ok(Response::UserAdded)
         },
 
-        Some("check_uniqueness") => {
-            let users = users_db.load().await.unwrap();
-            if let Some(id_string) = request.get("id_string") {
-                println!("- Checking uniqueness of the id_string");
-                let user_exists = users.iter().find(|user| user.id_string == id_string.as_str().unwrap());
-                if let Some(user) = user_exists {
-                    println!("X Check not passed");
-                    json!({ "status": "error", "message": "User with same ID found"})
-                } else {
-                    println!("✓ Check passed");
-                    json!({ "status": "success", "message": "✓ No user with same ID"})
-                }
+        Request::CheckUniqueness { id_string } => {
+            validate_id_string(&id_string)?;
+            println!("- Checking uniqueness of the id_string");
+            if leases.is_available(users_db.as_ref(), &id_string)? {
+                println!("✓ Check passed");
+                Ok(Response::IdAvailable)
             } else {
-                json!({ "status": "error", "message": "missing ID" })
+                println!("X Check not passed");
+                Ok(Response::IdTaken)
             }
         },
 
-        _ => {
-            json!({ "status": "error", "message": "invalid action" })
+        Request::ReserveId { id_string } => {
+            validate_id_string(&id_string)?;
+            println!("- Reserving the id_string");
+            match leases.reserve(users_db.as_ref(), &id_string)? {
+                Some(lease_token) => {
+                    println!("✓ ID reserved for {} s", leases.ttl().as_secs());
+                    Ok(Response::IdReserved { lease_token, ttl_secs: leases.ttl().as_secs() })
+                },
+                None => {
+                    println!("X ID taken or reserved");
+                    Ok(Response::IdTaken)
+                },
+            }
         },
+
+        Request::CommitId { id_string, lease_token, enrollment_token, issued_at } => {
+            validate_id_string(&id_string)?;
+            println!("- Adding reserved user");
+            enrollment.verify(&id_string, Some(&lease_token), &enrollment_token, issued_at)?;
+            if !leases.commit(users_db.as_ref(), &id_string, &lease_token)? {
+                println!("X ID taken in the meantime");
+                return Ok(Response::IdTaken);
+            }
+            println!("✓ User added");
+
+            Ok(Response::UserAdded)
+        },
+
+        Request::GetUserStatus { id_string } => {
+            validate_id_string(&id_string)?;
+            match users_db.get(&id_string)? {
+                Some(user) => Ok(Response::UserStatus { status: user.status }),
+                None => Ok(Response::UserNotFound),
+            }
+        },
+
+        Request::DeleteUser { id_string, deletion_token, issued_at } => {
+            validate_id_string(&id_string)?;
+            println!("- Deleting user");
+            enrollment.verify_deletion(&id_string, &deletion_token, issued_at)?;
+            if !users_db.delete(&id_string, now_secs())? {
+                println!("X No active account with this ID");
+                return Ok(Response::UserNotFound);
+            }
+            println!("✓ User deleted");
+
+            Ok(Response::UserDeleted)
+        },
+
+        Request::ReleaseId { id_string, lease_token } => {
+            validate_id_string(&id_string)?;
+            leases.release(&id_string, &lease_token);
+            println!("✓ Reservation released");
+
+            Ok(Response::IdReleased)
+        },
+
+        other => Err(ServiceError::unsupported_action(other.action())),
     }
-}
\ No newline at end of file
+}
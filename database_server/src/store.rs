@@ -0,0 +1,397 @@
+// ---------------------------------------
+// File: store.rs
+// Date: 18 Oct 2026
+// Description: Storage backends of the user directory
+// ---------------------------------------
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::ValueEnum;
+use serde::{Serialize, Deserialize};
+use rusqlite::{params, Connection, OptionalExtension};
+use arke_common::durable_file::{read_json_list, write_json_list};
+use arke_common::error::ServiceError;
+use arke_common::messages::AccountStatus;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "StoredUser")]
+pub struct User {
+    pub id_string: String,
+    pub status: AccountStatus,
+    /// Seconds since the Unix epoch when the status last changed
+    pub status_changed_at: u64,
+}
+
+/// A user as written by any version of the JSON backend
+#[derive(Deserialize)]
+struct StoredUser {
+    id_string: String,
+    #[serde(default)]
+    status: Option<AccountStatus>,
+    #[serde(default)]
+    status_changed_at: u64,
+    // Before the status field, fraudulent IDs were marked revoked
+    #[serde(default)]
+    revoked: bool,
+}
+
+impl From<StoredUser> for User {
+    fn from(stored: StoredUser) -> Self {
+        let status = match (stored.status, stored.revoked) {
+            (Some(status), _) => status,
+            (None, true) => AccountStatus::Suspended,
+            (None, false) => AccountStatus::Active,
+        };
+        User { id_string: stored.id_string, status, status_changed_at: stored.status_changed_at }
+    }
+}
+
+impl User {
+    /// A user enrolling now
+    pub fn new(id_string: impl Into<String>) -> Self {
+        Self { id_string: id_string.into(), status: AccountStatus::Active, status_changed_at: now_secs() }
+    }
+
+    /// Whether this account keeps its ID from being taken by someone else.
+    /// Only an account deleted at or before deleted_before gives its ID up.
+    pub fn holds_id(&self, deleted_before: u64) -> bool {
+        self.status != AccountStatus::Deleted || self.status_changed_at > deleted_before
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0)
+}
+
+/// Where the user directory is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// all_users.json, rewritten whole on every insert
+    Json,
+    /// users.sqlite3, an embedded SQLite database
+    Sqlite,
+}
+
+/// The user directory. Every method is atomic, so concurrent requests cannot lose writes.
+pub trait UserStore: Send + Sync {
+    /// The account with this ID, whatever its status
+    fn get(&self, id_string: &str) -> Result<Option<User>, ServiceError>;
+
+    /// Add a user unless its ID is taken, checking and inserting at once. An account deleted at or
+    /// before deleted_before does not take its ID any more and is replaced.
+    /// Returns false if the ID was taken.
+    fn insert(&self, user: &User, deleted_before: u64) -> Result<bool, ServiceError>;
+
+    /// Add several users as they are, skipping the IDs that have an account. Returns the number of users added.
+    fn insert_all(&self, users: &[User]) -> Result<usize, ServiceError>;
+
+    /// Mark an active account as deleted. Returns false if there is no active account with this ID.
+    fn delete(&self, id_string: &str, now: u64) -> Result<bool, ServiceError>;
+
+    /// Mark the ID as suspended, adding it if it was never enrolled. Returns false if it already was suspended.
+    fn suspend(&self, id_string: &str, now: u64) -> Result<bool, ServiceError>;
+
+    /// Accounts whose ID starts with prefix, only those with status if given, in order of ID. At most limit of them.
+    fn list(&self, prefix: &str, status: Option<AccountStatus>, limit: usize) -> Result<Vec<User>, ServiceError>;
+}
+
+/// Open the store of the given backend in data_dir
+pub fn open_store(backend: StorageBackend, data_dir: &Path) -> Result<Box<dyn UserStore>, ServiceError> {
+    match backend {
+        StorageBackend::Json => {
+            let store = JsonUserStore::new(json_path(data_dir));
+            // Read once here, so a corrupt file stops the start rather than failing every request
+            store.load()?;
+            Ok(Box::new(store))
+        },
+        StorageBackend::Sqlite => Ok(Box::new(SqliteUserStore::open(&sqlite_path(data_dir))?)),
+    }
+}
+
+/// Hold on the user directory of a data directory, so a migrate run from the command line cannot
+/// write to it while a server does. Released when dropped, and by the system when the process dies.
+pub struct StoreLock {
+    _file: File,
+}
+
+/// Take the hold on the user directory in data_dir, failing at once if another process has it
+pub fn lock_store(data_dir: &Path) -> Result<StoreLock, ServiceError> {
+    let path = lock_path(data_dir);
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path)
+        .map_err(|e| ServiceError::storage(format!("{}: {}", path.display(), e)))?;
+    match file.try_lock() {
+        Ok(()) => Ok(StoreLock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err(ServiceError::storage(format!(
+            "the user directory in {} is in use by another database server process, stop it first", data_dir.display()))),
+        Err(TryLockError::Error(e)) => Err(ServiceError::storage(format!("{}: {}", path.display(), e))),
+    }
+}
+
+pub fn lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("users.lock")
+}
+
+pub fn json_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("all_users.json")
+}
+
+pub fn sqlite_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("users.sqlite3")
+}
+
+/// Every user as a JSON array in one file. Simple, but each insert rewrites the whole file.
+pub struct JsonUserStore {
+    path: PathBuf,
+    // Held from load to save, so two inserts cannot overwrite each other
+    lock: Mutex<()>,
+}
+
+impl JsonUserStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// A missing file means no user has signed up yet, a file that does not parse is an error
+    pub fn load(&self) -> Result<Vec<User>, ServiceError> {
+        read_json_list(&self.path).map_err(ServiceError::storage)
+    }
+
+    /// Replaces the file atomically and keeps the previous one as all_users.json.bak
+    fn save(&self, users: &[User]) -> Result<(), ServiceError> {
+        write_json_list(&self.path, users).map_err(ServiceError::storage)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        // The guarded data is (), a panic while holding the lock leaves nothing inconsistent
+        self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl UserStore for JsonUserStore {
+    fn get(&self, id_string: &str) -> Result<Option<User>, ServiceError> {
+        let _guard = self.lock();
+        Ok(self.load()?.into_iter().find(|user| user.id_string == id_string))
+    }
+
+    fn insert(&self, user: &User, deleted_before: u64) -> Result<bool, ServiceError> {
+        let _guard = self.lock();
+        let mut users = self.load()?;
+        match users.iter_mut().find(|existing| existing.id_string == user.id_string) {
+            Some(existing) if existing.holds_id(deleted_before) => return Ok(false),
+            Some(existing) => *existing = user.clone(),
+            None => users.push(user.clone()),
+        }
+        self.save(&users)?;
+        Ok(true)
+    }
+
+    fn insert_all(&self, new_users: &[User]) -> Result<usize, ServiceError> {
+        let _guard = self.lock();
+        let mut users = self.load()?;
+        let before = users.len();
+        for user in new_users {
+            if !users.iter().any(|existing| existing.id_string == user.id_string) {
+                users.push(user.clone());
+            }
+        }
+        self.save(&users)?;
+        Ok(users.len() - before)
+    }
+
+    fn delete(&self, id_string: &str, now: u64) -> Result<bool, ServiceError> {
+        let _guard = self.lock();
+        let mut users = self.load()?;
+        match users.iter_mut().find(|user| user.id_string == id_string) {
+            Some(user) if user.status == AccountStatus::Active => {
+                user.status = AccountStatus::Deleted;
+                user.status_changed_at = now;
+            },
+            _ => return Ok(false),
+        }
+        self.save(&users)?;
+        Ok(true)
+    }
+
+    fn suspend(&self, id_string: &str, now: u64) -> Result<bool, ServiceError> {
+        let _guard = self.lock();
+        let mut users = self.load()?;
+        let suspended = User { id_string: id_string.to_string(), status: AccountStatus::Suspended, status_changed_at: now };
+        match users.iter_mut().find(|user| user.id_string == id_string) {
+            Some(user) if user.status == AccountStatus::Suspended => return Ok(false),
+            Some(user) => *user = suspended,
+            None => users.push(suspended),
+        }
+        self.save(&users)?;
+        Ok(true)
+    }
+
+    fn list(&self, prefix: &str, status: Option<AccountStatus>, limit: usize) -> Result<Vec<User>, ServiceError> {
+        let _guard = self.lock();
+        let mut users: Vec<User> = self.load()?
+            .into_iter()
+            .filter(|user| user.id_string.starts_with(prefix) && status.is_none_or(|status| user.status == status))
+            .collect();
+        users.sort_by(|a, b| a.id_string.cmp(&b.id_string));
+        users.truncate(limit);
+        Ok(users)
+    }
+}
+
+/// Schema changes, applied in order to bring older databases up to date. Never edit an entry, add a new one.
+const SQLITE_MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS users (
+         id_string TEXT PRIMARY KEY NOT NULL
+     );",
+    "ALTER TABLE users ADD COLUMN revoked INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE users ADD COLUMN status TEXT NOT NULL DEFAULT 'active';
+     ALTER TABLE users ADD COLUMN status_changed_at INTEGER NOT NULL DEFAULT 0;
+     UPDATE users SET status = 'suspended' WHERE revoked = 1;
+     ALTER TABLE users DROP COLUMN revoked;",
+];
+
+/// Users in an embedded SQLite database, looked up through the primary key index
+pub struct SqliteUserStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteUserStore {
+    pub fn open(path: &Path) -> Result<Self, ServiceError> {
+        let mut connection = Connection::open(path)
+            .map_err(|e| ServiceError::storage(format!("could not open {}: {}", path.display(), e)))?;
+        Self::migrate(&mut connection)
+            .map_err(|e| ServiceError::storage(format!("could not set up {}: {}", path.display(), e)))?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    /// Apply the migrations the database has not seen yet, user_version counts the ones applied
+    fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+        connection.execute_batch("PRAGMA journal_mode = WAL;")?;
+        let applied: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (version, migration) in SQLITE_MIGRATIONS.iter().enumerate().skip(applied) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", version + 1)?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // SQLite rolls back an unfinished transaction, so the connection stays usable after a panic
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn parse_status(status: String) -> Result<AccountStatus, ServiceError> {
+    AccountStatus::parse(&status).ok_or_else(|| ServiceError::storage(format!("unknown account status {:?}", status)))
+}
+
+impl UserStore for SqliteUserStore {
+    fn get(&self, id_string: &str) -> Result<Option<User>, ServiceError> {
+        let connection = self.connection();
+        let found = connection
+            .query_row(
+                "SELECT status, status_changed_at FROM users WHERE id_string = ?1",
+                params![id_string],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)),
+            )
+            .optional()
+            .map_err(ServiceError::storage)?;
+        match found {
+            Some((status, status_changed_at)) => Ok(Some(User {
+                id_string: id_string.to_string(),
+                status: parse_status(status)?,
+                status_changed_at,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn insert(&self, user: &User, deleted_before: u64) -> Result<bool, ServiceError> {
+        let connection = self.connection();
+        // The primary key makes the uniqueness check and the insert one statement
+        let inserted = connection
+            .execute(
+                "INSERT INTO users (id_string, status, status_changed_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (id_string) DO UPDATE SET status = excluded.status, status_changed_at = excluded.status_changed_at
+                 WHERE users.status = 'deleted' AND users.status_changed_at <= ?4",
+                params![user.id_string, user.status.as_str(), user.status_changed_at, deleted_before],
+            )
+            .map_err(ServiceError::storage)?;
+        Ok(inserted == 1)
+    }
+
+    fn insert_all(&self, users: &[User]) -> Result<usize, ServiceError> {
+        let mut connection = self.connection();
+        // All or nothing, so a failed import can simply be run again
+        let transaction = connection.transaction().map_err(ServiceError::storage)?;
+        let mut inserted = 0;
+        {
+            let mut statement = transaction
+                .prepare("INSERT OR IGNORE INTO users (id_string, status, status_changed_at) VALUES (?1, ?2, ?3)")
+                .map_err(ServiceError::storage)?;
+            for user in users {
+                inserted += statement
+                    .execute(params![user.id_string, user.status.as_str(), user.status_changed_at])
+                    .map_err(ServiceError::storage)?;
+            }
+        }
+        transaction.commit().map_err(ServiceError::storage)?;
+        Ok(inserted)
+    }
+
+    fn delete(&self, id_string: &str, now: u64) -> Result<bool, ServiceError> {
+        let connection = self.connection();
+        let changed = connection
+            .execute(
+                "UPDATE users SET status = 'deleted', status_changed_at = ?2 WHERE id_string = ?1 AND status = 'active'",
+                params![id_string, now],
+            )
+            .map_err(ServiceError::storage)?;
+        Ok(changed == 1)
+    }
+
+    fn suspend(&self, id_string: &str, now: u64) -> Result<bool, ServiceError> {
+        let connection = self.connection();
+        let changed = connection
+            .execute(
+                "INSERT INTO users (id_string, status, status_changed_at) VALUES (?1, 'suspended', ?2)
+                 ON CONFLICT (id_string) DO UPDATE SET status = 'suspended', status_changed_at = ?2
+                 WHERE users.status != 'suspended'",
+                params![id_string, now],
+            )
+            .map_err(ServiceError::storage)?;
+        Ok(changed == 1)
+    }
+
+    fn list(&self, prefix: &str, status: Option<AccountStatus>, limit: usize) -> Result<Vec<User>, ServiceError> {
+        let connection = self.connection();
+        // substr rather than LIKE, which would treat _ and % in the prefix as wildcards
+        let mut statement = connection
+            .prepare(
+                "SELECT id_string, status, status_changed_at FROM users
+                 WHERE substr(id_string, 1, length(?1)) = ?1 AND (?2 IS NULL OR status = ?2)
+                 ORDER BY id_string LIMIT ?3",
+            )
+            .map_err(ServiceError::storage)?;
+        let rows = statement
+            .query_map(
+                params![prefix, status.as_ref().map(AccountStatus::as_str), limit as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, u64>(2)?)),
+            )
+            .map_err(ServiceError::storage)?;
+        let mut users = Vec::new();
+        for row in rows {
+            let (id_string, status, status_changed_at) = row.map_err(ServiceError::storage)?;
+            users.push(User { id_string, status: parse_status(status)?, status_changed_at });
+        }
+        Ok(users)
+    }
+}
@@ -0,0 +1,177 @@
+// ---------------------------------------
+// File: mod.rs
+// Date: 18 Oct 2026
+// Description: A database server started on a temporary data directory, for the integration tests
+// ---------------------------------------
+
+// The server runs as its own process, the way it is deployed. The tests play the registration
+// authority with an enrollment key of their own, whose public half the server is started with.
+
+// Every test file uses only some of the helpers
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ed25519_dalek::{Signer, SigningKey};
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::messages::{deletion_message, enrollment_message, registrar_login_message, Blob, Request, Response};
+
+// How long the server may take to start listening
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestServer {
+    pub addr: String,
+    pub data_dir: PathBuf,
+    enrollment_key: SigningKey,
+    process: Child,
+}
+
+impl TestServer {
+    /// Start a server with storage ("json" or "sqlite") on a new data directory
+    pub async fn start(storage: &str, lease_ttl_secs: u64) -> Self {
+        Self::start_in(temp_dir(), storage, lease_ttl_secs).await
+    }
+
+    /// Start a server on data_dir, which may already hold a user directory. The directory is removed when the server is dropped.
+    pub async fn start_in(data_dir: PathBuf, storage: &str, lease_ttl_secs: u64) -> Self {
+        Self::launch(data_dir, storage, lease_ttl_secs, None).await
+    }
+
+    /// Start a server whose deleted accounts give up their ID after reuse_cooldown_secs
+    pub async fn start_with_cooldown(storage: &str, reuse_cooldown_secs: u64) -> Self {
+        Self::launch(temp_dir(), storage, 600, Some(reuse_cooldown_secs)).await
+    }
+
+    async fn launch(data_dir: PathBuf, storage: &str, lease_ttl_secs: u64, reuse_cooldown_secs: Option<u64>) -> Self {
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let enrollment_key = SigningKey::from_bytes(&rand::random());
+        // Written the way the registration authority writes enrollment_public_key.txt
+        let public_key_path = data_dir.join("enrollment_public_key.txt");
+        std::fs::write(&public_key_path, format!("{}\n", base64::encode(enrollment_key.verifying_key().to_bytes()))).unwrap();
+
+        let addr = free_addr();
+        let mut command = command();
+        if let Some(reuse_cooldown_secs) = reuse_cooldown_secs {
+            command.arg("--reuse-cooldown").arg(reuse_cooldown_secs.to_string());
+        }
+        let process = command
+            .arg("--bind").arg(&addr)
+            .arg("--data-dir").arg(&data_dir)
+            .arg("--storage").arg(storage)
+            .arg("--lease-ttl").arg(lease_ttl_secs.to_string())
+            .arg("--enrollment-key").arg(&public_key_path)
+            .arg("--no-admin")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut server = TestServer { addr, data_dir, enrollment_key, process };
+        server.wait_until_listening().await;
+        server
+    }
+
+    async fn wait_until_listening(&mut self) {
+        let started = std::time::Instant::now();
+        while Connection::connect(&self.addr).await.is_err() {
+            if let Some(status) = self.process.try_wait().unwrap() {
+                panic!("the database server exited with {}", status);
+            }
+            assert!(started.elapsed() < START_TIMEOUT, "the database server did not start listening on {}", self.addr);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// A connection that did not log in, like the one of a client
+    pub async fn connect(&self) -> Connection {
+        Connection::connect(&self.addr).await.unwrap()
+    }
+
+    /// A connection logged in as the registration authority
+    pub async fn registrar(&self) -> Connection {
+        let mut connection = self.connect().await;
+        let nonce = match connection.call(Request::RegistrarChallenge).await.unwrap() {
+            Response::RegistrarChallenge { nonce } => nonce,
+            other => panic!("unexpected response to registrar_challenge: {:?}", other),
+        };
+        let signature = self.enrollment_key.sign(&registrar_login_message(&nonce.0));
+        let response = connection.call(Request::RegistrarLogin { signature: Blob(signature.to_bytes().to_vec()) }).await.unwrap();
+        assert_eq!(response, Response::RegistrarLoggedIn);
+        connection
+    }
+
+    /// The user directory of the JSON backend
+    pub fn users_file(&self) -> PathBuf {
+        self.data_dir.join("all_users.json")
+    }
+
+    /// The enrollment token the registration authority would issue now for id_string, and for the lease on it if any
+    pub fn enrollment_token(&self, id_string: &str, lease_token: Option<&str>) -> (Blob, u64) {
+        let issued_at = now_secs();
+        let signature = self.enrollment_key.sign(&enrollment_message(id_string, lease_token, issued_at));
+        (Blob(signature.to_bytes().to_vec()), issued_at)
+    }
+
+    pub async fn add_user(&self, connection: &mut Connection, id_string: &str) -> Result<Response, ProtocolError> {
+        let (enrollment_token, issued_at) = self.enrollment_token(id_string, None);
+        connection.call(Request::AddUser { id_string: id_string.to_string(), enrollment_token, issued_at }).await
+    }
+
+    pub async fn reserve(&self, connection: &mut Connection, id_string: &str) -> Result<Response, ProtocolError> {
+        connection.call(Request::ReserveId { id_string: id_string.to_string() }).await
+    }
+
+    pub async fn commit(&self, connection: &mut Connection, id_string: &str, lease_token: &str) -> Result<Response, ProtocolError> {
+        let (enrollment_token, issued_at) = self.enrollment_token(id_string, Some(lease_token));
+        connection.call(Request::CommitId {
+            id_string: id_string.to_string(),
+            lease_token: lease_token.to_string(),
+            enrollment_token,
+            issued_at,
+        }).await
+    }
+
+    /// Delete the account with a deletion token the registration authority would issue now
+    pub async fn delete_user(&self, connection: &mut Connection, id_string: &str) -> Result<Response, ProtocolError> {
+        let issued_at = now_secs();
+        let deletion_token = Blob(self.enrollment_key.sign(&deletion_message(id_string, issued_at)).to_bytes().to_vec());
+        connection.call(Request::DeleteUser { id_string: id_string.to_string(), deletion_token, issued_at }).await
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+/// The database server binary built for the tests
+pub fn command() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_database_server"))
+}
+
+pub fn temp_dir() -> PathBuf {
+    let n = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("arke_database_server_test_{}_{}", std::process::id(), n))
+}
+
+/// An ID of the right length no other test of this process uses
+pub fn new_id() -> String {
+    format!("id{:06}", NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0)
+}
+
+// A port nothing listens on right now
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
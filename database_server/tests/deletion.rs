@@ -0,0 +1,52 @@
+// ---------------------------------------
+// File: deletion.rs
+// Date: 18 Oct 2026
+// Description: The ID of a deleted account is given up once the reuse cooldown is over
+// ---------------------------------------
+
+mod common;
+
+use std::time::Duration;
+use arke_common::client::Connection;
+use arke_common::messages::{AccountStatus, Request, Response};
+use common::{new_id, TestServer};
+
+// Deletion times are kept in whole seconds, a cooldown of one second may be over at once
+const REUSE_COOLDOWN_SECS: u64 = 2;
+
+async fn status(connection: &mut Connection, id_string: &str) -> Option<AccountStatus> {
+    match connection.call(Request::GetUserStatus { id_string: id_string.to_string() }).await.unwrap() {
+        Response::UserStatus { status } => Some(status),
+        Response::UserNotFound => None,
+        other => panic!("unexpected response to get_user_status: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn deleted_id_is_taken_again_after_the_reuse_cooldown() {
+    for backend in ["json", "sqlite"] {
+        let server = TestServer::start_with_cooldown(backend, REUSE_COOLDOWN_SECS).await;
+        let mut registrar = server.registrar().await;
+        let id_string = new_id();
+        assert_eq!(server.add_user(&mut registrar, &id_string).await.unwrap(), Response::UserAdded);
+
+        assert_eq!(server.delete_user(&mut registrar, &id_string).await.unwrap(), Response::UserDeleted, "{} backend", backend);
+        assert_eq!(server.delete_user(&mut registrar, &id_string).await.unwrap(), Response::UserNotFound, "{} backend", backend);
+        // Nobody signs up for the ID during the cooldown
+        assert_eq!(server.reserve(&mut registrar, &id_string).await.unwrap(), Response::IdTaken, "{} backend", backend);
+        tokio::time::sleep(Duration::from_millis(REUSE_COOLDOWN_SECS * 1000 + 1100)).await;
+
+        // The registration authority tells a stale binding from these two answers and drops it
+        assert_eq!(status(&mut registrar, &id_string).await, Some(AccountStatus::Deleted), "{} backend", backend);
+        let check = registrar.call(Request::CheckUniqueness { id_string: id_string.clone() }).await.unwrap();
+        assert_eq!(check, Response::IdAvailable, "{} backend", backend);
+
+        // A new registration of the ID, the way the registration authority enrolls it
+        let lease_token = match server.reserve(&mut registrar, &id_string).await.unwrap() {
+            Response::IdReserved { lease_token, .. } => lease_token,
+            other => panic!("the ID was not reserved: {:?}", other),
+        };
+        assert_eq!(server.commit(&mut registrar, &id_string, &lease_token).await.unwrap(), Response::UserAdded, "{} backend", backend);
+        assert_eq!(status(&mut registrar, &id_string).await, Some(AccountStatus::Active), "{} backend", backend);
+    }
+}
@@ -0,0 +1,128 @@
+// ---------------------------------------
+// File: error_codes.rs
+// Date: 18 Oct 2026
+// Description: The database server rejects requests with the error code of the reason
+// ---------------------------------------
+
+mod common;
+
+use tokio::net::TcpStream;
+use arke_common::client::ProtocolError;
+use arke_common::error::ErrorCode;
+use arke_common::frame::{read_json, write_frame, write_json, FrameKind, MAX_FRAME_LEN};
+use arke_common::messages::{Blob, Request, RequestEnvelope, Response, ResponseEnvelope, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use common::{new_id, TestServer};
+
+fn code<T: std::fmt::Debug>(result: Result<T, ProtocolError>) -> ErrorCode {
+    result.unwrap_err().code().expect("the server rejected the request")
+}
+
+// Send one raw frame, as a peer of another version would, and read the answer
+async fn exchange(server: &TestServer, envelope: &RequestEnvelope) -> Response {
+    let mut socket = TcpStream::connect(&server.addr).await.unwrap();
+    write_json(&mut socket, envelope).await.unwrap();
+    let answer: ResponseEnvelope = read_json(&mut socket, MAX_FRAME_LEN).await.unwrap().unwrap();
+    answer.response
+}
+
+#[tokio::test]
+async fn taken_id_is_answered_with_id_taken() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+    let id_string = new_id();
+
+    assert_eq!(server.add_user(&mut registrar, &id_string).await.unwrap(), Response::UserAdded);
+    assert_eq!(server.add_user(&mut registrar, &id_string).await.unwrap(), Response::IdTaken);
+    assert_eq!(server.reserve(&mut registrar, &id_string).await.unwrap(), Response::IdTaken);
+    let check = registrar.call(Request::CheckUniqueness { id_string }).await.unwrap();
+    assert_eq!(check, Response::IdTaken);
+    let check = registrar.call(Request::CheckUniqueness { id_string: new_id() }).await.unwrap();
+    assert_eq!(check, Response::IdAvailable);
+}
+
+#[tokio::test]
+async fn malformed_id_is_rejected_as_malformed_field() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+
+    for id_string in ["short", "much_too_long", "a-b.c!d?"] {
+        assert_eq!(code(registrar.call(Request::CheckUniqueness { id_string: id_string.to_string() }).await), ErrorCode::MalformedField);
+        assert_eq!(code(server.add_user(&mut registrar, id_string).await), ErrorCode::MalformedField);
+    }
+}
+
+#[tokio::test]
+async fn enrolling_without_registrar_login_is_unauthorized() {
+    let server = TestServer::start("json", 600).await;
+    let mut client = server.connect().await;
+    let id_string = new_id();
+
+    assert_eq!(code(server.add_user(&mut client, &id_string).await), ErrorCode::Unauthorized);
+    assert_eq!(code(server.reserve(&mut client, &id_string).await), ErrorCode::Unauthorized);
+    // A login signed with another key does not help
+    client.call(Request::RegistrarChallenge).await.unwrap();
+    assert_eq!(code(client.call(Request::RegistrarLogin { signature: Blob(vec![0; 64]) }).await), ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn token_for_another_id_is_an_invalid_proof() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+    let (enrollment_token, issued_at) = server.enrollment_token(&new_id(), None);
+
+    let added = registrar.call(Request::AddUser { id_string: new_id(), enrollment_token, issued_at }).await;
+    assert_eq!(code(added), ErrorCode::InvalidProof);
+    let added = registrar.call(Request::AddUser { id_string: new_id(), enrollment_token: Blob(vec![0; 64]), issued_at }).await;
+    assert_eq!(code(added), ErrorCode::InvalidProof);
+}
+
+#[tokio::test]
+async fn unknown_lease_is_an_invalid_lease() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+
+    assert_eq!(code(server.commit(&mut registrar, &new_id(), "not a lease").await), ErrorCode::InvalidLease);
+}
+
+#[tokio::test]
+async fn unreadable_user_directory_is_a_storage_failure() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+    std::fs::write(server.users_file(), "not a user directory").unwrap();
+
+    assert_eq!(code(registrar.call(Request::CheckUniqueness { id_string: new_id() }).await), ErrorCode::StorageFailure);
+    assert_eq!(code(server.add_user(&mut registrar, &new_id()).await), ErrorCode::StorageFailure);
+}
+
+#[tokio::test]
+async fn request_for_another_server_is_an_unsupported_action() {
+    let server = TestServer::start("json", 600).await;
+    let mut client = server.connect().await;
+
+    assert_eq!(code(client.call(Request::GetRegistrarPublicKey).await), ErrorCode::UnsupportedAction);
+}
+
+#[tokio::test]
+async fn older_protocol_version_is_answered_with_version_mismatch() {
+    let server = TestServer::start("json", 600).await;
+    let mismatch = Response::VersionMismatch { min_version: MIN_PROTOCOL_VERSION, max_version: PROTOCOL_VERSION };
+
+    // A hello offering only versions from before MIN_PROTOCOL_VERSION
+    let hello = Request::Hello { min_version: 0, max_version: MIN_PROTOCOL_VERSION - 1, encodings: Vec::new() };
+    assert_eq!(exchange(&server, &RequestEnvelope { version: 0, request: hello }).await, mismatch);
+
+    // A request written for an older version, without a hello
+    let request = Request::CheckUniqueness { id_string: new_id() };
+    assert_eq!(exchange(&server, &RequestEnvelope { version: MIN_PROTOCOL_VERSION - 1, request }).await, mismatch);
+}
+
+#[tokio::test]
+async fn unknown_action_is_a_malformed_request() {
+    let server = TestServer::start("json", 600).await;
+    let mut socket = TcpStream::connect(&server.addr).await.unwrap();
+
+    let body = format!(r#"{{"version":{},"action":"no_such_action"}}"#, PROTOCOL_VERSION);
+    write_frame(&mut socket, FrameKind::Json, body.as_bytes()).await.unwrap();
+    let answer: ResponseEnvelope = read_json(&mut socket, MAX_FRAME_LEN).await.unwrap().unwrap();
+    assert!(matches!(answer.response, Response::Error { code: ErrorCode::MalformedRequest, .. }));
+}
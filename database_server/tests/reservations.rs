@@ -0,0 +1,121 @@
+// ---------------------------------------
+// File: reservations.rs
+// Date: 18 Oct 2026
+// Description: Concurrent reservations of one ID, and leases that expire
+// ---------------------------------------
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Barrier;
+use arke_common::error::ErrorCode;
+use arke_common::messages::{Request, Response};
+use common::{new_id, TestServer};
+
+const BACKENDS: [&str; 2] = ["json", "sqlite"];
+// Reservations sent at the same moment
+const PARALLEL: usize = 16;
+
+fn lease_token(response: Response) -> String {
+    match response {
+        Response::IdReserved { lease_token, .. } => lease_token,
+        other => panic!("the ID was not reserved: {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parallel_reservations_of_one_id_give_one_lease() {
+    for backend in BACKENDS {
+        let server = Arc::new(TestServer::start(backend, 600).await);
+        let id_string = new_id();
+
+        let barrier = Arc::new(Barrier::new(PARALLEL));
+        let mut tasks = Vec::new();
+        for _ in 0..PARALLEL {
+            let mut registrar = server.registrar().await;
+            let (server, barrier, id_string) = (Arc::clone(&server), Arc::clone(&barrier), id_string.clone());
+            tasks.push(tokio::spawn(async move {
+                barrier.wait().await;
+                let reserved = server.reserve(&mut registrar, &id_string).await.unwrap();
+                // Whoever got the lease commits on the same connection
+                match reserved {
+                    Response::IdReserved { lease_token, .. } => Some(server.commit(&mut registrar, &id_string, &lease_token).await.unwrap()),
+                    Response::IdTaken => None,
+                    other => panic!("unexpected response to reserve_id: {:?}", other),
+                }
+            }));
+        }
+        let mut committed = Vec::new();
+        for task in tasks {
+            committed.extend(task.await.unwrap());
+        }
+        assert_eq!(committed, vec![Response::UserAdded], "{} backend", backend);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserved_id_cannot_be_added_without_the_lease() {
+    for backend in BACKENDS {
+        let server = Arc::new(TestServer::start(backend, 600).await);
+        let mut registrar = server.registrar().await;
+        let id_string = new_id();
+        let lease_token = lease_token(server.reserve(&mut registrar, &id_string).await.unwrap());
+
+        // add_user of the same ID while the lease holds it, from other connections at once
+        let barrier = Arc::new(Barrier::new(PARALLEL));
+        let mut tasks = Vec::new();
+        for _ in 0..PARALLEL {
+            let mut other = server.registrar().await;
+            let (server, barrier, id_string) = (Arc::clone(&server), Arc::clone(&barrier), id_string.clone());
+            tasks.push(tokio::spawn(async move {
+                barrier.wait().await;
+                server.add_user(&mut other, &id_string).await.unwrap()
+            }));
+        }
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), Response::IdTaken, "{} backend", backend);
+        }
+        assert_eq!(server.commit(&mut registrar, &id_string, &lease_token).await.unwrap(), Response::UserAdded, "{} backend", backend);
+    }
+}
+
+#[tokio::test]
+async fn expired_lease_releases_the_id() {
+    for backend in BACKENDS {
+        let server = TestServer::start(backend, 1).await;
+        let mut registrar = server.registrar().await;
+        let id_string = new_id();
+
+        let expired = lease_token(server.reserve(&mut registrar, &id_string).await.unwrap());
+        assert_eq!(server.reserve(&mut registrar, &id_string).await.unwrap(), Response::IdTaken);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+
+        // The ID is free again, and the old lease no longer commits it
+        let check = registrar.call(Request::CheckUniqueness { id_string: id_string.clone() }).await.unwrap();
+        assert_eq!(check, Response::IdAvailable, "{} backend", backend);
+        let commit = server.commit(&mut registrar, &id_string, &expired).await;
+        assert_eq!(commit.unwrap_err().code(), Some(ErrorCode::InvalidLease), "{} backend", backend);
+
+        // Someone else reserves it, the holder of the expired lease cannot take it back
+        let fresh = lease_token(server.reserve(&mut registrar, &id_string).await.unwrap());
+        let commit = server.commit(&mut registrar, &id_string, &expired).await;
+        assert_eq!(commit.unwrap_err().code(), Some(ErrorCode::InvalidLease), "{} backend", backend);
+        assert_eq!(server.commit(&mut registrar, &id_string, &fresh).await.unwrap(), Response::UserAdded, "{} backend", backend);
+    }
+}
+
+#[tokio::test]
+async fn released_lease_frees_the_id_at_once() {
+    let server = TestServer::start("json", 600).await;
+    let mut registrar = server.registrar().await;
+    let id_string = new_id();
+
+    let lease_token = lease_token(server.reserve(&mut registrar, &id_string).await.unwrap());
+    let released = registrar.call(Request::ReleaseId { id_string: id_string.clone(), lease_token: lease_token.clone() }).await.unwrap();
+    assert_eq!(released, Response::IdReleased);
+    assert!(matches!(server.reserve(&mut registrar, &id_string).await.unwrap(), Response::IdReserved { .. }));
+    // The released lease does not commit the ID reserved since
+    let commit = server.commit(&mut registrar, &id_string, &lease_token).await;
+    assert_eq!(commit.unwrap_err().code(), Some(ErrorCode::InvalidLease));
+}
@@ -0,0 +1,58 @@
+// ---------------------------------------
+// File: revocation.rs
+// Date: 18 Oct 2026
+// Description: Revoking an ID from the command line, only while no server runs on the data directory
+// ---------------------------------------
+
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+use arke_common::messages::{AccountStatus, Request, Response};
+use common::{command, new_id, temp_dir, TestServer};
+
+fn revoke(data_dir: &Path, id_string: &str) -> Output {
+    command().arg("--data-dir").arg(data_dir).arg("--storage").arg("sqlite").arg("revoke").arg(id_string).output().unwrap()
+}
+
+#[tokio::test]
+async fn revoked_id_is_suspended() {
+    let data_dir = temp_dir();
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let id_string = new_id();
+    let revoked = revoke(&data_dir, &id_string);
+    assert!(revoked.status.success(), "{}", String::from_utf8_lossy(&revoked.stderr));
+
+    let server = TestServer::start_in(data_dir, "sqlite", 600).await;
+    let mut registrar = server.registrar().await;
+    let status = registrar.call(Request::GetUserStatus { id_string: id_string.clone() }).await.unwrap();
+    assert_eq!(status, Response::UserStatus { status: AccountStatus::Suspended });
+    assert_eq!(server.add_user(&mut registrar, &id_string).await.unwrap(), Response::IdTaken);
+}
+
+#[tokio::test]
+async fn malformed_id_is_not_revoked() {
+    let data_dir = temp_dir();
+    std::fs::create_dir_all(&data_dir).unwrap();
+    for id_string in ["short", "much_too_long", "a-b.c!d?"] {
+        let revoked = revoke(&data_dir, id_string);
+        assert!(!revoked.status.success(), "{} was revoked", id_string);
+    }
+    let listed: rusqlite::Result<u32> = rusqlite::Connection::open(data_dir.join("users.sqlite3")).unwrap()
+        .query_row("SELECT count(*) FROM users", [], |row| row.get(0));
+    assert_eq!(listed.unwrap(), 0);
+    std::fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[tokio::test]
+async fn revoke_is_refused_while_a_server_runs() {
+    let server = TestServer::start("sqlite", 600).await;
+    let id_string = new_id();
+
+    let revoked = revoke(&server.data_dir, &id_string);
+    assert!(!revoked.status.success());
+    assert!(String::from_utf8_lossy(&revoked.stderr).contains("in use"), "{}", String::from_utf8_lossy(&revoked.stderr));
+    let mut client = server.connect().await;
+    let status = client.call(Request::GetUserStatus { id_string }).await.unwrap();
+    assert_eq!(status, Response::UserNotFound);
+}
@@ -0,0 +1,151 @@
+// ---------------------------------------
+// File: storage.rs
+// Date: 18 Oct 2026
+// Description: Concurrent enrollments on both storage backends, and user directories from older versions
+// ---------------------------------------
+
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Barrier;
+use arke_common::client::Connection;
+use arke_common::messages::{AccountStatus, Request, Response};
+use common::{command, new_id, temp_dir, TestServer};
+
+const BACKENDS: [&str; 2] = ["json", "sqlite"];
+// Enrollments sent at the same moment
+const PARALLEL: usize = 16;
+
+// Send add_user for every ID at the same moment, each on its own registrar connection
+async fn add_at_once(server: &Arc<TestServer>, ids: &[String]) -> Vec<Response> {
+    let barrier = Arc::new(Barrier::new(ids.len()));
+    let mut tasks = Vec::new();
+    for id_string in ids {
+        let mut registrar = server.registrar().await;
+        let (server, barrier, id_string) = (Arc::clone(server), Arc::clone(&barrier), id_string.clone());
+        tasks.push(tokio::spawn(async move {
+            barrier.wait().await;
+            server.add_user(&mut registrar, &id_string).await.unwrap()
+        }));
+    }
+    let mut responses = Vec::new();
+    for task in tasks {
+        responses.push(task.await.unwrap());
+    }
+    responses
+}
+
+async fn status(connection: &mut Connection, id_string: &str) -> Option<AccountStatus> {
+    match connection.call(Request::GetUserStatus { id_string: id_string.to_string() }).await.unwrap() {
+        Response::UserStatus { status } => Some(status),
+        Response::UserNotFound => None,
+        other => panic!("unexpected response to get_user_status: {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parallel_enrollments_of_one_id_add_it_once() {
+    for backend in BACKENDS {
+        let server = Arc::new(TestServer::start(backend, 600).await);
+        let id_string = new_id();
+
+        let responses = add_at_once(&server, &vec![id_string.clone(); PARALLEL]).await;
+        let added = responses.iter().filter(|response| **response == Response::UserAdded).count();
+        let taken = responses.iter().filter(|response| **response == Response::IdTaken).count();
+        assert_eq!((added, taken), (1, PARALLEL - 1), "{} backend", backend);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parallel_enrollments_of_different_ids_lose_none() {
+    for backend in BACKENDS {
+        let server = Arc::new(TestServer::start(backend, 600).await);
+        let ids: Vec<String> = (0..PARALLEL).map(|_| new_id()).collect();
+
+        let responses = add_at_once(&server, &ids).await;
+        assert!(responses.iter().all(|response| *response == Response::UserAdded), "{} backend: {:?}", backend, responses);
+        // Every insert made it to the user directory, none was overwritten by another
+        let mut client = server.connect().await;
+        for id_string in &ids {
+            assert_eq!(status(&mut client, id_string).await, Some(AccountStatus::Active), "{} backend", backend);
+        }
+    }
+}
+
+// A users.sqlite3 in data_dir as an older version of the server left it
+fn old_database(data_dir: &Path, schema: &str) {
+    std::fs::create_dir_all(data_dir).unwrap();
+    let connection = rusqlite::Connection::open(data_dir.join("users.sqlite3")).unwrap();
+    connection.execute_batch(schema).unwrap();
+}
+
+#[tokio::test]
+async fn sqlite_file_of_the_first_schema_is_migrated() {
+    let data_dir = temp_dir();
+    // The first SQLite backend did not number its schema
+    old_database(&data_dir, "CREATE TABLE users (id_string TEXT PRIMARY KEY NOT NULL);
+                             INSERT INTO users VALUES ('oldusr01');");
+    let server = TestServer::start_in(data_dir, "sqlite", 600).await;
+    let mut registrar = server.registrar().await;
+
+    assert_eq!(status(&mut registrar, "oldusr01").await, Some(AccountStatus::Active));
+    assert_eq!(server.reserve(&mut registrar, "oldusr01").await.unwrap(), Response::IdTaken);
+    let id_string = new_id();
+    assert_eq!(server.add_user(&mut registrar, &id_string).await.unwrap(), Response::UserAdded);
+    assert_eq!(status(&mut registrar, &id_string).await, Some(AccountStatus::Active));
+}
+
+#[tokio::test]
+async fn sqlite_file_with_revoked_ids_is_migrated() {
+    let data_dir = temp_dir();
+    old_database(&data_dir, "CREATE TABLE users (id_string TEXT PRIMARY KEY NOT NULL, revoked INTEGER NOT NULL DEFAULT 0);
+                             INSERT INTO users VALUES ('oldusr01', 0), ('oldusr02', 1);
+                             PRAGMA user_version = 2;");
+    let server = TestServer::start_in(data_dir, "sqlite", 600).await;
+    let mut registrar = server.registrar().await;
+
+    assert_eq!(status(&mut registrar, "oldusr01").await, Some(AccountStatus::Active));
+    // A revoked ID is suspended now, and stays taken
+    assert_eq!(status(&mut registrar, "oldusr02").await, Some(AccountStatus::Suspended));
+    assert_eq!(server.add_user(&mut registrar, "oldusr02").await.unwrap(), Response::IdTaken);
+}
+
+#[tokio::test]
+async fn json_directory_is_imported_into_sqlite() {
+    let data_dir = temp_dir();
+    std::fs::create_dir_all(&data_dir).unwrap();
+    // Written by every version of the JSON backend, from before the status field on
+    std::fs::write(data_dir.join("all_users.json"), r#"[
+        {"id_string": "jsonusr1"},
+        {"id_string": "jsonusr2", "revoked": true},
+        {"id_string": "jsonusr3", "status": "deleted", "status_changed_at": 1700000000}
+    ]"#).unwrap();
+
+    // Importing twice adds nothing the second time
+    for _ in 0..2 {
+        let migrated = command().arg("--data-dir").arg(&data_dir).arg("--storage").arg("sqlite").arg("migrate").output().unwrap();
+        assert!(migrated.status.success(), "{}", String::from_utf8_lossy(&migrated.stderr));
+    }
+
+    let server = TestServer::start_in(data_dir, "sqlite", 600).await;
+    let mut client = server.connect().await;
+    assert_eq!(status(&mut client, "jsonusr1").await, Some(AccountStatus::Active));
+    assert_eq!(status(&mut client, "jsonusr2").await, Some(AccountStatus::Suspended));
+    assert_eq!(status(&mut client, "jsonusr3").await, Some(AccountStatus::Deleted));
+    let listed: rusqlite::Result<u32> = rusqlite::Connection::open(server.data_dir.join("users.sqlite3")).unwrap()
+        .query_row("SELECT count(*) FROM users", [], |row| row.get(0));
+    assert_eq!(listed.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn migrate_is_refused_while_a_server_runs() {
+    let server = TestServer::start("sqlite", 600).await;
+    std::fs::write(server.users_file(), r#"[{"id_string": "jsonusr1"}]"#).unwrap();
+
+    let migrated = command().arg("--data-dir").arg(&server.data_dir).arg("--storage").arg("sqlite").arg("migrate").output().unwrap();
+    assert!(!migrated.status.success());
+    assert!(String::from_utf8_lossy(&migrated.stderr).contains("in use"), "{}", String::from_utf8_lossy(&migrated.stderr));
+    let mut client = server.connect().await;
+    assert_eq!(status(&mut client, "jsonusr1").await, None);
+}
@@ -0,0 +1,395 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Runtime configuration of the key-issuing authority
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use std::path::PathBuf;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use arke_common::config::{load_file, parse_socket_addr, prepare_data_dir, refuse_secrets, tls_files, validate_endpoint, ConfigError, TlsFiles};
+use arke_common::tls::{Pin, Pins};
+use crate::dkg::{DkgConfig, DkgFault, DkgPublicKey};
+use crate::key_issuing_authority::{NUMBER_OF_PARTICIPANTS, THRESHOLD};
+use crate::registrar_keys::RegistrarKeys;
+
+const DEFAULT_BIND: &str = "127.0.0.1:8081";
+// The admin endpoint only listens on localhost unless configured otherwise
+const DEFAULT_ADMIN_BIND: &str = "127.0.0.1:9081";
+const DEFAULT_DATA_DIR: &str = "data";
+// Issuer node i of the launcher listens on this port + i, for the DKG on this port + NUMBER_OF_PARTICIPANTS + i
+// and for arke-admin on this port + 2 * NUMBER_OF_PARTICIPANTS + i
+const DEFAULT_NODE_BASE_PORT: u16 = 8100;
+const DEFAULT_DKG_ROUND_TIMEOUT_SECS: u64 = 10;
+// Extraction jobs allowed to wait for a worker before new ones are refused as busy
+const DEFAULT_QUEUE_CAPACITY: usize = 64;
+const DEFAULT_REGISTRATION_AUTHORITY: &str = "127.0.0.1:8082";
+
+/// Command line flags, each of them can also be set through its environment variable
+#[derive(Parser, Debug)]
+#[command(about = "Arke key-issuing authority")]
+struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ARKE_KIA_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, env = "ARKE_KIA_BIND")]
+    bind: Option<String>,
+    /// Directory holding the state of the key-issuing authority
+    #[arg(long, env = "ARKE_KIA_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Run a new trusted setup and DKG even if a saved one exists, invalidating every issued key
+    #[arg(long)]
+    regenerate: bool,
+    /// Run as the issuer node holding only the share with this index
+    #[arg(long, env = "ARKE_KIA_NODE")]
+    node: Option<u32>,
+    /// Deal the shares and start every issuer as a separate node on localhost
+    #[arg(long, conflicts_with = "node")]
+    launch_nodes: bool,
+    /// Port of the first node started by --launch-nodes, the others follow it
+    #[arg(long, env = "ARKE_KIA_NODE_BASE_PORT")]
+    node_base_port: Option<u16>,
+    /// Let the nodes started by --launch-nodes generate their shares with the DKG instead of dealing them
+    #[arg(long, requires = "launch_nodes")]
+    dkg: bool,
+    /// DKG address of every issuer node in issuer order, repeat the flag (or separate with commas).
+    /// With --node, the node takes part in the DKG if it has no share yet
+    #[arg(long = "dkg-peer", env = "ARKE_KIA_DKG_PEERS", value_delimiter = ',')]
+    dkg_peers: Vec<String>,
+    /// DKG identity key of every issuer node in issuer order, as printed by --show-dkg-key, repeat the flag
+    /// (or separate with commas). Needed with --dkg-peer, the messages of the other nodes are checked with it
+    #[arg(long = "dkg-peer-key", env = "ARKE_KIA_DKG_PEER_KEYS", value_delimiter = ',')]
+    dkg_peer_keys: Vec<String>,
+    /// Print the public DKG identity key of this node for the --dkg-peer-key of the others and exit.
+    /// The key is created in the data directory if there is none yet
+    #[arg(long, conflicts_with_all = ["launch_nodes", "run_signer"])]
+    show_dkg_key: bool,
+    /// Run the DKG as the node of --node with the --dkg-peer nodes, save the share and exit, so a signer
+    /// started afterwards finds the share without ever talking to the network
+    #[arg(long, conflicts_with_all = ["launch_nodes", "run_signer"])]
+    dkg_only: bool,
+    /// Seconds to wait for the messages of one DKG round
+    #[arg(long, env = "ARKE_KIA_DKG_ROUND_TIMEOUT")]
+    dkg_round_timeout: Option<u64>,
+    /// File with the public setup the DKG nodes share
+    #[arg(long, env = "ARKE_KIA_PUBLIC_SETUP")]
+    public_setup: Option<PathBuf>,
+    /// Misbehave in the DKG, for tests
+    #[arg(long, value_enum, default_value_t = DkgFault::None)]
+    dkg_fault: DkgFault,
+    /// With --launch-nodes, do not start the node of this issuer (repeatable), for tests
+    #[arg(long = "skip-node")]
+    skip_nodes: Vec<u32>,
+    /// With --launch-nodes --dkg, start a node misbehaving in the DKG, as INDEX=FAULT (repeatable), for tests
+    #[arg(long = "faulty-node", value_parser = parse_faulty_node)]
+    faulty_nodes: Vec<(u32, DkgFault)>,
+    /// Address of the admin endpoint for arke-admin. The nodes started by --launch-nodes get one each
+    #[arg(long, env = "ARKE_KIA_ADMIN_BIND")]
+    admin_bind: Option<String>,
+    /// Do not start the admin endpoint
+    #[arg(long)]
+    no_admin: bool,
+    /// File with the admin key, created if it does not exist. admin.key in the data directory by default,
+    /// the nodes started by --launch-nodes share the one of the launcher
+    #[arg(long, env = "ARKE_KIA_ADMIN_KEY")]
+    admin_key: Option<PathBuf>,
+    /// Threads running VerifyID and BlindPartialExtract, one per CPU by default
+    #[arg(long, env = "ARKE_KIA_WORKERS")]
+    workers: Option<usize>,
+    /// Extraction jobs that may wait for a worker, further ones are refused as busy
+    #[arg(long, env = "ARKE_KIA_QUEUE_CAPACITY")]
+    queue_capacity: Option<usize>,
+    /// PEM certificate chain to serve clients over TLS with, together with --tls-key.
+    /// The nodes started by --launch-nodes serve with the same one
+    #[arg(long, env = "ARKE_KIA_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of the TLS certificate
+    #[arg(long, env = "ARKE_KIA_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+    /// host:port of the registration authority, attestations are verified under the registrar keys it serves
+    #[arg(long, env = "ARKE_KIA_REGISTRATION_AUTHORITY")]
+    registration_authority: Option<String>,
+    /// Pin of the registration authority to fetch its keys over TLS, cert-sha256:<hex> or spki-sha256:<hex>
+    #[arg(long, env = "ARKE_KIA_REGISTRATION_AUTHORITY_PIN")]
+    registration_authority_pin: Option<String>,
+    /// Fetch the registrar keys over plain TCP when the registration authority has no pin, for one started without TLS
+    #[arg(long, env = "ARKE_KIA_INSECURE_PLAINTEXT")]
+    insecure_plaintext: bool,
+    /// Unix socket of the signer holding the issuer shares, this process then holds none of them
+    #[arg(long, env = "ARKE_KIA_SIGNER")]
+    signer: Option<PathBuf>,
+    /// Run as the signer: hold the issuer shares (of --node if given) and answer on the --signer socket
+    /// (signer.sock in the data directory by default) instead of serving clients
+    #[arg(long, conflicts_with = "launch_nodes")]
+    run_signer: bool,
+}
+
+fn parse_faulty_node(value: &str) -> Result<(u32, DkgFault), String> {
+    let (index, fault) = value.split_once('=').ok_or("expected INDEX=FAULT")?;
+    let index = index.parse::<u32>().map_err(|e| format!("bad index: {}", e))?;
+    let fault = DkgFault::from_str(fault, true)?;
+    Ok((index, fault))
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<String>,
+    data_dir: Option<PathBuf>,
+    node: Option<u32>,
+    node_base_port: Option<u16>,
+    dkg_peers: Option<Vec<String>>,
+    dkg_peer_keys: Option<Vec<String>>,
+    dkg_round_timeout: Option<u64>,
+    public_setup: Option<PathBuf>,
+    admin_bind: Option<String>,
+    admin_key: Option<PathBuf>,
+    workers: Option<usize>,
+    queue_capacity: Option<usize>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    registration_authority: Option<String>,
+    registration_authority_pin: Option<String>,
+    insecure_plaintext: Option<bool>,
+    signer: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub data_dir: PathBuf,
+    pub regenerate: bool,
+    /// Issuer index when running as a single node
+    pub node: Option<u32>,
+    pub launch_nodes: bool,
+    pub node_base_port: u16,
+    /// Whether the launched nodes run the DKG
+    pub dkg: bool,
+    pub dkg_peers: Vec<String>,
+    /// Public DKG identity keys, in the order of dkg_peers
+    pub dkg_peer_keys: Vec<DkgPublicKey>,
+    pub show_dkg_key: bool,
+    pub dkg_only: bool,
+    pub dkg_round_timeout: Duration,
+    pub dkg_fault: DkgFault,
+    public_setup: Option<PathBuf>,
+    pub skip_nodes: Vec<u32>,
+    pub faulty_nodes: Vec<(u32, DkgFault)>,
+    /// None if the admin endpoint is off
+    pub admin_bind: Option<SocketAddr>,
+    admin_key: Option<PathBuf>,
+    /// Size of the worker pool of every process, each launched node has its own
+    pub workers: usize,
+    pub queue_capacity: usize,
+    /// None if clients connect over plain TCP
+    pub tls: Option<TlsFiles>,
+    pub registration_authority: String,
+    pub registration_authority_pin: Option<Pin>,
+    /// Whether the registration authority may be reached over plain TCP when it has no pin
+    pub insecure_plaintext: bool,
+    /// None if the issuer shares are held by this process
+    pub signer: Option<PathBuf>,
+    pub run_signer: bool,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file: FileConfig = load_file(cli.config.as_deref())?;
+
+        let bind = cli.bind.or(file.bind).unwrap_or_else(|| DEFAULT_BIND.to_string());
+        let data_dir = cli.data_dir.or(file.data_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+        // --launch-nodes ignores a node index coming from the config file or the environment
+        let node = if cli.launch_nodes { None } else { cli.node.or(file.node) };
+        let node_base_port = cli.node_base_port.or(file.node_base_port).unwrap_or(DEFAULT_NODE_BASE_PORT);
+        let dkg_peers = if cli.dkg_peers.is_empty() { file.dkg_peers.unwrap_or_default() } else { cli.dkg_peers };
+        let dkg_peer_keys = if cli.dkg_peer_keys.is_empty() { file.dkg_peer_keys.unwrap_or_default() } else { cli.dkg_peer_keys };
+        let dkg_round_timeout = cli.dkg_round_timeout.or(file.dkg_round_timeout).unwrap_or(DEFAULT_DKG_ROUND_TIMEOUT_SECS);
+        let public_setup = cli.public_setup.or(file.public_setup);
+        let admin_bind = cli.admin_bind.or(file.admin_bind).unwrap_or_else(|| DEFAULT_ADMIN_BIND.to_string());
+        let workers = cli.workers.or(file.workers)
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |cpus| cpus.get()));
+        let queue_capacity = cli.queue_capacity.or(file.queue_capacity).unwrap_or(DEFAULT_QUEUE_CAPACITY);
+        let registration_authority = validate_endpoint("registration_authority",
+            &cli.registration_authority.or(file.registration_authority).unwrap_or_else(|| DEFAULT_REGISTRATION_AUTHORITY.to_string()))?;
+        let registration_authority_pin = cli.registration_authority_pin.or(file.registration_authority_pin)
+            .map(|pin| Pin::parse(&pin)
+                .ok_or_else(|| ConfigError::invalid("registration_authority_pin", pin.clone(), "expected cert-sha256:<hex> or spki-sha256:<hex>")))
+            .transpose()?;
+
+        if let Some(node) = node {
+            if node as usize >= NUMBER_OF_PARTICIPANTS {
+                return Err(ConfigError::invalid("node", node.to_string(),
+                    format!("there are only {} issuers", NUMBER_OF_PARTICIPANTS)));
+            }
+        }
+        if node_base_port as usize + 3 * NUMBER_OF_PARTICIPANTS - 1 > u16::MAX as usize {
+            return Err(ConfigError::invalid("node_base_port", node_base_port.to_string(),
+                format!("no room for {} consecutive ports", 3 * NUMBER_OF_PARTICIPANTS)));
+        }
+        if !dkg_peers.is_empty() {
+            if dkg_peers.len() != NUMBER_OF_PARTICIPANTS {
+                return Err(ConfigError::invalid("dkg_peers", dkg_peers.join(","),
+                    format!("expected the addresses of all {} issuers", NUMBER_OF_PARTICIPANTS)));
+            }
+            for dkg_peer in &dkg_peers {
+                validate_endpoint("dkg_peers", dkg_peer)?;
+            }
+            // The node listens for the DKG on its own entry
+            if let Some(node) = node {
+                parse_socket_addr("dkg_peers", &dkg_peers[node as usize])?;
+            }
+            if dkg_peer_keys.len() != NUMBER_OF_PARTICIPANTS {
+                return Err(ConfigError::invalid("dkg_peer_keys", dkg_peer_keys.join(","),
+                    format!("expected the DKG keys of all {} issuers, in the order of dkg_peers", NUMBER_OF_PARTICIPANTS)));
+            }
+        }
+        let dkg_peer_keys = dkg_peer_keys.iter()
+            .map(|key| key.parse::<DkgPublicKey>().map_err(|reason| ConfigError::invalid("dkg_peer_keys", key.as_str(), reason)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if cli.dkg_only && (node.is_none() || dkg_peers.is_empty()) {
+            return Err(ConfigError::invalid("dkg_only", "true", "the DKG needs the --node to run it as and the --dkg-peer nodes"));
+        }
+        if dkg_round_timeout == 0 {
+            return Err(ConfigError::invalid("dkg_round_timeout", "0", "must be at least 1 second"));
+        }
+        if workers == 0 {
+            return Err(ConfigError::invalid("workers", "0", "must be at least 1"));
+        }
+        if queue_capacity == 0 {
+            return Err(ConfigError::invalid("queue_capacity", "0", "must be at least 1"));
+        }
+        let signer = cli.signer.or(file.signer);
+        if let (Some(signer), true) = (&signer, cli.launch_nodes) {
+            return Err(ConfigError::invalid("signer", signer.display().to_string(),
+                "the nodes started by --launch-nodes hold their shares themselves"));
+        }
+
+        let config = Config {
+            bind: parse_socket_addr("bind", &bind)?,
+            data_dir: prepare_data_dir("data_dir", data_dir)?,
+            regenerate: cli.regenerate,
+            node,
+            launch_nodes: cli.launch_nodes,
+            node_base_port,
+            dkg: cli.dkg,
+            dkg_peers,
+            dkg_peer_keys,
+            show_dkg_key: cli.show_dkg_key,
+            dkg_only: cli.dkg_only,
+            dkg_round_timeout: Duration::from_secs(dkg_round_timeout),
+            dkg_fault: cli.dkg_fault,
+            public_setup,
+            skip_nodes: cli.skip_nodes,
+            faulty_nodes: cli.faulty_nodes,
+            admin_bind: if cli.no_admin { None } else { Some(parse_socket_addr("admin_bind", &admin_bind)?) },
+            admin_key: cli.admin_key.or(file.admin_key),
+            workers,
+            queue_capacity,
+            tls: tls_files(cli.tls_cert.or(file.tls_cert), cli.tls_key.or(file.tls_key))?,
+            registration_authority,
+            registration_authority_pin,
+            insecure_plaintext: cli.insecure_plaintext || file.insecure_plaintext.unwrap_or(false),
+            signer,
+            run_signer: cli.run_signer,
+        };
+        // The network process of a signer must not be able to read the shares either
+        if config.signer.is_some() && !config.run_signer {
+            refuse_secrets("data_dir", &config.data_dir, &[config.state_path(), config.node_state_path(), config.dkg_identity_path()])?;
+        }
+        Ok(config)
+    }
+
+    pub fn state_path(&self) -> PathBuf {
+        self.data_dir.join("key_issuing_authority.state")
+    }
+
+    /// State file of a node started with --node
+    pub fn node_state_path(&self) -> PathBuf {
+        Self::node_state_path_in(&self.data_dir)
+    }
+
+    pub fn node_state_path_in(data_dir: &Path) -> PathBuf {
+        data_dir.join("issuer_node.state")
+    }
+
+    /// Data directory the launcher gives to the node of issuer_index
+    pub fn node_data_dir(&self, issuer_index: usize) -> PathBuf {
+        self.data_dir.join(format!("node_{}", issuer_index))
+    }
+
+    /// Address the launcher gives to the node of issuer_index, on the same interface as bind
+    pub fn node_bind(&self, issuer_index: usize) -> SocketAddr {
+        SocketAddr::new(self.bind.ip(), self.node_base_port + issuer_index as u16)
+    }
+
+    /// Socket a process started with --run-signer listens on
+    pub fn signer_path(&self) -> PathBuf {
+        self.signer.clone().unwrap_or_else(|| self.data_dir.join("signer.sock"))
+    }
+
+    pub fn public_setup_path(&self) -> PathBuf {
+        self.public_setup.clone().unwrap_or_else(|| self.data_dir.join("public_setup.state"))
+    }
+
+    /// DKG settings of a node started with --node and --dkg-peer
+    pub fn dkg_config(&self) -> Option<DkgConfig> {
+        let node = self.node?;
+        if self.dkg_peers.is_empty() {
+            return None;
+        }
+        Some(DkgConfig {
+            issuer_index: node as usize,
+            peers: self.dkg_peers.clone(),
+            peer_keys: self.dkg_peer_keys.clone(),
+            identity: self.dkg_identity_path(),
+            threshold: THRESHOLD,
+            round_timeout: self.dkg_round_timeout,
+            fault: self.dkg_fault,
+        })
+    }
+
+    /// File with the DKG identity key of a node
+    pub fn dkg_identity_path(&self) -> PathBuf {
+        self.data_dir.join("dkg_identity.key")
+    }
+
+    /// Address the node listens on for the DKG, its own entry in dkg_peers
+    pub fn dkg_bind(&self) -> Option<SocketAddr> {
+        let node = self.node?;
+        // Checked to be a socket address when the config was loaded
+        self.dkg_peers.get(node as usize)?.parse().ok()
+    }
+
+    /// DKG address the launcher gives to the node of issuer_index
+    pub fn node_dkg_address(&self, issuer_index: usize) -> SocketAddr {
+        SocketAddr::new(self.bind.ip(), self.node_base_port + (NUMBER_OF_PARTICIPANTS + issuer_index) as u16)
+    }
+
+    /// Admin address the launcher gives to the node of issuer_index, on the same interface as admin_bind
+    pub fn node_admin_bind(&self, issuer_index: usize) -> Option<SocketAddr> {
+        let admin_bind = self.admin_bind?;
+        Some(SocketAddr::new(admin_bind.ip(), self.node_base_port + (2 * NUMBER_OF_PARTICIPANTS + issuer_index) as u16))
+    }
+
+    /// Where attestations are checked against the registrar keys of the registration authority
+    pub fn registrar_keys(&self) -> RegistrarKeys {
+        let mut pins = BTreeMap::new();
+        if let Some(pin) = &self.registration_authority_pin {
+            pins.insert(self.registration_authority.clone(), pin.clone());
+        }
+        let pins = Pins::new(pins);
+        RegistrarKeys::new(self.registration_authority.clone(), if self.insecure_plaintext { pins.allow_plaintext() } else { pins })
+    }
+
+    /// File with the key arke-admin logs in with
+    pub fn admin_key_path(&self) -> PathBuf {
+        self.admin_key.clone().unwrap_or_else(|| self.data_dir.join("admin.key"))
+    }
+}
@@ -0,0 +1,889 @@
+// ---------------------------------------
+// File: dkg.rs
+// Date: 18 Oct 2026
+// Description: Distributed key generation between the issuer nodes
+// ---------------------------------------
+
+// Joint-Feldman DKG (Pedersen's DKG) with complaints, run between the issuer nodes over TCP.
+// Every node is also a dealer. With t = THRESHOLD and x = issuer index + 1:
+//
+//   1. Deal:       dealer j picks a random polynomial f_j of degree t, sends every node
+//                  the commitments C_jk = g_hat^a_jk and its share f_j(x), encrypted to that node.
+//   2. Echo:       every node tells the others the digest of the commitments it got from each
+//                  dealer. The commitments of a dealer count once more than (n + t) / 2 nodes
+//                  echoed the same digest; two such quorums share an honest node, so a dealer
+//                  sending different commitments to different nodes gets at most one of them.
+//   3. Complaints: every node checks g_hat^f_j(x) == prod_k C_jk^(x^k) against the agreed
+//                  commitments and complains against every dealer whose share is missing or wrong.
+//   4. Reveals:    a dealer answers the complaints against it by revealing the shares.
+//                  It is disqualified when more than t nodes complained, when it does not
+//                  answer, or when a revealed share is missing or wrong.
+//   5. Confirm:    every node sends the qualified dealers and the digest of the public keys it
+//                  got. A node hearing of another result stops, the DKG has to be run again.
+//
+// Each node's secret share is the sum of the shares of the qualified dealers and the public
+// key of issuer m is g_hat^x_m, computed from the commitments alone, so no process ever
+// sees the master secret. Every node has a DKG identity key, whose public half the others
+// know from their configuration. A message is signed by its sender together with a fresh
+// challenge of its receiver, so no node can speak for another one or replay an earlier
+// message, and a share can only be read by the node it was dealt to. Nodes that are down or
+// stop answering are handled by the round timeouts. Up to t faulty nodes are tolerated
+// among n >= 3t + 1; what they send differently to different nodes is caught by the echo
+// and confirm rounds.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use clap::ValueEnum;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout, timeout_at, Instant};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey, StaticSecret};
+use arke_common::frame::{read_json, write_json, MAX_REQUEST_FRAME_LEN};
+use arke_common::messages::Blob;
+use arke_common::params_cache::digest;
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use ark_std::io::Cursor;
+use ark_std::UniformRand;
+use ark_ff::{PrimeField, Zero};
+use ark_ec::ProjectiveCurve;
+use ark_ec::bls12::Bls12;
+use ark_bls12_377::{Fr, G1Projective, G2Projective, Parameters};
+use arke_core::{BLSPublicParameters, IssuerPublicKey};
+use secret_sharing::shamir_secret_sharing::SecretShare;
+use rand::{thread_rng, RngCore};
+use crate::state;
+
+// Signed by the sender of every message, followed by the receiver, its challenge and the message
+const MESSAGE_DOMAIN: &[u8] = b"arke-dkg-message-v1";
+// Hashed with the Diffie-Hellman secret into the key a share is encrypted with
+const SHARE_KEY_DOMAIN: &[u8] = b"arke-dkg-share-v1";
+const CHALLENGE_LEN: usize = 32;
+
+/// Misbehaviour of a node, to test that the others still agree on a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DkgFault {
+    /// Follow the protocol
+    None,
+    /// Listen but never send anything, like a node that is down
+    Silent,
+    /// Send a wrong share to the next node, then reveal the right one when it complains
+    BadShare,
+    /// Send a wrong share to the next node and ignore its complaint
+    NoReveal,
+    /// Send a wrong share to the next node and reveal another wrong share
+    BadReveal,
+    /// Send other commitments, with shares matching them, to half of the nodes
+    Equivocate,
+}
+
+/// Settings of one node taking part in the DKG
+#[derive(Clone, Debug)]
+pub struct DkgConfig {
+    pub issuer_index: usize,
+    /// DKG address of every node, including this one, by issuer index
+    pub peers: Vec<String>,
+    /// DKG identity key of every node, including this one, by issuer index
+    pub peer_keys: Vec<DkgPublicKey>,
+    /// File with the DKG identity key of this node
+    pub identity: PathBuf,
+    pub threshold: usize,
+    /// How long to wait for the messages of one round
+    pub round_timeout: Duration,
+    pub fault: DkgFault,
+}
+
+/// Result of the DKG for one node
+pub struct DkgOutput {
+    /// The parameters the keys were generated under, the same on every node
+    pub pp_issuance: BLSPublicParameters<Bls12<Parameters>>,
+    pub secret_key: SecretShare<Fr>,
+    /// Public keys of all issuers by issuer index, also of the disqualified ones
+    pub issuers_public_keys: Vec<IssuerPublicKey<Bls12<Parameters>>>,
+    /// Issuer indices of the dealers whose polynomial is part of the key
+    pub qualified: Vec<usize>,
+}
+
+/// Long-term keys of a node in the DKG: it signs its messages with the first and receives its
+/// shares encrypted to the second
+pub struct DkgIdentity {
+    signing_key: SigningKey,
+    encryption_key: StaticSecret,
+}
+
+/// The public half of a DkgIdentity, written in base64 for --dkg-peer-key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DkgPublicKey {
+    verifying_key: VerifyingKey,
+    encryption_key: X25519PublicKey,
+}
+
+impl DkgIdentity {
+    /// Length of the encoded key, the ed25519 seed followed by the X25519 secret
+    pub const LEN: usize = SECRET_KEY_LENGTH + 32;
+
+    pub fn generate() -> Self {
+        let mut rng = thread_rng();
+        Self { signing_key: SigningKey::generate(&mut rng), encryption_key: StaticSecret::random_from_rng(&mut rng) }
+    }
+
+    /// Load the key at path, or create it there if there is none yet
+    pub fn load_or_create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(identity) = state::load_dkg_identity(path)? {
+            return Ok(identity);
+        }
+        let identity = Self::generate();
+        state::save_dkg_identity(path, &identity)?;
+        println!("✓ Created the DKG identity key {}", path.display());
+        Ok(identity)
+    }
+
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        let (seed, secret) = bytes.split_at(SECRET_KEY_LENGTH);
+        let seed: [u8; SECRET_KEY_LENGTH] = seed.try_into().expect("split at the seed length");
+        let secret: [u8; 32] = secret.try_into().expect("the rest is the X25519 secret");
+        Self { signing_key: SigningKey::from_bytes(&seed), encryption_key: StaticSecret::from(secret) }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[..SECRET_KEY_LENGTH].copy_from_slice(&self.signing_key.to_bytes());
+        bytes[SECRET_KEY_LENGTH..].copy_from_slice(&self.encryption_key.to_bytes());
+        bytes
+    }
+
+    pub fn public_key(&self) -> DkgPublicKey {
+        DkgPublicKey {
+            verifying_key: self.signing_key.verifying_key(),
+            encryption_key: X25519PublicKey::from(&self.encryption_key),
+        }
+    }
+}
+
+impl fmt::Display for DkgPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = [self.verifying_key.as_bytes().as_slice(), self.encryption_key.as_bytes()].concat();
+        write!(f, "{}", base64::encode(bytes))
+    }
+}
+
+impl FromStr for DkgPublicKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let bytes = base64::decode(value).map_err(|e| format!("not base64: {}", e))?;
+        if bytes.len() != PUBLIC_KEY_LENGTH + 32 {
+            return Err(format!("expected {} bytes, got {}", PUBLIC_KEY_LENGTH + 32, bytes.len()));
+        }
+        let (verifying_key, encryption_key) = bytes.split_at(PUBLIC_KEY_LENGTH);
+        let verifying_key = VerifyingKey::from_bytes(verifying_key.try_into().expect("split at the key length"))
+            .map_err(|e| format!("not an ed25519 key: {}", e))?;
+        let encryption_key: [u8; 32] = encryption_key.try_into().expect("the rest is the X25519 key");
+        Ok(Self { verifying_key, encryption_key: X25519PublicKey::from(encryption_key) })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "round", rename_all = "snake_case")]
+enum DkgMessage {
+    Deal { commitments: Vec<Blob>, share: SealedShare },
+    /// The digest of the commitments this node got from each dealer
+    Echo { digests: Vec<(usize, String)> },
+    Complaints { against: Vec<usize> },
+    Reveals { commitments: Vec<Blob>, shares: Vec<(usize, Blob)> },
+    Confirm { qualified: Vec<usize>, public_keys_digest: String },
+}
+
+impl DkgMessage {
+    fn round(&self) -> &'static str {
+        match self {
+            DkgMessage::Deal { .. } => "deal",
+            DkgMessage::Echo { .. } => "echo",
+            DkgMessage::Complaints { .. } => "complaints",
+            DkgMessage::Reveals { .. } => "reveals",
+            DkgMessage::Confirm { .. } => "confirm",
+        }
+    }
+}
+
+/// A share encrypted to the node it is dealt to, under a key only used for this share
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SealedShare {
+    ephemeral_key: Blob,
+    ciphertext: Blob,
+}
+
+/// Sent by the receiver as soon as a node connects, the message that follows has to be signed with it
+#[derive(Serialize, Deserialize, Debug)]
+struct DkgChallenge {
+    nonce: Blob,
+}
+
+/// A DkgMessage in JSON and the signature of its sender
+#[derive(Serialize, Deserialize, Debug)]
+struct SignedDkgMessage {
+    sender: usize,
+    message: Blob,
+    signature: Blob,
+}
+
+/// BLS public parameters made of the standard generators, so no trusted party has to pick them
+pub fn bls_public_parameters() -> BLSPublicParameters<Bls12<Parameters>> {
+    BLSPublicParameters {
+        g: G1Projective::prime_subgroup_generator(),
+        g_hat: G2Projective::prime_subgroup_generator(),
+    }
+}
+
+/// Run the DKG as the node of config.issuer_index, listening for the other nodes on bind
+pub async fn run_dkg(config: &DkgConfig, bind: SocketAddr) -> Result<DkgOutput, Box<dyn std::error::Error>> {
+    let identity = state::load_dkg_identity(&config.identity)?.ok_or_else(|| {
+        format!("no DKG identity key at {}, create it with --show-dkg-key and give the other nodes its public half",
+                config.identity.display())
+    })?;
+    let listener = TcpListener::bind(bind).await?;
+    run_dkg_on(config, &identity, listener).await
+}
+
+// run_dkg with the identity loaded and the listener bound already
+async fn run_dkg_on(config: &DkgConfig, identity: &DkgIdentity, listener: TcpListener) -> Result<DkgOutput, Box<dyn std::error::Error>> {
+    let n = config.peers.len();
+    let t = config.threshold;
+    let me = config.issuer_index;
+    if me >= n {
+        return Err(format!("issuer {} is not one of the {} DKG peers", me, n).into());
+    }
+    if config.peer_keys.len() != n {
+        return Err(format!("expected the DKG keys of all {} peers, got {}", n, config.peer_keys.len()).into());
+    }
+    if config.peer_keys[me] != identity.public_key() {
+        return Err(format!("the DKG key given for issuer {} is not the one in {}", me, config.identity.display()).into());
+    }
+    if n < 3 * t + 1 {
+        return Err(format!("{} DKG peers cannot tolerate {} faulty ones, at least {} are needed", n, t, 3 * t + 1).into());
+    }
+    let pp_issuance = bls_public_parameters();
+    let g_hat = pp_issuance.g_hat;
+
+    let (inbox_sender, mut inbox) = mpsc::unbounded_channel();
+    let listening = tokio::spawn(receive(listener, Arc::new(config.peer_keys.clone()), me, config.round_timeout, inbox_sender.clone()));
+    let network = Network {
+        peers: config.peers.clone(),
+        me,
+        signing_key: Arc::new(identity.signing_key.clone()),
+        inbox: inbox_sender,
+        retry_for: config.round_timeout,
+    };
+    let mut pending = Vec::new();
+    let silent = config.fault == DkgFault::Silent;
+
+    // ================
+    // Round 1: deal
+    // ================
+    println!("- DKG: dealing");
+    let (coefficients, commitment_blobs) = random_polynomial(t, &g_hat);
+    // A second polynomial for the nodes an equivocating dealer cheats
+    let other = (config.fault == DkgFault::Equivocate).then(|| random_polynomial(t, &g_hat));
+    // The node a faulty dealer cheats
+    let victim = (me + 1) % n;
+    if !silent {
+        for receiver in 0..n {
+            let (coefficients, commitment_blobs) = match &other {
+                Some((coefficients, commitment_blobs)) if receiver % 2 != me % 2 => (coefficients, commitment_blobs),
+                _ => (&coefficients, &commitment_blobs),
+            };
+            let mut share = evaluate(coefficients, receiver);
+            if receiver == victim && matches!(config.fault, DkgFault::BadShare | DkgFault::NoReveal | DkgFault::BadReveal) {
+                share += Fr::from(1u64);
+            }
+            let context = share_context(me, receiver, &commitments_digest(commitment_blobs));
+            let share = seal_share(&share, &config.peer_keys[receiver].encryption_key, &context);
+            network.send(receiver, DkgMessage::Deal { commitments: commitment_blobs.clone(), share });
+        }
+    }
+
+    let deals = collect(&mut inbox, &mut pending, "deal", n, config.round_timeout).await;
+    // The digest of the commitments each dealer sent this node, the commitments and the share that came with them
+    let mut dealt: HashMap<usize, (String, Vec<G2Projective>, Option<Fr>)> = HashMap::new();
+    for (dealer, message) in deals {
+        if let DkgMessage::Deal { commitments: blobs, share } = message {
+            let commitments = match decode_commitments(&blobs, t) {
+                Some(commitments) => commitments,
+                None => continue,
+            };
+            let digest = commitments_digest(&blobs);
+            let share = open_share(&share, identity, &share_context(dealer, me, &digest));
+            dealt.insert(dealer, (digest, commitments, share));
+        }
+    }
+
+    // ================
+    // Round 2: echo
+    // ================
+    if !silent {
+        let digests = dealt.iter().map(|(dealer, (digest, _, _))| (*dealer, digest.clone())).collect();
+        network.broadcast(DkgMessage::Echo { digests });
+    }
+    let echoes = collect(&mut inbox, &mut pending, "echo", n, config.round_timeout).await;
+    // Nodes that echoed each digest, by dealer
+    let mut echoed: BTreeMap<usize, HashMap<String, BTreeSet<usize>>> = BTreeMap::new();
+    for (sender, message) in echoes {
+        if let DkgMessage::Echo { digests } = message {
+            for (dealer, digest) in digests.into_iter().filter(|(dealer, _)| *dealer < n) {
+                echoed.entry(dealer).or_default().entry(digest).or_default().insert(sender);
+            }
+        }
+    }
+    // The digest of the commitments every honest node holds each dealer to
+    let mut agreed: BTreeMap<usize, String> = BTreeMap::new();
+    for (dealer, digests) in echoed {
+        match digests.into_iter().find(|(_, echoers)| 2 * echoers.len() > n + t) {
+            Some((digest, _)) => {
+                agreed.insert(dealer, digest);
+            },
+            None => println!("X DKG: issuer {} sent different commitments to different nodes, leaving it out", dealer),
+        }
+    }
+
+    // Commitments of every dealer this node holds the agreed ones of, and the shares that check out
+    let mut dealer_commitments: HashMap<usize, Vec<G2Projective>> = HashMap::new();
+    let mut my_shares: HashMap<usize, Fr> = HashMap::new();
+    for (dealer, (digest, commitments, share)) in dealt {
+        if agreed.get(&dealer) != Some(&digest) {
+            continue;
+        }
+        if let Some(share) = share.filter(|share| verify_share(&commitments, me, share, &g_hat)) {
+            my_shares.insert(dealer, share);
+        }
+        dealer_commitments.insert(dealer, commitments);
+    }
+    let against: Vec<usize> = agreed.keys().copied().filter(|dealer| !my_shares.contains_key(dealer)).collect();
+    for dealer in &against {
+        println!("X DKG: no valid share from issuer {}, complaining", dealer);
+    }
+
+    // ====================
+    // Round 3: complaints
+    // ====================
+    if !silent {
+        network.broadcast(DkgMessage::Complaints { against: against.clone() });
+    }
+    let complaints = collect(&mut inbox, &mut pending, "complaints", n, config.round_timeout).await;
+    // Complainers against each dealer
+    let mut complainers: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for (sender, message) in complaints {
+        if let DkgMessage::Complaints { against } = message {
+            for dealer in against.into_iter().filter(|dealer| *dealer < n) {
+                complainers.entry(dealer).or_default().insert(sender);
+            }
+        }
+    }
+
+    // =================
+    // Round 4: reveals
+    // =================
+    if !silent {
+        let shares = complainers.get(&me).into_iter().flatten()
+            .filter(|_| config.fault != DkgFault::NoReveal)
+            .map(|complainer| {
+                let mut share = evaluate(&coefficients, *complainer);
+                if config.fault == DkgFault::BadReveal && *complainer == victim {
+                    share += Fr::from(1u64);
+                }
+                (*complainer, to_blob(&share))
+            })
+            .collect();
+        network.broadcast(DkgMessage::Reveals { commitments: commitment_blobs.clone(), shares });
+    }
+    let reveals = collect(&mut inbox, &mut pending, "reveals", n, config.round_timeout).await;
+
+    // Every node applies the same rules to the same messages, so they agree on the qualified dealers
+    let mut qualified = Vec::new();
+    for (&dealer, digest) in &agreed {
+        let complained = complainers.get(&dealer).cloned().unwrap_or_default();
+        if complained.is_empty() {
+            if dealer_commitments.contains_key(&dealer) {
+                qualified.push(dealer);
+            }
+            continue;
+        }
+        if complained.len() > t {
+            println!("X DKG: disqualified issuer {}, {} complaints", dealer, complained.len());
+            continue;
+        }
+        let (commitments, revealed) = match reveals.get(&dealer) {
+            Some(DkgMessage::Reveals { commitments, shares }) => (commitments, shares),
+            _ => {
+                println!("X DKG: disqualified issuer {}, it did not answer the complaints", dealer);
+                continue;
+            }
+        };
+        // A node that got no deal learns the commitments here, they have to be the agreed ones
+        let revealed_commitments = || (commitments_digest(commitments) == *digest).then(|| decode_commitments(commitments, t)).flatten();
+        let commitments = match dealer_commitments.get(&dealer).cloned().or_else(revealed_commitments) {
+            Some(commitments) => commitments,
+            None => {
+                println!("X DKG: disqualified issuer {}, its commitments are not the agreed ones", dealer);
+                continue;
+            }
+        };
+        let revealed: HashMap<usize, Fr> = revealed.iter()
+            .filter_map(|(complainer, share)| Some((*complainer, from_blob::<Fr>(share)?)))
+            .collect();
+        let all_valid = complained.iter().all(|complainer| {
+            revealed.get(complainer).map_or(false, |share| verify_share(&commitments, *complainer, share, &g_hat))
+        });
+        if !all_valid {
+            println!("X DKG: disqualified issuer {}, a revealed share is missing or wrong", dealer);
+            continue;
+        }
+        if let Some(share) = revealed.get(&me) {
+            my_shares.insert(dealer, *share);
+        }
+        dealer_commitments.insert(dealer, commitments);
+        qualified.push(dealer);
+    }
+
+    // At most t nodes are dishonest, so t + 1 qualified dealers include an honest one
+    if qualified.len() < t + 1 {
+        return Err(format!("only {} of the {} dealers needed are qualified", qualified.len(), t + 1).into());
+    }
+    println!("✓ DKG: qualified issuers {:?}", qualified);
+
+    let mut secret_key = Fr::zero();
+    for dealer in &qualified {
+        secret_key += my_shares.get(dealer).ok_or("a qualified dealer gave no valid share")?;
+    }
+    let public_keys: Vec<G2Projective> = (0..n)
+        .map(|issuer| qualified.iter()
+            .map(|dealer| evaluate_in_exponent(&dealer_commitments[dealer], issuer))
+            .fold(G2Projective::zero(), |sum, public_key| sum + public_key))
+        .collect();
+    if public_keys[me] != g_hat.mul(secret_key.into_repr()) {
+        return Err("the secret share does not match the public key, the DKG failed".into());
+    }
+
+    // =================
+    // Round 5: confirm
+    // =================
+    let public_keys_digest = digest(&public_keys.iter().flat_map(|public_key| to_blob(public_key).0).collect::<Vec<u8>>());
+    if !silent {
+        network.broadcast(DkgMessage::Confirm { qualified: qualified.clone(), public_keys_digest: public_keys_digest.clone() });
+    }
+    let confirms = collect(&mut inbox, &mut pending, "confirm", n, config.round_timeout).await;
+    for (sender, message) in confirms {
+        if let DkgMessage::Confirm { qualified: their_qualified, public_keys_digest: their_digest } = message {
+            if their_qualified != qualified || their_digest != public_keys_digest {
+                listening.abort();
+                return Err(format!("issuer {} ended the DKG with the qualified issuers {:?} and public keys {}, this node with {:?} and {}, \
+                                    the DKG has to be run again", sender, their_qualified, their_digest, qualified, public_keys_digest).into());
+            }
+        }
+    }
+    println!("✓ DKG: every node that answered got the same key");
+
+    // Give the other nodes time to finish their last round before the listener goes away
+    sleep(config.round_timeout / 4).await;
+    listening.abort();
+
+    Ok(DkgOutput {
+        pp_issuance,
+        secret_key: SecretShare::new(share_index(me), secret_key),
+        issuers_public_keys: public_keys.into_iter().map(IssuerPublicKey).collect(),
+        qualified,
+    })
+}
+
+
+// Evaluation point of an issuer, shares are never evaluated at 0
+fn share_index(issuer_index: usize) -> usize {
+    issuer_index + 1
+}
+
+// The coefficients of a random polynomial of degree t and the commitments to them
+fn random_polynomial(t: usize, g_hat: &G2Projective) -> (Vec<Fr>, Vec<Blob>) {
+    let mut rng = thread_rng();
+    let coefficients: Vec<Fr> = (0..=t).map(|_| Fr::rand(&mut rng)).collect();
+    let commitments = coefficients.iter().map(|a| to_blob(&g_hat.mul(a.into_repr()))).collect();
+    (coefficients, commitments)
+}
+
+fn evaluate(coefficients: &[Fr], issuer_index: usize) -> Fr {
+    let x = Fr::from(share_index(issuer_index) as u64);
+    coefficients.iter().rev().fold(Fr::zero(), |acc, coefficient| acc * x + coefficient)
+}
+
+// g_hat^f(x) from the commitments to the coefficients of f
+fn evaluate_in_exponent(commitments: &[G2Projective], issuer_index: usize) -> G2Projective {
+    let x = Fr::from(share_index(issuer_index) as u64);
+    let mut power = Fr::from(1u64);
+    let mut result = G2Projective::zero();
+    for commitment in commitments {
+        result += commitment.mul(power.into_repr());
+        power *= x;
+    }
+    result
+}
+
+fn verify_share(commitments: &[G2Projective], issuer_index: usize, share: &Fr, g_hat: &G2Projective) -> bool {
+    g_hat.mul(share.into_repr()) == evaluate_in_exponent(commitments, issuer_index)
+}
+
+fn decode_commitments(blobs: &[Blob], threshold: usize) -> Option<Vec<G2Projective>> {
+    if blobs.len() != threshold + 1 {
+        return None;
+    }
+    blobs.iter().map(from_blob::<G2Projective>).collect()
+}
+
+// SHA-256 of the commitments as sent, each prefixed with its length
+fn commitments_digest(blobs: &[Blob]) -> String {
+    let mut bytes = Vec::new();
+    for blob in blobs {
+        bytes.extend_from_slice(&(blob.0.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&blob.0);
+    }
+    digest(&bytes)
+}
+
+// Authenticated along with an encrypted share, so it cannot be passed off as the share of another
+// dealer, for another node or under other commitments
+fn share_context(dealer: usize, receiver: usize, commitments_digest: &str) -> Vec<u8> {
+    [&(dealer as u32).to_be_bytes()[..], &(receiver as u32).to_be_bytes(), commitments_digest.as_bytes()].concat()
+}
+
+fn share_key(shared_secret: &[u8; 32], ephemeral_key: &X25519PublicKey, receiver_key: &X25519PublicKey) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(SHARE_KEY_DOMAIN);
+    hasher.update(shared_secret);
+    hasher.update(ephemeral_key.as_bytes());
+    hasher.update(receiver_key.as_bytes());
+    Key::clone_from_slice(&hasher.finalize())
+}
+
+// Encrypt share to receiver_key with a fresh X25519 key, so the key of every share is only used once
+fn seal_share(share: &Fr, receiver_key: &X25519PublicKey, context: &[u8]) -> SealedShare {
+    let ephemeral_secret = EphemeralSecret::random_from_rng(thread_rng());
+    let ephemeral_key = X25519PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(receiver_key);
+    let cipher = ChaCha20Poly1305::new(&share_key(shared_secret.as_bytes(), &ephemeral_key, receiver_key));
+    // The key encrypts nothing else, a fixed nonce is safe
+    let ciphertext = cipher.encrypt(&Nonce::default(), Payload { msg: &to_blob(share).0, aad: context })
+        .expect("a share is far below the size limit of ChaCha20Poly1305");
+    SealedShare { ephemeral_key: Blob(ephemeral_key.as_bytes().to_vec()), ciphertext: Blob(ciphertext) }
+}
+
+// The share in sealed, or None if it was not encrypted to identity with this context
+fn open_share(sealed: &SealedShare, identity: &DkgIdentity, context: &[u8]) -> Option<Fr> {
+    let ephemeral_key: [u8; 32] = sealed.ephemeral_key.0.as_slice().try_into().ok()?;
+    let ephemeral_key = X25519PublicKey::from(ephemeral_key);
+    let shared_secret = identity.encryption_key.diffie_hellman(&ephemeral_key);
+    let my_key = X25519PublicKey::from(&identity.encryption_key);
+    let cipher = ChaCha20Poly1305::new(&share_key(shared_secret.as_bytes(), &ephemeral_key, &my_key));
+    let share = cipher.decrypt(&Nonce::default(), Payload { msg: &sealed.ciphertext.0, aad: context }).ok()?;
+    from_blob(&Blob(share))
+}
+
+fn to_blob<T: CanonicalSerialize>(value: &T) -> Blob {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes).expect("serializing to a Vec cannot fail");
+    Blob(bytes)
+}
+
+fn from_blob<T: CanonicalDeserialize>(blob: &Blob) -> Option<T> {
+    // CanonicalDeserialize
+    let mut cursor = Cursor::new(&blob.0);
+    T::deserialize(&mut cursor).ok()
+}
+
+// Collect the messages of one round, at most one per sender, until all n arrived or the round times out.
+// Messages of later rounds that arrive early are kept in pending.
+async fn collect(inbox: &mut mpsc::UnboundedReceiver<(usize, DkgMessage)>,
+                 pending: &mut Vec<(usize, DkgMessage)>,
+                 round: &'static str,
+                 n: usize,
+                 round_timeout: Duration) -> HashMap<usize, DkgMessage> {
+    let mut received = HashMap::new();
+    let mut later = Vec::new();
+    for (sender, message) in pending.drain(..) {
+        if message.round() == round {
+            received.entry(sender).or_insert(message);
+        } else {
+            later.push((sender, message));
+        }
+    }
+    *pending = later;
+
+    let deadline = Instant::now() + round_timeout;
+    while received.len() < n {
+        let (sender, message) = match timeout_at(deadline, inbox.recv()).await {
+            Ok(Some(entry)) => entry,
+            // The round timed out, the missing nodes count as silent
+            _ => break,
+        };
+        if message.round() != round {
+            pending.push((sender, message));
+        } else {
+            received.entry(sender).or_insert(message);
+        }
+    }
+    println!("- DKG: {} of {} nodes took part in the {} round", received.len(), n, round);
+    received
+}
+
+// The bytes the sender of a message signs
+fn signed_bytes(receiver: usize, nonce: &[u8], message: &[u8]) -> Vec<u8> {
+    [MESSAGE_DOMAIN, &(receiver as u32).to_be_bytes(), nonce, message].concat()
+}
+
+// Pass every message signed by another node to the protocol, together with the index of that node
+async fn receive(listener: TcpListener, peer_keys: Arc<Vec<DkgPublicKey>>, me: usize, connection_timeout: Duration,
+                 inbox: mpsc::UnboundedSender<(usize, DkgMessage)>) {
+    loop {
+        let (socket, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(_) => continue,
+        };
+        let peer_keys = Arc::clone(&peer_keys);
+        let inbox = inbox.clone();
+        tokio::spawn(async move {
+            match timeout(connection_timeout, receive_one(socket, &peer_keys, me)).await {
+                Ok(Ok(message)) => {
+                    let _ = inbox.send(message);
+                },
+                Ok(Err(reason)) => println!("X DKG: refused a message; err = {}", reason),
+                // Whoever connected never sent a message
+                Err(_) => {},
+            }
+        });
+    }
+}
+
+// Challenge the node that connected and read the message it signs with the challenge
+async fn receive_one(mut socket: TcpStream, peer_keys: &[DkgPublicKey], me: usize) -> Result<(usize, DkgMessage), String> {
+    let mut nonce = [0u8; CHALLENGE_LEN];
+    thread_rng().fill_bytes(&mut nonce);
+    write_json(&mut socket, &DkgChallenge { nonce: Blob(nonce.to_vec()) }).await.map_err(|e| e.to_string())?;
+    let signed: SignedDkgMessage = read_json(&mut socket, MAX_REQUEST_FRAME_LEN).await
+        .map_err(|e| e.to_string())?
+        .ok_or("the connection closed before the message")?;
+
+    // Messages of this node never come over the network
+    let peer_key = peer_keys.get(signed.sender).filter(|_| signed.sender != me)
+        .ok_or_else(|| format!("{} is not another DKG peer", signed.sender))?;
+    let signature = Signature::from_slice(&signed.signature.0).map_err(|e| e.to_string())?;
+    peer_key.verifying_key.verify_strict(&signed_bytes(me, &nonce, &signed.message.0), &signature)
+        .map_err(|_| format!("the signature of issuer {} does not verify", signed.sender))?;
+    let message = serde_json::from_slice(&signed.message.0).map_err(|e| e.to_string())?;
+    Ok((signed.sender, message))
+}
+
+struct Network {
+    peers: Vec<String>,
+    me: usize,
+    signing_key: Arc<SigningKey>,
+    inbox: mpsc::UnboundedSender<(usize, DkgMessage)>,
+    // Nodes may start a bit later than this one, keep retrying that long
+    retry_for: Duration,
+}
+
+impl Network {
+    fn broadcast(&self, message: DkgMessage) {
+        for receiver in 0..self.peers.len() {
+            self.send(receiver, message.clone());
+        }
+    }
+
+    // Send in the background, so a node that is down does not hold up the round
+    fn send(&self, receiver: usize, message: DkgMessage) {
+        if receiver == self.me {
+            let _ = self.inbox.send((self.me, message));
+            return;
+        }
+        let address = self.peers[receiver].clone();
+        let deadline = Instant::now() + self.retry_for;
+        let signing_key = Arc::clone(&self.signing_key);
+        let me = self.me;
+        let round = message.round();
+        let message = serde_json::to_vec(&message).expect("a DKG message always serializes");
+        tokio::spawn(async move {
+            loop {
+                if let Ok(Ok(())) = timeout_at(deadline, send_one(&address, receiver, me, &signing_key, &message)).await {
+                    return;
+                }
+                if Instant::now() >= deadline {
+                    println!("X DKG: could not send the {} message to {}", round, address);
+                    return;
+                }
+                sleep(Duration::from_millis(200)).await;
+            }
+        });
+    }
+}
+
+// Answer the challenge of the node at address with message, signed
+async fn send_one(address: &str, receiver: usize, me: usize, signing_key: &SigningKey, message: &[u8]) -> Result<(), String> {
+    let mut stream = TcpStream::connect(address).await.map_err(|e| e.to_string())?;
+    let challenge: DkgChallenge = read_json(&mut stream, MAX_REQUEST_FRAME_LEN).await
+        .map_err(|e| e.to_string())?
+        .ok_or("the connection closed before the challenge")?;
+    if challenge.nonce.0.len() != CHALLENGE_LEN {
+        return Err(format!("expected a challenge of {} bytes", CHALLENGE_LEN));
+    }
+    let signature = signing_key.sign(&signed_bytes(receiver, &challenge.nonce.0, message));
+    let signed = SignedDkgMessage { sender: me, message: Blob(message.to_vec()), signature: Blob(signature.to_bytes().to_vec()) };
+    write_json(&mut stream, &signed).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::LocalSet;
+
+    const N: usize = 4;
+    const T: usize = 1;
+
+    type NodeResult = Option<Result<DkgOutput, String>>;
+
+    // Run N nodes in this process, except the ones that dropped out, and return the result of each
+    async fn run_nodes(faults: &[(usize, DkgFault)], dropped: &[usize]) -> Vec<NodeResult> {
+        let identities: Vec<DkgIdentity> = (0..N).map(|_| DkgIdentity::generate()).collect();
+        let mut listeners = Vec::new();
+        for _ in 0..N {
+            listeners.push(TcpListener::bind("127.0.0.1:0").await.unwrap());
+        }
+        let peers: Vec<String> = listeners.iter().map(|listener| listener.local_addr().unwrap().to_string()).collect();
+        let peer_keys: Vec<DkgPublicKey> = identities.iter().map(DkgIdentity::public_key).collect();
+
+        let local = LocalSet::new();
+        let mut nodes = Vec::new();
+        for (issuer_index, (identity, listener)) in identities.into_iter().zip(listeners).enumerate() {
+            // A node that dropped out leaves its port closed
+            if dropped.contains(&issuer_index) {
+                nodes.push(None);
+                continue;
+            }
+            let config = DkgConfig {
+                issuer_index,
+                peers: peers.clone(),
+                peer_keys: peer_keys.clone(),
+                identity: PathBuf::from(format!("node_{}.key", issuer_index)),
+                threshold: T,
+                round_timeout: Duration::from_secs(3),
+                fault: faults.iter().find(|(faulty, _)| *faulty == issuer_index).map_or(DkgFault::None, |(_, fault)| *fault),
+            };
+            nodes.push(Some(local.spawn_local(async move {
+                run_dkg_on(&config, &identity, listener).await.map_err(|e| e.to_string())
+            })));
+        }
+        local.run_until(async move {
+            let mut results = Vec::new();
+            for node in nodes {
+                results.push(match node {
+                    Some(node) => Some(node.await.unwrap()),
+                    None => None,
+                });
+            }
+            results
+        }).await
+    }
+
+    // Every honest node finished with the same qualified dealers, the same BLS public parameters
+    // and the same public keys
+    fn assert_agree(results: &[NodeResult], honest: &[usize], qualified: &[usize]) {
+        let public_setup = |issuer_index: usize| {
+            let output = results[issuer_index].as_ref().expect("the node ran").as_ref()
+                .unwrap_or_else(|e| panic!("the DKG failed on issuer {}: {}", issuer_index, e));
+            assert_eq!(output.qualified, qualified, "qualified issuers of issuer {}", issuer_index);
+            let public_keys = output.issuers_public_keys.iter().map(|public_key| to_blob(public_key).0).collect::<Vec<_>>();
+            (to_blob(&output.pp_issuance).0, public_keys)
+        };
+        let (expected_pp_issuance, expected_public_keys) = public_setup(honest[0]);
+        assert!(expected_pp_issuance == to_blob(&bls_public_parameters()).0, "issuer {} got other BLS public parameters", honest[0]);
+        for &issuer_index in &honest[1..] {
+            let (pp_issuance, public_keys) = public_setup(issuer_index);
+            assert!(pp_issuance == expected_pp_issuance, "issuer {} got other BLS public parameters", issuer_index);
+            assert!(public_keys == expected_public_keys, "issuer {} got other public keys", issuer_index);
+        }
+    }
+
+    #[tokio::test]
+    async fn bad_share_is_kept_when_revealed_and_dropped_node_is_left_out() {
+        let results = run_nodes(&[(1, DkgFault::BadShare)], &[3]).await;
+        assert_agree(&results, &[0, 2], &[0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn bad_reveal_and_dropped_node_are_left_out() {
+        let results = run_nodes(&[(1, DkgFault::BadReveal)], &[3]).await;
+        assert_agree(&results, &[0, 2], &[0, 2]);
+    }
+
+    #[tokio::test]
+    async fn dealer_ignoring_a_complaint_is_left_out() {
+        let results = run_nodes(&[(1, DkgFault::NoReveal)], &[]).await;
+        assert_agree(&results, &[0, 2, 3], &[0, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn silent_node_is_left_out() {
+        let results = run_nodes(&[(1, DkgFault::Silent)], &[]).await;
+        assert_agree(&results, &[0, 2, 3], &[0, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn equivocating_dealer_is_left_out() {
+        let results = run_nodes(&[(1, DkgFault::Equivocate)], &[]).await;
+        assert_agree(&results, &[0, 2, 3], &[0, 2, 3]);
+    }
+
+    #[test]
+    fn shares_only_open_for_their_receiver_and_context() {
+        let (receiver, other) = (DkgIdentity::generate(), DkgIdentity::generate());
+        let share = Fr::from(42u64);
+        let context = share_context(0, 1, "digest");
+        let sealed = seal_share(&share, &receiver.public_key().encryption_key, &context);
+        assert_eq!(open_share(&sealed, &receiver, &context), Some(share));
+        assert_eq!(open_share(&sealed, &other, &context), None);
+        assert_eq!(open_share(&sealed, &receiver, &share_context(2, 1, "digest")), None);
+        assert_eq!(open_share(&sealed, &receiver, &share_context(0, 1, "other digest")), None);
+    }
+
+    #[tokio::test]
+    async fn messages_are_only_taken_from_the_peer_that_signed_them() {
+        let identities: Vec<DkgIdentity> = (0..2).map(|_| DkgIdentity::generate()).collect();
+        let peer_keys = Arc::new(identities.iter().map(DkgIdentity::public_key).collect::<Vec<_>>());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (inbox_sender, mut inbox) = mpsc::unbounded_channel();
+        let receiver = tokio::spawn(receive(listener, peer_keys, 0, Duration::from_secs(3), inbox_sender));
+
+        let message = serde_json::to_vec(&DkgMessage::Complaints { against: vec![1] }).unwrap();
+        // Issuer 0 claiming to be issuer 1, and issuer 1 speaking for itself
+        send_one(&address, 0, 1, &identities[0].signing_key, &message).await.unwrap();
+        send_one(&address, 0, 1, &identities[1].signing_key, &message).await.unwrap();
+        let (sender, received) = timeout(Duration::from_secs(3), inbox.recv()).await.unwrap().unwrap();
+        assert_eq!(sender, 1);
+        assert!(matches!(received, DkgMessage::Complaints { against } if against == vec![1]));
+        assert!(timeout(Duration::from_millis(500), inbox.recv()).await.is_err(), "the forged message was taken");
+        receiver.abort();
+    }
+
+    #[test]
+    fn public_keys_round_trip_through_their_text_form() {
+        let public_key = DkgIdentity::generate().public_key();
+        assert_eq!(public_key.to_string().parse::<DkgPublicKey>(), Ok(public_key));
+        assert!("not a key".parse::<DkgPublicKey>().is_err());
+        let identity = DkgIdentity::generate();
+        assert_eq!(DkgIdentity::from_bytes(&identity.to_bytes()).public_key(), identity.public_key());
+    }
+}
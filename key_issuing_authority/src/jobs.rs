@@ -0,0 +1,197 @@
+// ---------------------------------------
+// File: jobs.rs
+// Date: 18 Oct 2026
+// Description: Bounded worker pool running VerifyID and BlindPartialExtract off the async runtime
+// ---------------------------------------
+
+// VerifyID and BlindPartialExtract take long enough to stall a tokio worker thread, and with them
+// every other connection served by it. They run on a fixed number of worker threads instead, fed
+// by a queue of bounded length: once the queue is full, new jobs are refused with `busy` rather
+// than piling up.
+//
+// A job is either awaited by the connection that submitted it (to_VerifyID_and_BlindPartialExtract)
+// or polled by its job ID (submit_blind_partial_extract, then get_job_status). The result of a
+// polled job is kept for JOB_RESULT_TTL after it finished.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::Response;
+
+const JOB_RESULT_TTL: Duration = Duration::from_secs(300);
+
+type JobResult = Result<Response, ServiceError>;
+type Job = Box<dyn FnOnce() -> JobResult + Send>;
+
+enum JobState {
+    Queued,
+    Running,
+    Done { result: JobResult, expires_at: Instant },
+}
+
+struct Queue {
+    pending: VecDeque<(String, Job)>,
+    /// Jobs polled by ID
+    polled: HashMap<String, JobState>,
+    /// Jobs awaited by the connection that submitted them
+    waiters: HashMap<String, oneshot::Sender<JobResult>>,
+    running: usize,
+    completed: u64,
+    failed: u64,
+    /// Jobs refused because the queue was full
+    rejected: u64,
+}
+
+pub struct JobQueue {
+    workers: usize,
+    capacity: usize,
+    queue: Mutex<Queue>,
+    job_available: Condvar,
+}
+
+impl JobQueue {
+    /// Start workers threads taking jobs from a queue holding at most capacity jobs that wait for a worker
+    pub fn start(workers: usize, capacity: usize) -> Arc<Self> {
+        let jobs = Arc::new(Self {
+            workers,
+            capacity,
+            queue: Mutex::new(Queue {
+                pending: VecDeque::new(),
+                polled: HashMap::new(),
+                waiters: HashMap::new(),
+                running: 0,
+                completed: 0,
+                failed: 0,
+                rejected: 0,
+            }),
+            job_available: Condvar::new(),
+        });
+        for worker in 0..workers {
+            let jobs = Arc::clone(&jobs);
+            std::thread::Builder::new()
+                .name(format!("extract-worker-{}", worker))
+                .spawn(move || jobs.work())
+                .expect("could not start a worker thread");
+        }
+        jobs
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        // Jobs run outside the lock, a panic while holding it cannot leave a half-done change
+        let mut queue = self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Results nobody fetched in time are dropped here
+        let now = Instant::now();
+        queue.polled.retain(|_, state| !matches!(state, JobState::Done { expires_at, .. } if *expires_at <= now));
+        queue
+    }
+
+    fn enqueue(&self, queue: &mut Queue, job: Job) -> Result<(String, u32), ServiceError> {
+        if queue.pending.len() >= self.capacity {
+            queue.rejected += 1;
+            return Err(ServiceError::new(ErrorCode::Busy,
+                format!("{} jobs are already waiting, try again later", queue.pending.len())));
+        }
+        let job_id = format!("{:032x}", rand::random::<u128>());
+        let queue_position = queue.pending.len() as u32;
+        queue.pending.push_back((job_id.clone(), job));
+        self.job_available.notify_one();
+        Ok((job_id, queue_position))
+    }
+
+    /// Queue a job whose result is fetched later with status
+    pub fn submit(&self, job: impl FnOnce() -> JobResult + Send + 'static) -> JobResult {
+        let mut queue = self.lock();
+        let (job_id, queue_position) = self.enqueue(&mut queue, Box::new(job))?;
+        queue.polled.insert(job_id.clone(), JobState::Queued);
+        Ok(Response::JobQueued { job_id, queue_position })
+    }
+
+    /// Queue a job and wait for its result
+    pub async fn run(&self, job: impl FnOnce() -> JobResult + Send + 'static) -> JobResult {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut queue = self.lock();
+            let (job_id, _) = self.enqueue(&mut queue, Box::new(job))?;
+            queue.waiters.insert(job_id, sender);
+        }
+        receiver.await.map_err(|_| ServiceError::new(ErrorCode::Internal, "the job was dropped"))?
+    }
+
+    /// Where a polled job is, or its result once it is done
+    pub fn status(&self, job_id: &str) -> JobResult {
+        let queue = self.lock();
+        match queue.polled.get(job_id) {
+            Some(JobState::Queued) => {
+                let queue_position = queue.pending.iter().position(|(pending, _)| pending == job_id).unwrap_or(0);
+                Ok(Response::JobPending { queue_position: queue_position as u32 })
+            },
+            Some(JobState::Running) => Ok(Response::JobRunning),
+            Some(JobState::Done { result, .. }) => result.clone(),
+            None => Err(ServiceError::new(ErrorCode::UnknownJob, "no job with this ID, or its result has expired")),
+        }
+    }
+
+    /// Queue depth and job counts, for the admin endpoint
+    pub fn metrics(&self) -> BTreeMap<String, String> {
+        let queue = self.lock();
+        BTreeMap::from([
+            ("workers".to_string(), self.workers.to_string()),
+            ("queue_capacity".to_string(), self.capacity.to_string()),
+            ("queue_depth".to_string(), queue.pending.len().to_string()),
+            ("jobs_running".to_string(), queue.running.to_string()),
+            ("jobs_completed".to_string(), queue.completed.to_string()),
+            ("jobs_failed".to_string(), queue.failed.to_string()),
+            ("jobs_rejected_busy".to_string(), queue.rejected.to_string()),
+        ])
+    }
+
+    // Body of every worker thread
+    fn work(&self) {
+        loop {
+            let (job_id, job) = {
+                let mut queue = self.lock();
+                loop {
+                    match queue.pending.pop_front() {
+                        // Nobody waits for the result any more, e.g. the client timed out
+                        Some((job_id, _)) if queue.waiters.get(&job_id).is_some_and(|waiter| waiter.is_closed()) => {
+                            queue.waiters.remove(&job_id);
+                        },
+                        Some(next) => break next,
+                        None => {
+                            queue = self.job_available.wait(queue).unwrap_or_else(|poisoned| poisoned.into_inner());
+                        },
+                    }
+                }
+            };
+            {
+                let mut queue = self.lock();
+                queue.running += 1;
+                if let Some(state) = queue.polled.get_mut(&job_id) {
+                    *state = JobState::Running;
+                }
+            }
+
+            // A panicking job fails on its own instead of taking the worker thread with it
+            let result = std::panic::catch_unwind(AssertUnwindSafe(job))
+                .unwrap_or_else(|_| Err(ServiceError::new(ErrorCode::Internal, "the job panicked")));
+
+            let mut queue = self.lock();
+            queue.running -= 1;
+            if result.is_ok() {
+                queue.completed += 1;
+            } else {
+                queue.failed += 1;
+            }
+            match queue.waiters.remove(&job_id) {
+                // The connection may have closed in the meantime, then the result is dropped
+                Some(waiter) => { let _ = waiter.send(result); },
+                None => {
+                    queue.polled.insert(job_id, JobState::Done { result, expires_at: Instant::now() + JOB_RESULT_TTL });
+                },
+            }
+        }
+    }
+}
@@ -5,197 +5,390 @@
 //              Sign up new user (key-issuing authority-side)
 // ---------------------------------------
 #![allow(non_camel_case_types)]
-#![allow(private_in_public)]
 #![allow(unused_variables)]
 
 use tokio::net::TcpListener;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use serde_json::Value;
-use serde_json::json;
-use std::sync::Arc;
-use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use arke_common::admin::{serve_admin, serve_counted_connection, AdminKey, RequestStats};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::compression::compress;
+use arke_common::download::serve_chunk;
+use arke_common::signer::SignerClient;
+use arke_common::tls::{accept, TlsAcceptor};
+use arke_common::messages::{Blob, Compression, Request, Response};
+use arke_common::params_cache::{digest, PP_ZK_VERSION};
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize, SerializationError};
 use ark_std::io::Cursor;
 
 use ark_ec::bls12::Bls12;
-use arke_core::{ThresholdObliviousIdNIKE, IssuerPublicKey, BLSPublicParameters,
-                BlindIDCircuitParameters, RegistrarPublicKey, UserID,
+use arke_core::{ThresholdObliviousIdNIKE, IssuerPublicKey, BlindIDCircuitParameters, RegistrarPublicKey, UserID,
                 BlindPartialSecretKey, BlindRegistrationAttestation, BlindID};
 use rand::thread_rng;
 use ark_bls12_377::{Bls12_377, Parameters};
 use ark_ec::bw6::BW6;
 use ark_bw6_761::Parameters as Parameters761;
 use ark_bw6_761::BW6_761;
-use ark_bls12_377::FrParameters;
-use ark_ff::Fp256;
 use ark_ff::One;
-use secret_sharing::shamir_secret_sharing::SecretShare;
+use crate::dkg::bls_public_parameters;
+use crate::jobs::JobQueue;
+use crate::registrar_keys::RegistrarKeys;
+use crate::signer::{fetch_published, ExtractJob, Extractor, Shares};
+use crate::state::{self, AuthorityState, PublicSetup, PublishedSetup};
 type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
 /// Total number of participants
-const NUMBER_OF_PARTICIPANTS: usize = 10;
+pub const NUMBER_OF_PARTICIPANTS: usize = 10;
 /// Maximum number of dishonest key-issuing authorities that the system can tolerate
-const THRESHOLD: usize = 3;
-const REGISTRAR_DOMAIN: &'static [u8] = b"registration";
+pub const THRESHOLD: usize = 3;
+pub const REGISTRAR_DOMAIN: &'static [u8] = b"registration";
 
 
+/// The parameters and public keys of every issuer and who uses their shares, replaced as a whole by admin_reload
 #[derive(Clone)]
-pub struct keyIssuingAuthority {
-    pp_zk: Arc<BlindIDCircuitParameters<BW6<Parameters761>>>,
-    pp_issuance: Arc<BLSPublicParameters<Bls12<Parameters>>>,
-    honest_issuers_secret_keys: Arc<Vec<SecretShare<Fp256<FrParameters>>>>,
+struct Setup {
+    encoded_pp_zk: Arc<EncodedPpZk>,
     honest_issuers_public_keys: Arc<Vec<IssuerPublicKey<Bls12<Parameters>>>>,
+    extractor: Extractor,
+}
+
+#[derive(Clone)]
+pub struct keyIssuingAuthority {
+    setup: Arc<RwLock<Setup>>,
+    stats: Arc<RequestStats>,
+    jobs: Arc<JobQueue>,
+    registrar_keys: Arc<RegistrarKeys>,
+    // Where admin_reload reads the setup from, or the socket of the signer holding the shares
+    state_path: PathBuf,
 }
 
 impl keyIssuingAuthority {
-    pub async fn new() -> Self {
-        let mut rng = thread_rng();
-
-        let id = UserID::new("00000000");
-        let num_of_domain_sep_bytes = REGISTRAR_DOMAIN.len();
-        let num_of_identifier_bytes = id.0.as_bytes().len();
-        let num_of_blinding_factor_bits = ark_bls12_377::Fr::one().serialized_size() * 8;
-        // Simulate the zk-SNARK trusted setup
-        println!("- Running zk-SNARK trusted setup");
-        let pp_zk = ArkeIdNIKE::setup_blind_id_proof(
-            num_of_domain_sep_bytes,
-            num_of_identifier_bytes,
-            num_of_blinding_factor_bits,
-            &mut rng,
-        )
-        .unwrap();
-        println!("✓ Finished zk-SNARK trusted setup");
-
-        // Simulate the ID-NIKE.SetupDKG between participants
-        println!("- Running SetupDKG");
-        let (pp_issuance, honest_issuers_secret_keys, honest_issuers_public_keys) =
-            ArkeIdNIKE::simulate_issuers_DKG(THRESHOLD, NUMBER_OF_PARTICIPANTS, &mut rng).unwrap();
-        println!("✓ Finished SetupDKG");
-
-        Self { pp_zk: Arc::new(pp_zk),
-               pp_issuance: Arc::new(pp_issuance),
-               honest_issuers_secret_keys: Arc::new(honest_issuers_secret_keys),
-               honest_issuers_public_keys: Arc::new(honest_issuers_public_keys),
+    /// Load the setup saved at state_path, or run a new one if there is none or if regenerate is set
+    pub async fn new(state_path: &Path, regenerate: bool, jobs: Arc<JobQueue>, registrar_keys: RegistrarKeys) -> Result<Self, Box<dyn std::error::Error>> {
+        let state = setup(state_path, regenerate)?;
+        Ok(Self::from_setup(state_path, Setup::from_state(state)?, jobs, registrar_keys))
+    }
+
+    /// Serve with the shares held by the signer, which also sends the public setup
+    pub async fn with_signer(signer: SignerClient, jobs: Arc<JobQueue>,
+                             registrar_keys: RegistrarKeys) -> Result<Self, Box<dyn std::error::Error>> {
+        println!("- Fetching the public setup from the signer at {}", signer.path().display());
+        let published = fetch_published(&signer).await?;
+        println!("✓ The issuer shares are held by the signer at {}", signer.path().display());
+        let signer_path = signer.path().to_path_buf();
+        Ok(Self::from_setup(&signer_path, Setup::published(published, signer)?, jobs, registrar_keys))
+    }
+
+    fn from_setup(state_path: &Path, setup: Setup, jobs: Arc<JobQueue>, registrar_keys: RegistrarKeys) -> Self {
+        Self {
+            setup: Arc::new(RwLock::new(setup)),
+            stats: Arc::new(RequestStats::new()),
+            jobs,
+            registrar_keys: Arc::new(registrar_keys),
+            state_path: state_path.to_path_buf(),
         }
     }
 
+    fn setup(&self) -> Setup {
+        // Only ever replaced as a whole, a panic cannot leave it half-written
+        self.setup.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
 
-    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
-        let listener = TcpListener::bind("127.0.0.1:8081").await?;
+    /// Serve clients on bind, over TLS if there is an acceptor, and arke-admin on the admin address if there is one
+    pub async fn start(self, bind: SocketAddr, admin: Option<(SocketAddr, AdminKey)>, tls: Option<TlsAcceptor>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((admin_bind, admin_key)) = admin {
+            let key_issuing_authority = self.clone();
+            let stats = Arc::clone(&self.stats);
+            let admin_tls = tls.clone();
+            println!("Admin endpoint running on {}{}...", admin_bind, if tls.is_some() { " over TLS" } else { "" });
+            tokio::spawn(async move {
+                let served = serve_admin(admin_bind, Arc::new(admin_key), stats, admin_tls, move |request| {
+                    let key_issuing_authority = key_issuing_authority.clone();
+                    async move { key_issuing_authority.process_admin_request(request).await }
+                }).await;
+                if let Err(e) = served {
+                    eprintln!("X Admin endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        let listener = TcpListener::bind(bind).await?;
 
         loop {
-            let (mut socket, _) = listener.accept().await?;
-            let pp_zk = Arc::clone(&self.pp_zk);
-            let pp_issuance = Arc::clone(&self.pp_issuance);
-            let honest_issuers_secret_keys = Arc::clone(&self.honest_issuers_secret_keys);
-            let honest_issuers_public_keys = Arc::clone(&self.honest_issuers_public_keys);
+            let (socket, _) = listener.accept().await?;
+            let key_issuing_authority = self.clone();
+            let tls = tls.clone();
 
             tokio::spawn(async move {
-                let mut buf = vec![0; 4096];
-
-                loop {
-                    match socket.read(&mut buf).await {
-                        // Connection closed
-                        Ok(n) if n == 0 => return,
-                        // Connection open  
-                        Ok(n) => {
-                            let request: Value = serde_json::from_slice(&buf[..n]).unwrap();
-                            // Handle request 
-                            let response = process_request(request, &pp_zk, &pp_issuance, 
-                                                                    &honest_issuers_secret_keys, 
-                                                                    &honest_issuers_public_keys).await;
-                            let response_bytes = serde_json::to_vec(&response).unwrap();
-                            if let Err(e) = socket.write_all(&response_bytes).await {
-                                eprintln!("failed to write to socket; err = {:?}", e);
-                                return;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("failed to read from socket; err = {:?}", e);
-                            return;
-                        }
-                    }
-                }
+                let socket = match accept(tls.as_ref(), socket).await {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        eprintln!("X TLS handshake failed; err = {}", e);
+                        return;
+                    },
+                };
+                let stats = Arc::clone(&key_issuing_authority.stats);
+                serve_counted_connection(socket, stats, move |request| {
+                    // Taken per request, so a reload applies to connections that are already open
+                    let setup = key_issuing_authority.setup();
+                    let jobs = Arc::clone(&key_issuing_authority.jobs);
+                    let registrar_keys = Arc::clone(&key_issuing_authority.registrar_keys);
+                    async move { process_request(request, setup, &jobs, &registrar_keys).await }
+                }).await
             });
         }
     }
+
+    async fn process_admin_request(&self, request: Request) -> Result<Response, ServiceError> {
+        match request {
+            Request::AdminHealth => {
+                let setup = self.setup();
+                let mut details = BTreeMap::from([
+                    ("mode".to_string(), "all issuers in one process".to_string()),
+                    ("issuers".to_string(), setup.honest_issuers_public_keys.len().to_string()),
+                    ("issuer_shares_held_by".to_string(), setup.extractor.held_by()),
+                    ("threshold".to_string(), THRESHOLD.to_string()),
+                    ("state_path".to_string(), self.state_path.display().to_string()),
+                    ("registrar_keys_from".to_string(), self.registrar_keys.endpoint().to_string()),
+                    ("pp_zk_digest".to_string(), setup.encoded_pp_zk.digest.clone()),
+                ]);
+                details.extend(self.jobs.metrics());
+                Ok(Response::AdminHealth { health: self.stats.health("key_issuing_authority", details) })
+            },
+
+            Request::AdminReload => {
+                let (setup, reloaded) = match self.setup().extractor {
+                    Extractor::Shares(_) => {
+                        // Unlike at startup, a missing setup is an error here rather than a reason to run a new one
+                        let state = state::load(&self.state_path).map_err(ServiceError::storage)?
+                            .ok_or_else(|| ServiceError::storage(format!("there is no saved setup in {}", self.state_path.display())))?;
+                        (Setup::from_state(state).map_err(ServiceError::internal)?, "the setup")
+                    },
+                    // The signer keeps the shares it started with, only the public setup is fetched again
+                    Extractor::Signer(signer) => {
+                        let published = fetch_published(&signer).await?;
+                        (Setup::published(published, signer).map_err(ServiceError::internal)?, "the public setup")
+                    },
+                };
+                *self.setup.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = setup;
+                println!("✓ Reloaded {} for an admin", reloaded);
+                Ok(Response::AdminReloaded { detail: format!("reloaded {} from {}", reloaded, self.state_path.display()) })
+            },
+
+            other => Err(ServiceError::new(ErrorCode::UnsupportedAction,
+                format!("{} is not an admin action of the key-issuing authority", other.action()))),
+        }
+    }
+}
+
+impl Setup {
+    fn from_state(state: AuthorityState) -> Result<Self, SerializationError> {
+        let encoded_pp_zk = EncodedPpZk::new(&state.pp_zk)?;
+        let (shares, honest_issuers_public_keys) = Shares::from_authority_state(state);
+        Ok(Self { encoded_pp_zk: Arc::new(encoded_pp_zk),
+                  honest_issuers_public_keys: Arc::new(honest_issuers_public_keys),
+                  extractor: Extractor::Shares(Arc::new(shares)),
+        })
+    }
+
+    fn published(published: PublishedSetup, signer: SignerClient) -> Result<Self, SerializationError> {
+        Ok(Self { encoded_pp_zk: Arc::new(EncodedPpZk::new(&published.pp_zk)?),
+                  honest_issuers_public_keys: Arc::new(published.issuers_public_keys),
+                  extractor: Extractor::Signer(signer),
+        })
+    }
+}
+
+/// pp_zk as served by get_pp_zk, encoded once when the setup is loaded rather than on every request
+pub struct EncodedPpZk {
+    pub bytes: Vec<u8>,
+    /// Published by get_pp_zk_digest, so clients can tell whether their cached copy is still current
+    pub digest: String,
+    // Compressed on the first request asking for it
+    zstd: OnceLock<Vec<u8>>,
+}
+
+impl EncodedPpZk {
+    pub fn new(pp_zk: &BlindIDCircuitParameters<BW6<Parameters761>>) -> Result<Self, SerializationError> {
+        let mut bytes = Vec::new();
+        pp_zk.serialize(&mut bytes)?;
+        let digest = digest(&bytes);
+        Ok(Self { bytes, digest, zstd: OnceLock::new() })
+    }
+
+    // The bytes sent for pp_zk with compression
+    fn served(&self, compression: Option<Compression>) -> Result<&[u8], ServiceError> {
+        match compression {
+            None => Ok(self.bytes.as_slice()),
+            Some(Compression::Zstd) => match self.zstd.get() {
+                Some(compressed) => Ok(compressed.as_slice()),
+                None => {
+                    let compressed = compress(Compression::Zstd, &self.bytes).map_err(ServiceError::internal)?;
+                    // Two requests may race to compress it, both get the same bytes
+                    Ok(self.zstd.get_or_init(|| compressed).as_slice())
+                },
+            },
+        }
+    }
+
+    pub fn response(&self, compression: Option<Compression>) -> Result<Response, ServiceError> {
+        Ok(Response::PpZk { pp_zk: Blob(self.served(compression)?.to_vec()), compression })
+    }
+
+    pub fn chunk_response(&self, offset: u64, length: u32, compression: Option<Compression>) -> Result<Response, ServiceError> {
+        serve_chunk(self.served(compression)?, &self.digest, compression, offset, length)
+    }
+
+    pub fn digest_response(&self) -> Response {
+        Response::PpZkDigest { version: PP_ZK_VERSION, digest: self.digest.clone() }
+    }
+}
+
+
+/// Load the setup saved at state_path, or run the zk-SNARK trusted setup and the DKG if there is none or if regenerate is set
+pub fn setup(state_path: &Path, regenerate: bool) -> Result<AuthorityState, Box<dyn std::error::Error>> {
+    if regenerate {
+        println!("- Regenerating the setup, user keys issued so far will stop working");
+    } else {
+        println!("- Loading the setup from {}", state_path.display());
+        if let Some(state) = state::load(state_path)? {
+            println!("✓ Loaded the setup");
+            return Ok(state);
+        }
+        println!("- No saved setup found");
+    }
+
+    let mut rng = thread_rng();
+    let pp_zk = zk_snark_setup();
+
+    // Simulate the ID-NIKE.SetupDKG between participants
+    println!("- Running SetupDKG");
+    let (pp_issuance, honest_issuers_secret_keys, honest_issuers_public_keys) =
+        ArkeIdNIKE::simulate_issuers_DKG(THRESHOLD, NUMBER_OF_PARTICIPANTS, &mut rng).unwrap();
+    println!("✓ Finished SetupDKG");
+
+    let state = AuthorityState { pp_zk,
+                                 pp_issuance,
+                                 issuers_secret_keys: honest_issuers_secret_keys,
+                                 issuers_public_keys: honest_issuers_public_keys,
+    };
+    state::save(state_path, &state)?;
+    println!("✓ Saved the setup to {}", state_path.display());
+
+    Ok(state)
+}
+
+/// Load the public setup saved at path, or create it if there is none or if regenerate is set.
+/// Unlike setup, this creates no secret shares, the issuer nodes generate them with the DKG.
+pub fn public_setup(path: &Path, regenerate: bool) -> Result<PublicSetup, Box<dyn std::error::Error>> {
+    if !regenerate {
+        println!("- Loading the public setup from {}", path.display());
+        if let Some(public_setup) = state::load_public_setup(path)? {
+            println!("✓ Loaded the public setup");
+            return Ok(public_setup);
+        }
+        println!("- No saved public setup found");
+    }
+
+    let public_setup = PublicSetup { pp_zk: zk_snark_setup(), pp_issuance: bls_public_parameters() };
+    state::save_public_setup(path, &public_setup)?;
+    println!("✓ Saved the public setup to {}", path.display());
+
+    Ok(public_setup)
+}
+
+fn zk_snark_setup() -> BlindIDCircuitParameters<BW6<Parameters761>> {
+    let mut rng = thread_rng();
+
+    let id = UserID::new("00000000");
+    let num_of_domain_sep_bytes = REGISTRAR_DOMAIN.len();
+    let num_of_identifier_bytes = id.0.as_bytes().len();
+    let num_of_blinding_factor_bits = ark_bls12_377::Fr::one().serialized_size() * 8;
+    // Simulate the zk-SNARK trusted setup
+    println!("- Running zk-SNARK trusted setup");
+    let pp_zk = ArkeIdNIKE::setup_blind_id_proof(
+        num_of_domain_sep_bytes,
+        num_of_identifier_bytes,
+        num_of_blinding_factor_bits,
+        &mut rng,
+    )
+    .unwrap();
+    println!("✓ Finished zk-SNARK trusted setup");
+    pp_zk
 }
 
 
-async fn process_request(request: Value,
-                        pp_zk: &Arc<BlindIDCircuitParameters<BW6<Parameters761>>>,
-                        pp_issuance: &Arc<BLSPublicParameters<Bls12<Parameters>>>,
-                        honest_issuers_secret_keys: &Arc<Vec<SecretShare<Fp256<FrParameters>>>>,
-                        honest_issuers_public_keys: &Arc<Vec<IssuerPublicKey<Bls12<Parameters>>>>,) -> Value {
-    match request["action"].as_str() {
-        Some("get_pp_zk") => {
-            let mut pp_zk_bytes = Vec::new();
-            pp_zk.serialize(&mut pp_zk_bytes).unwrap();
-            let pp_zk_str = base64::encode(&pp_zk_bytes);   
-            
-            json!({ "status": "success", "message": "✓ Got pp_zk", 
-                    "pp_zk": pp_zk_str, 
-                 })
+async fn process_request(request: Request, setup: Setup, jobs: &JobQueue, registrar_keys: &RegistrarKeys) -> Result<Response, ServiceError> {
+    match request {
+        Request::GetPpZk { compression } => setup.encoded_pp_zk.response(compression),
+
+        Request::GetPpZkDigest => Ok(setup.encoded_pp_zk.digest_response()),
+
+        Request::GetPpZkChunk { offset, length, compression } => setup.encoded_pp_zk.chunk_response(offset, length, compression),
+
+        Request::GetIssuerPublicKeys => {
+            Ok(Response::IssuerPublicKeys { issuer_public_keys: serialize_issuer_public_keys(&setup.honest_issuers_public_keys)? })
         },
         
-        Some("to_VerifyID_and_BlindPartialExtract") => {
-            let registrar_public_key_base64 = request["registrar_public_key_base64"].as_str().unwrap().to_string();
-            // Decode from base64
-            let registrar_public_key_bytes = base64::decode(&registrar_public_key_base64).unwrap();
-            // CanonicalDeserialize 
-            let mut registrar_public_key_cursor = Cursor::new(&registrar_public_key_bytes);
-            let registrar_public_key = RegistrarPublicKey::<Bls12<Parameters>>::deserialize(&mut registrar_public_key_cursor).unwrap(); 
-
-            let blind_id_base64 = request["blind_id_base64"].as_str().unwrap().to_string();
-            // Decode from base64
-            let blind_id_bytes = base64::decode(&blind_id_base64).unwrap();
-            // CanonicalDeserialize 
-            let mut blind_id_cursor = Cursor::new(&blind_id_bytes);
-            let blind_id = BlindID::<Bls12<Parameters>, BW6_761>::deserialize(&mut blind_id_cursor).unwrap();
-            
-            let blind_reg_attestation_base64 = request["blind_reg_attestation_base64"].as_str().unwrap().to_string();
-            // Decode from base64
-            let blind_reg_attestation_bytes = base64::decode(&blind_reg_attestation_base64).unwrap();
-            // CanonicalDeserialize 
-            let mut blind_reg_attestation_cursor = Cursor::new(&blind_reg_attestation_bytes);
-            let blind_reg_attestation = BlindRegistrationAttestation::<Bls12<Parameters>>::deserialize(&mut blind_reg_attestation_cursor).unwrap();
-
-            // Run ID-NIKE.VerifyID and ID-NIKE.BlindPartialExtract
-            println!("- Running VerifyID and BlindPartialExtract");
-            let honest_issuers_secret_keys_vec = Arc::try_unwrap(honest_issuers_secret_keys.clone()).unwrap_or_else(|shared_vec| (*shared_vec).clone());
-            let blind_partial_user_keys: Vec<BlindPartialSecretKey<Bls12_377>> = honest_issuers_secret_keys_vec
-                .iter()
-                .zip(honest_issuers_public_keys.iter())
-                .map(|(secret_key, _public_key)| {
-                    ArkeIdNIKE::blind_partial_extract(
-                        &pp_issuance,
-                        pp_zk,
-                        &registrar_public_key,
-                        secret_key,
-                        &blind_id,
-                        &blind_reg_attestation,
-                        REGISTRAR_DOMAIN,
-                    ).unwrap()
-                }).collect();
-            println!("✓ Finished VerifyID and BlindPartialExtract");
-
-            let mut serialized_keys = Vec::new();
-            // Iterate through the vector, serializing each key individually
-            for key in &blind_partial_user_keys {
-                // Create a Vec<u8> to hold the serialized version of each key
-                let mut serialized_key = Vec::new();
-                // Serialize each key into the serialized_key buffer
-                key.serialize(&mut serialized_key).unwrap();
-                // Extend serialized_keys Vec with each serialized_key
-                serialized_keys.extend(serialized_key);
-            }
-            let blind_partial_user_keys_str = base64::encode(&serialized_keys); 
-
-            json!({ "status": "success", "message": "✓ Finished VerifyID and BlindPartialExtract", 
-                "blind_partial_user_keys": blind_partial_user_keys_str,
-            })
+        Request::VerifyIdAndBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } => {
+            let job = setup.extractor.job(registrar_keys, registrar_key_id, blind_id, blind_reg_attestation).await?;
+            jobs.run(move || blind_partial_extract(job)).await
         },
 
-        _ => {
-            json!({ "status": "error", "message": "invalid action" })
+        Request::SubmitBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } => {
+            let job = setup.extractor.job(registrar_keys, registrar_key_id, blind_id, blind_reg_attestation).await?;
+            jobs.submit(move || blind_partial_extract(job))
         },
+
+        Request::GetJobStatus { job_id } => jobs.status(&job_id),
+
+        other => Err(ServiceError::unsupported_action(other.action())),
     }
-}
\ No newline at end of file
+}
+
+// Run on a worker of the job queue, with the shares of every issuer
+fn blind_partial_extract(job: ExtractJob) -> Result<Response, ServiceError> {
+    Ok(Response::BlindPartialUserKeys { blind_partial_user_keys: job()? })
+}
+
+
+/// The deserialized fields of a to_VerifyID_and_BlindPartialExtract request
+pub type ExtractRequest = (RegistrarPublicKey<Bls12<Parameters>>,
+                           BlindID<Bls12<Parameters>, BW6_761>,
+                           BlindRegistrationAttestation<Bls12<Parameters>>);
+
+/// Look up the registrar key and CanonicalDeserialize the other fields of a to_VerifyID_and_BlindPartialExtract request.
+/// A retired or unknown registrar key is refused before the request takes a place in the queue
+pub async fn deserialize_extract_request(registrar_keys: &RegistrarKeys, registrar_key_id: u32, blind_id: &Blob, blind_reg_attestation: &Blob)
+    -> Result<ExtractRequest, ServiceError> {
+    let registrar_public_key = registrar_keys.valid_key(registrar_key_id).await?;
+
+    // CanonicalDeserialize 
+    let mut blind_id_cursor = Cursor::new(&blind_id.0);
+    let blind_id = BlindID::<Bls12<Parameters>, BW6_761>::deserialize(&mut blind_id_cursor)
+        .map_err(|e| ServiceError::bad_encoding("blind_id", e))?;
+    
+    // CanonicalDeserialize 
+    let mut blind_reg_attestation_cursor = Cursor::new(&blind_reg_attestation.0);
+    let blind_reg_attestation = BlindRegistrationAttestation::<Bls12<Parameters>>::deserialize(&mut blind_reg_attestation_cursor)
+        .map_err(|e| ServiceError::bad_encoding("blind_reg_attestation", e))?;
+
+    Ok((registrar_public_key, blind_id, blind_reg_attestation))
+}
+
+pub fn serialize_issuer_public_keys(issuer_public_keys: &[IssuerPublicKey<Bls12<Parameters>>]) -> Result<Vec<Blob>, ServiceError> {
+    issuer_public_keys
+        .iter()
+        .map(|public_key| {
+            let mut serialized_key = Vec::new();
+            public_key.serialize(&mut serialized_key).map_err(ServiceError::internal)?;
+            Ok(Blob(serialized_key))
+        }).collect()
+}
+
+pub fn serialize_blind_partial_user_key(key: &BlindPartialSecretKey<Bls12_377>) -> Result<Blob, ServiceError> {
+    let mut serialized_key = Vec::new();
+    key.serialize(&mut serialized_key).map_err(ServiceError::internal)?;
+    Ok(Blob(serialized_key))
+}
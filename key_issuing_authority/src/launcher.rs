@@ -0,0 +1,158 @@
+// ---------------------------------------
+// File: launcher.rs
+// Date: 18 Oct 2026
+// Description: Start every key issuer as a separate node on localhost
+// ---------------------------------------
+
+// The launcher runs (or loads) the setup, deals each issuer its share in
+// <data dir>/node_<index>/ and starts one key_issuing_authority process per
+// issuer with --node. It is meant for local tests: the launcher itself still
+// sees every share, the nodes it starts do not.
+// With --dkg the launcher only creates the public setup and the nodes generate
+// their shares between them, so no process sees every share. Every node creates
+// its own DKG identity key, the launcher only collects the public halves.
+
+use std::path::Path;
+use std::time::Duration;
+use clap::ValueEnum;
+use tokio::process::{Child, Command};
+use arke_common::admin::AdminKey;
+use crate::config::Config;
+use crate::dkg::DkgFault;
+use crate::key_issuing_authority::{public_setup, setup, NUMBER_OF_PARTICIPANTS};
+use crate::state::{self, NodeState};
+
+pub async fn launch_nodes(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let mut dkg_peer_keys = Vec::new();
+    if config.dkg {
+        public_setup(&config.public_setup_path(), config.regenerate)?;
+        for issuer_index in 0..NUMBER_OF_PARTICIPANTS {
+            let node_data_dir = config.node_data_dir(issuer_index);
+            std::fs::create_dir_all(&node_data_dir)?;
+            dkg_peer_keys.push(dkg_peer_key(&exe, &node_data_dir).await?);
+        }
+    } else {
+        deal_shares(config)?;
+    }
+
+    // Created here rather than by the nodes, so they do not race to create it
+    if config.admin_bind.is_some() {
+        AdminKey::load_or_create(&config.admin_key_path())?;
+    }
+
+    let dkg_peers: Vec<String> = (0..NUMBER_OF_PARTICIPANTS)
+        .map(|issuer_index| config.node_dkg_address(issuer_index).to_string())
+        .collect();
+    let mut nodes: Vec<Option<Child>> = Vec::new();
+    for issuer_index in 0..NUMBER_OF_PARTICIPANTS {
+        // A node left out on purpose behaves like a node that is down
+        if config.skip_nodes.contains(&(issuer_index as u32)) {
+            println!("- Not starting issuer node {}", issuer_index);
+            nodes.push(None);
+            continue;
+        }
+        let bind = config.node_bind(issuer_index);
+        let mut command = Command::new(&exe);
+        command
+            .arg("--node").arg(issuer_index.to_string())
+            .arg("--bind").arg(bind.to_string())
+            .arg("--data-dir").arg(config.node_data_dir(issuer_index))
+            .arg("--workers").arg(config.workers.to_string())
+            .arg("--queue-capacity").arg(config.queue_capacity.to_string())
+            .arg("--registration-authority").arg(&config.registration_authority)
+            .kill_on_drop(true);
+        if let Some(pin) = &config.registration_authority_pin {
+            command.arg("--registration-authority-pin").arg(pin.to_string());
+        }
+        if config.insecure_plaintext {
+            command.arg("--insecure-plaintext");
+        }
+        // Every node answers arke-admin on its own port, with the admin key of the launcher
+        match config.node_admin_bind(issuer_index) {
+            Some(admin_bind) => {
+                command
+                    .arg("--admin-bind").arg(admin_bind.to_string())
+                    .arg("--admin-key").arg(config.admin_key_path());
+            },
+            None => {
+                command.arg("--no-admin");
+            },
+        }
+        if let Some(tls) = &config.tls {
+            command.arg("--tls-cert").arg(&tls.cert).arg("--tls-key").arg(&tls.key);
+        }
+        if config.dkg {
+            let fault = config.faulty_nodes.iter()
+                .find(|(faulty_node, _)| *faulty_node as usize == issuer_index)
+                .map_or(DkgFault::None, |(_, fault)| *fault);
+            command
+                .arg("--dkg-peer").arg(dkg_peers.join(","))
+                .arg("--dkg-peer-key").arg(dkg_peer_keys.join(","))
+                .arg("--dkg-round-timeout").arg(config.dkg_round_timeout.as_secs().to_string())
+                .arg("--public-setup").arg(config.public_setup_path())
+                .arg("--dkg-fault").arg(fault.to_possible_value().unwrap().get_name());
+            // A new public setup invalidates the shares of an earlier DKG
+            if config.regenerate {
+                command.arg("--regenerate");
+            }
+        }
+        let child = command.spawn()?;
+        println!("✓ Started issuer node {} on {}", issuer_index, bind);
+        nodes.push(Some(child));
+    }
+
+    // Keep the other nodes running when one of them exits, so tests can stop single nodes
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {
+                for (issuer_index, node) in nodes.iter_mut().enumerate() {
+                    if let Some(child) = node {
+                        if let Some(status) = child.try_wait()? {
+                            println!("X Issuer node {} exited ({})", issuer_index, status);
+                            *node = None;
+                        }
+                    }
+                }
+                if nodes.iter().all(Option::is_none) {
+                    return Err("every issuer node exited".into());
+                }
+            }
+        }
+    }
+
+    println!("- Stopping the issuer nodes");
+    for child in nodes.iter_mut().flatten() {
+        child.kill().await?;
+    }
+    Ok(())
+}
+
+// The public DKG key of the node with data_dir, which the node creates itself if it has none
+async fn dkg_peer_key(exe: &Path, data_dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new(exe).arg("--data-dir").arg(data_dir).arg("--show-dkg-key").output().await?;
+    if !output.status.success() {
+        return Err(format!("could not get the DKG key of the node in {}: {}",
+                           data_dir.display(), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    // The key is the last line, after the note that it was created
+    let stdout = String::from_utf8(output.stdout)?;
+    Ok(stdout.lines().last().unwrap_or_default().to_string())
+}
+
+// Run or load the setup and write the share of every issuer to the directory of its node
+fn deal_shares(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let state = setup(&config.state_path(), config.regenerate)?;
+
+    // Deal the shares again at every start, so the nodes always match the saved setup
+    println!("- Dealing the issuer shares");
+    for issuer_index in 0..state.issuers_secret_keys.len() {
+        let node_data_dir = config.node_data_dir(issuer_index);
+        std::fs::create_dir_all(&node_data_dir)?;
+        let node_state = NodeState::from_authority_state(&state, issuer_index);
+        state::save_node(&Config::node_state_path_in(&node_data_dir), &node_state)?;
+    }
+    println!("✓ Dealt {} issuer shares", state.issuers_secret_keys.len());
+    Ok(())
+}
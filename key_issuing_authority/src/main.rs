@@ -1,12 +1,152 @@
 use tokio::runtime::Runtime;
+use arke_common::admin::AdminKey;
+use arke_common::signer::SignerClient;
+use arke_common::tls::load_acceptor;
+mod config;
+use config::Config;
+mod dkg;
+use dkg::DkgIdentity;
+mod jobs;
+use jobs::JobQueue;
 mod key_issuing_authority;
-use key_issuing_authority::keyIssuingAuthority; 
+mod launcher;
+mod node;
+mod registrar_keys;
+mod signer;
+mod state;
+use key_issuing_authority::keyIssuingAuthority;
+use node::issuerNode;
 
 fn main() {
-    println!("Key-issuing authority running...");
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // The public half goes to the configuration of the other nodes, the key itself stays in the data directory
+    if config.show_dkg_key {
+        match DkgIdentity::load_or_create(&config.dkg_identity_path()) {
+            Ok(identity) => println!("{}", identity.public_key()),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Runs the DKG in a process of its own, a signer started afterwards only reads the share it saves
+    if config.dkg_only {
+        let dkg_config = config.dkg_config().expect("checked to have --node and --dkg-peer when the config was loaded");
+        let rt = Runtime::new().unwrap();
+        let ran = rt.block_on(node::load_or_run_dkg(&config.node_state_path(), &config.public_setup_path(), &dkg_config,
+                                                    config.dkg_bind().unwrap(), config.regenerate));
+        if let Err(e) = ran {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // The signer serves no clients and no admin, only the network process on its socket
+    if config.run_signer {
+        let signer_path = config.signer_path();
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let (shares, published) = match signer::load(&config) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    eprintln!("X {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Issuer signer running on {}...", signer_path.display());
+            if let Err(e) = signer::serve(&signer_path, shares, published, config.registrar_keys()).await {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        });
+        return;
+    }
+
+    // Created on first start, arke-admin needs a copy of it. The launcher only passes its own on to the nodes.
+    let admin = match config.admin_bind {
+        Some(admin_bind) if !config.launch_nodes => match AdminKey::load_or_create(&config.admin_key_path()) {
+            Ok(admin_key) => Some((admin_bind, admin_key)),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    // The launcher does not serve clients, it passes the files on to the nodes
+    let tls = match &config.tls {
+        Some(files) if !config.launch_nodes => match load_acceptor(files) {
+            Ok(acceptor) => Some(acceptor),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    let over_tls = if tls.is_some() { " over TLS" } else { "" };
+
     let rt = Runtime::new().unwrap();
+    if config.launch_nodes {
+        println!("Launching {} key issuer nodes...", key_issuing_authority::NUMBER_OF_PARTICIPANTS);
+        rt.block_on(async {
+            if let Err(e) = launcher::launch_nodes(&config).await {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        });
+        return;
+    }
+    if let Some(node) = config.node {
+        println!("Key issuer node {} running on {}{}...", node, config.bind, over_tls);
+        let jobs = JobQueue::start(config.workers, config.queue_capacity);
+        rt.block_on(async {
+            let registrar_keys = config.registrar_keys();
+            // With a signer, the DKG ran before with --dkg-only
+            let issuer_node = match (&config.signer, config.dkg_config()) {
+                (Some(signer), _) => issuerNode::with_signer(node, SignerClient::new(signer.clone()), jobs, registrar_keys).await,
+                (None, Some(dkg_config)) => issuerNode::from_dkg(&config.node_state_path(), &config.public_setup_path(), &dkg_config,
+                                                                 config.dkg_bind().unwrap(), config.regenerate, jobs, registrar_keys).await,
+                (None, None) => issuerNode::new(&config.node_state_path(), node, jobs, registrar_keys),
+            };
+            let issuer_node = match issuer_node {
+                Ok(issuer_node) => issuer_node,
+                Err(e) => {
+                    eprintln!("X {}", e);
+                    std::process::exit(1);
+                }
+            };
+            issuer_node.start(config.bind, admin, tls).await.unwrap();
+        });
+        return;
+    }
+
+    println!("Key-issuing authority running on {}{}...", config.bind, over_tls);
+    let jobs = JobQueue::start(config.workers, config.queue_capacity);
     rt.block_on(async {
-        let key_issuing_athority = keyIssuingAuthority::new().await;
-        key_issuing_athority.start().await.unwrap();
+        let registrar_keys = config.registrar_keys();
+        let key_issuing_athority = match &config.signer {
+            Some(signer) => keyIssuingAuthority::with_signer(SignerClient::new(signer.clone()), jobs, registrar_keys).await,
+            None => keyIssuingAuthority::new(&config.state_path(), config.regenerate, jobs, registrar_keys).await,
+        };
+        let key_issuing_athority = match key_issuing_athority {
+            Ok(key_issuing_athority) => key_issuing_athority,
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        };
+        key_issuing_athority.start(config.bind, admin, tls).await.unwrap();
     });
 }
\ No newline at end of file
@@ -0,0 +1,290 @@
+// ---------------------------------------
+// File: node.rs
+// Date: 18 Oct 2026
+// Description: A single key issuer running as its own networked node
+// ---------------------------------------
+#![allow(non_camel_case_types)]
+
+// Unlike keyIssuingAuthority, a node holds the secret share of one issuer only,
+// so a client has to collect THRESHOLD + 1 answers from different nodes to get its key.
+
+use tokio::net::TcpListener;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use arke_common::admin::{serve_admin, serve_counted_connection, AdminKey, RequestStats};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::tls::{accept, TlsAcceptor};
+use arke_common::messages::{Blob, Request, Response};
+use arke_common::signer::SignerClient;
+use ark_serialize::SerializationError;
+
+use ark_ec::bls12::Bls12;
+use arke_core::IssuerPublicKey;
+use ark_bls12_377::Parameters;
+use crate::dkg::{run_dkg, DkgConfig};
+use crate::jobs::JobQueue;
+use crate::registrar_keys::RegistrarKeys;
+use crate::key_issuing_authority::{serialize_issuer_public_keys, EncodedPpZk};
+use crate::signer::{fetch_published, ExtractJob, Extractor, Shares};
+use crate::state::{self, NodeState, PublishedSetup};
+
+
+/// The public keys of the issuers and pp_zk encoded for get_pp_zk, and who uses the share of the node
+#[derive(Clone)]
+struct Loaded {
+    issuer_index: u32,
+    issuers_public_keys: Arc<Vec<IssuerPublicKey<Bls12<Parameters>>>>,
+    encoded_pp_zk: Arc<EncodedPpZk>,
+    extractor: Extractor,
+}
+
+#[derive(Clone)]
+pub struct issuerNode {
+    // Replaced as a whole by admin_reload
+    loaded: Arc<RwLock<Loaded>>,
+    stats: Arc<RequestStats>,
+    jobs: Arc<JobQueue>,
+    registrar_keys: Arc<RegistrarKeys>,
+    // Where admin_reload reads the share from, or the socket of the signer holding the share
+    state_path: PathBuf,
+}
+
+impl issuerNode {
+    /// Load the share dealt to the node of issuer_index at state_path
+    pub fn new(state_path: &Path, issuer_index: u32, jobs: Arc<JobQueue>, registrar_keys: RegistrarKeys) -> Result<Self, Box<dyn std::error::Error>> {
+        let state = load_share_at_startup(state_path, issuer_index)?;
+        Ok(Self::from_loaded(state_path, Loaded::new(state)?, jobs, registrar_keys))
+    }
+
+    /// Serve the node of issuer_index with the share held by the signer, which also sends the public setup
+    pub async fn with_signer(issuer_index: u32, signer: SignerClient, jobs: Arc<JobQueue>,
+                             registrar_keys: RegistrarKeys) -> Result<Self, Box<dyn std::error::Error>> {
+        println!("- Fetching the public setup from the signer at {}", signer.path().display());
+        let published = fetch_published(&signer).await?;
+        println!("✓ The share of issuer {} is held by the signer at {}", issuer_index, signer.path().display());
+        let signer_path = signer.path().to_path_buf();
+        Ok(Self::from_loaded(&signer_path, Loaded::published(issuer_index, published, signer)?, jobs, registrar_keys))
+    }
+
+    fn from_loaded(state_path: &Path, loaded: Loaded, jobs: Arc<JobQueue>, registrar_keys: RegistrarKeys) -> Self {
+        Self {
+            loaded: Arc::new(RwLock::new(loaded)),
+            stats: Arc::new(RequestStats::new()),
+            jobs,
+            registrar_keys: Arc::new(registrar_keys),
+            state_path: state_path.to_path_buf(),
+        }
+    }
+
+    fn loaded(&self) -> Loaded {
+        // Only ever replaced as a whole, a panic cannot leave it half-written
+        self.loaded.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Load the share of this node at state_path, or generate it with the other nodes if there is none
+    /// or if regenerate is set
+    pub async fn from_dkg(state_path: &Path, public_setup_path: &Path, dkg_config: &DkgConfig, dkg_bind: SocketAddr,
+                          regenerate: bool, jobs: Arc<JobQueue>, registrar_keys: RegistrarKeys) -> Result<Self, Box<dyn std::error::Error>> {
+        let state = load_or_run_dkg(state_path, public_setup_path, dkg_config, dkg_bind, regenerate).await?;
+        Ok(Self::from_loaded(state_path, Loaded::new(state)?, jobs, registrar_keys))
+    }
+
+    /// Serve clients on bind, over TLS if there is an acceptor, and arke-admin on the admin address if there is one
+    pub async fn start(self, bind: SocketAddr, admin: Option<(SocketAddr, AdminKey)>, tls: Option<TlsAcceptor>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((admin_bind, admin_key)) = admin {
+            let issuer_node = self.clone();
+            let stats = Arc::clone(&self.stats);
+            let admin_tls = tls.clone();
+            println!("Admin endpoint running on {}{}...", admin_bind, if tls.is_some() { " over TLS" } else { "" });
+            tokio::spawn(async move {
+                let served = serve_admin(admin_bind, Arc::new(admin_key), stats, admin_tls, move |request| {
+                    let issuer_node = issuer_node.clone();
+                    async move { issuer_node.process_admin_request(request).await }
+                }).await;
+                if let Err(e) = served {
+                    eprintln!("X Admin endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        let listener = TcpListener::bind(bind).await?;
+
+        loop {
+            let (socket, _) = listener.accept().await?;
+            let issuer_node = self.clone();
+            let tls = tls.clone();
+
+            tokio::spawn(async move {
+                let socket = match accept(tls.as_ref(), socket).await {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        eprintln!("X TLS handshake failed; err = {}", e);
+                        return;
+                    },
+                };
+                let stats = Arc::clone(&issuer_node.stats);
+                serve_counted_connection(socket, stats, move |request| {
+                    // Taken per request, so a reload applies to connections that are already open
+                    let loaded = issuer_node.loaded();
+                    let jobs = Arc::clone(&issuer_node.jobs);
+                    let registrar_keys = Arc::clone(&issuer_node.registrar_keys);
+                    async move { process_request(request, loaded, &jobs, &registrar_keys).await }
+                }).await
+            });
+        }
+    }
+
+    async fn process_admin_request(&self, request: Request) -> Result<Response, ServiceError> {
+        match request {
+            Request::AdminHealth => {
+                let Loaded { issuer_index, issuers_public_keys, encoded_pp_zk, extractor } = self.loaded();
+                let mut details = BTreeMap::from([
+                    ("mode".to_string(), "single issuer node".to_string()),
+                    ("issuer_index".to_string(), issuer_index.to_string()),
+                    ("issuers".to_string(), issuers_public_keys.len().to_string()),
+                    ("issuer_share_held_by".to_string(), extractor.held_by()),
+                    ("state_path".to_string(), self.state_path.display().to_string()),
+                    ("registrar_keys_from".to_string(), self.registrar_keys.endpoint().to_string()),
+                    ("pp_zk_digest".to_string(), encoded_pp_zk.digest.clone()),
+                ]);
+                details.extend(self.jobs.metrics());
+                Ok(Response::AdminHealth { health: self.stats.health("issuer_node", details) })
+            },
+
+            Request::AdminReload => {
+                let Loaded { issuer_index, extractor, .. } = self.loaded();
+                let (loaded, reloaded) = match extractor {
+                    Extractor::Shares(_) => {
+                        // The node keeps its issuer index, a share of another issuer is refused
+                        let state = load_share(&self.state_path, issuer_index).map_err(ServiceError::storage)?;
+                        (Loaded::new(state).map_err(ServiceError::internal)?, "the issuer share")
+                    },
+                    // The signer keeps the share it started with, only the public setup is fetched again
+                    Extractor::Signer(signer) => {
+                        let published = fetch_published(&signer).await?;
+                        (Loaded::published(issuer_index, published, signer).map_err(ServiceError::internal)?, "the public setup")
+                    },
+                };
+                *self.loaded.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = loaded;
+                println!("✓ Reloaded {} for an admin", reloaded);
+                Ok(Response::AdminReloaded { detail: format!("reloaded {} from {}", reloaded, self.state_path.display()) })
+            },
+
+            other => Err(ServiceError::new(ErrorCode::UnsupportedAction,
+                format!("{} is not an admin action of an issuer node", other.action()))),
+        }
+    }
+}
+
+impl Loaded {
+    fn new(state: NodeState) -> Result<Self, SerializationError> {
+        let encoded_pp_zk = EncodedPpZk::new(&state.pp_zk)?;
+        let issuer_index = state.issuer_index;
+        let (shares, issuers_public_keys) = Shares::from_node_state(state);
+        Ok(Self {
+            issuer_index,
+            issuers_public_keys: Arc::new(issuers_public_keys),
+            encoded_pp_zk: Arc::new(encoded_pp_zk),
+            extractor: Extractor::Shares(Arc::new(shares)),
+        })
+    }
+
+    fn published(issuer_index: u32, published: PublishedSetup, signer: SignerClient) -> Result<Self, SerializationError> {
+        Ok(Self {
+            issuer_index,
+            issuers_public_keys: Arc::new(published.issuers_public_keys),
+            encoded_pp_zk: Arc::new(EncodedPpZk::new(&published.pp_zk)?),
+            extractor: Extractor::Signer(signer),
+        })
+    }
+}
+
+/// load_share, telling the operator about it
+pub fn load_share_at_startup(state_path: &Path, issuer_index: u32) -> Result<NodeState, Box<dyn std::error::Error>> {
+    println!("- Loading the issuer share from {}", state_path.display());
+    let state = load_share(state_path, issuer_index)?;
+    println!("✓ Loaded the share of issuer {}", state.issuer_index);
+    Ok(state)
+}
+
+/// Load the share of the node at state_path, or generate it with the other nodes if there is none
+/// or if regenerate is set. The public setup has to exist already, the DKG only creates the keys.
+pub async fn load_or_run_dkg(state_path: &Path, public_setup_path: &Path, dkg_config: &DkgConfig, dkg_bind: SocketAddr,
+                             regenerate: bool) -> Result<NodeState, Box<dyn std::error::Error>> {
+    let issuer_index = dkg_config.issuer_index as u32;
+    if !regenerate && state_path.exists() {
+        return load_share_at_startup(state_path, issuer_index);
+    }
+
+    let public_setup = state::load_public_setup(public_setup_path)?.ok_or_else(|| {
+        format!("no public setup found at {}, start the nodes with --launch-nodes --dkg to create it", public_setup_path.display())
+    })?;
+    println!("- Running the DKG with {} nodes, listening on {}", dkg_config.peers.len(), dkg_bind);
+    let output = run_dkg(dkg_config, dkg_bind).await?;
+    println!("✓ Finished the DKG");
+
+    let state = NodeState {
+        issuer_index,
+        pp_zk: public_setup.pp_zk,
+        pp_issuance: output.pp_issuance,
+        secret_key: output.secret_key,
+        issuers_public_keys: output.issuers_public_keys,
+    };
+    state::save_node(state_path, &state)?;
+    println!("✓ Saved the share of issuer {} to {}", issuer_index, state_path.display());
+    Ok(state)
+}
+
+/// Load the share at state_path, which has to be the one of issuer_index
+fn load_share(state_path: &Path, issuer_index: u32) -> Result<NodeState, Box<dyn std::error::Error>> {
+    let state = state::load_node(state_path)?.ok_or_else(|| {
+        format!("no issuer share found at {}, start the nodes with --launch-nodes to deal the shares", state_path.display())
+    })?;
+    if state.issuer_index != issuer_index {
+        return Err(format!("{} holds the share of issuer {}, not of issuer {}",
+                           state_path.display(), state.issuer_index, issuer_index).into());
+    }
+    Ok(state)
+}
+
+
+async fn process_request(request: Request, Loaded { issuer_index, issuers_public_keys, encoded_pp_zk, extractor }: Loaded, jobs: &JobQueue,
+                         registrar_keys: &RegistrarKeys) -> Result<Response, ServiceError> {
+    match request {
+        Request::GetPpZk { compression } => encoded_pp_zk.response(compression),
+
+        Request::GetPpZkDigest => Ok(encoded_pp_zk.digest_response()),
+
+        Request::GetPpZkChunk { offset, length, compression } => encoded_pp_zk.chunk_response(offset, length, compression),
+
+        // Every node knows the public keys of all issuers, so clients can check the answers of the others
+        Request::GetIssuerPublicKeys => {
+            Ok(Response::IssuerPublicKeys { issuer_public_keys: serialize_issuer_public_keys(&issuers_public_keys)? })
+        },
+
+        Request::VerifyIdAndBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } => {
+            let job = extractor.job(registrar_keys, registrar_key_id, blind_id, blind_reg_attestation).await?;
+            jobs.run(move || blind_partial_extract(issuer_index, job)).await
+        },
+
+        Request::SubmitBlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } => {
+            let job = extractor.job(registrar_keys, registrar_key_id, blind_id, blind_reg_attestation).await?;
+            jobs.submit(move || blind_partial_extract(issuer_index, job))
+        },
+
+        Request::GetJobStatus { job_id } => jobs.status(&job_id),
+
+        other => Err(ServiceError::unsupported_action(other.action())),
+    }
+}
+
+// Run on a worker of the job queue. The job uses the share of this node only, with VerifyID and
+// BlindPartialExtract run here or by the signer.
+fn blind_partial_extract(issuer_index: u32, job: ExtractJob) -> Result<Response, ServiceError> {
+    let [blind_partial_user_key]: [Blob; 1] = job()?.try_into().map_err(|keys: Vec<Blob>| {
+        ServiceError::new(ErrorCode::Internal, format!("expected the key of a single share, got {} keys", keys.len()))
+    })?;
+    Ok(Response::BlindPartialUserKey { issuer_index, blind_partial_user_key })
+}
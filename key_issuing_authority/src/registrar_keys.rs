@@ -0,0 +1,96 @@
+// ---------------------------------------
+// File: registrar_keys.rs
+// Date: 18 Oct 2026
+// Description: The registrar keys attestations are verified under
+// ---------------------------------------
+
+// The key-issuing authority asks the registration authority for its key history with
+// get_registrar_key_history, over TLS with the pin of the registration authority, and keeps the
+// answer. It asks again for a key_id it does not know yet, e.g. right after a rotation, and once
+// the answer is older than MAX_AGE, so a retired key stops verifying within that time.
+
+use std::time::{Duration, Instant};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::io::Cursor;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{valid_registrar_key, RegistrarKeyInfo, Request, Response};
+use arke_common::tls::Pins;
+
+use ark_ec::bls12::Bls12;
+use arke_core::RegistrarPublicKey;
+use ark_bls12_377::Parameters;
+
+// Longest a key history is used before it is fetched again
+const MAX_AGE: Duration = Duration::from_secs(60);
+// An unknown key_id only fetches the history again once it is this old, so requests naming made-up
+// keys cannot flood the registration authority
+const MIN_REFETCH_AGE: Duration = Duration::from_secs(2);
+// Longest wait for the registration authority
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Fetched {
+    history: Vec<RegistrarKeyInfo>,
+    at: Instant,
+}
+
+/// The key history of the registration authority at endpoint
+pub struct RegistrarKeys {
+    endpoint: String,
+    pins: Pins,
+    // Held while fetching, so requests arriving meanwhile wait for the same answer
+    fetched: Mutex<Option<Fetched>>,
+}
+
+impl RegistrarKeys {
+    pub fn new(endpoint: String, pins: Pins) -> Self {
+        Self { endpoint, pins, fetched: Mutex::new(None) }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The history, fetched again if it is too old or does not know key_id
+    async fn history(&self, key_id: u32) -> Result<Vec<RegistrarKeyInfo>, ServiceError> {
+        let mut fetched = self.fetched.lock().await;
+        let usable = fetched.as_ref().is_some_and(|fetched| {
+            let age = fetched.at.elapsed();
+            age < MAX_AGE && (age < MIN_REFETCH_AGE || fetched.history.iter().any(|key| key.key_id == key_id))
+        });
+        if !usable {
+            *fetched = Some(Fetched { history: self.fetch().await?, at: Instant::now() });
+        }
+        Ok(fetched.as_ref().map(|fetched| fetched.history.clone()).unwrap_or_default())
+    }
+
+    async fn fetch(&self) -> Result<Vec<RegistrarKeyInfo>, ServiceError> {
+        let unavailable = |reason: String| ServiceError::new(ErrorCode::Internal,
+            format!("the registrar keys of the registration authority at {} are unavailable: {}", self.endpoint, reason));
+        let fetch = async {
+            let mut connection = Connection::connect_pinned(&self.endpoint, &self.pins).await?;
+            match connection.call(Request::GetRegistrarKeyHistory).await? {
+                Response::RegistrarKeyHistory { keys } => Ok(keys),
+                other => Err(ProtocolError::unexpected("get_registrar_key_history", other)),
+            }
+        };
+        match timeout(FETCH_TIMEOUT, fetch).await {
+            Ok(Ok(keys)) => Ok(keys),
+            Ok(Err(e)) => Err(unavailable(e.to_string())),
+            Err(_) => Err(unavailable(format!("no answer within {} s", FETCH_TIMEOUT.as_secs()))),
+        }
+    }
+
+    /// The public key of key_id, unless the registration authority retired it
+    pub async fn valid_key(&self, key_id: u32) -> Result<RegistrarPublicKey<Bls12<Parameters>>, ServiceError> {
+        let history = self.history(key_id).await?;
+        let key = valid_registrar_key(&history, key_id)?;
+        // CanonicalDeserialize
+        let mut cursor = Cursor::new(&key.registrar_public_key.0);
+        RegistrarPublicKey::<Bls12<Parameters>>::deserialize(&mut cursor)
+            .map_err(|e| ServiceError::new(ErrorCode::Internal,
+                format!("registrar key {} of the registration authority at {} is unreadable: {:?}", key_id, self.endpoint, e)))
+    }
+}
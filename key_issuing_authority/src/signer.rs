@@ -0,0 +1,174 @@
+// ---------------------------------------
+// File: signer.rs
+// Date: 18 Oct 2026
+// Description: Issuer shares held by this process or by a separate signer
+// ---------------------------------------
+
+// With --signer the key-issuing authority (or an issuer node) never loads a secret share. A second
+// process started with --run-signer on a data directory of its own loads the setup, sends its public
+// part to the network process and runs BlindPartialExtract for it over a Unix socket (see
+// arke_common/src/signer.rs).
+// Both check the requests, the network process to refuse bad ones before they take a place in the
+// queue, the signer because it cannot trust the network process to have done so. For that the
+// signer fetches the registrar keys from the registration authority itself, its only connection
+// to the network.
+//
+// The signer never runs the DKG, which talks to the other nodes over TCP. A node that keeps its
+// share in a signer runs the DKG first with --dkg-only, and the signer only reads the share it saved.
+
+use std::path::Path;
+use std::sync::Arc;
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::Blob;
+use arke_common::signer::{serve_signer, SignerClient, SignerRequest, SignerResponse};
+
+use ark_ec::bls12::Bls12;
+use ark_ec::bw6::BW6;
+use arke_core::{ThresholdObliviousIdNIKE, IssuerPublicKey, BLSPublicParameters,
+                BlindIDCircuitParameters, BlindPartialSecretKey};
+use ark_bls12_377::{Bls12_377, Parameters, FrParameters};
+use ark_bw6_761::Parameters as Parameters761;
+use ark_bw6_761::BW6_761;
+use ark_ff::Fp256;
+use secret_sharing::shamir_secret_sharing::SecretShare;
+use crate::config::Config;
+use crate::key_issuing_authority::{self, deserialize_extract_request, serialize_blind_partial_user_key, ExtractRequest, REGISTRAR_DOMAIN};
+use crate::node;
+use crate::registrar_keys::RegistrarKeys;
+use crate::state::{self, AuthorityState, NodeState, PublishedSetup};
+type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
+
+/// What BlindPartialExtract needs, the secret shares and the parameters they are used with
+pub struct Shares {
+    pp_zk: BlindIDCircuitParameters<BW6<Parameters761>>,
+    pp_issuance: BLSPublicParameters<Bls12<Parameters>>,
+    secret_keys: Vec<SecretShare<Fp256<FrParameters>>>,
+}
+
+/// The work of one extraction, run on a worker of the job queue
+pub type ExtractJob = Box<dyn FnOnce() -> Result<Vec<Blob>, ServiceError> + Send>;
+
+/// Who runs BlindPartialExtract
+#[derive(Clone)]
+pub enum Extractor {
+    /// This process, with the shares it loaded
+    Shares(Arc<Shares>),
+    /// The signer listening on a socket
+    Signer(SignerClient),
+}
+
+impl Shares {
+    /// The shares of every issuer, and the public keys that go with them
+    pub fn from_authority_state(state: AuthorityState) -> (Self, Vec<IssuerPublicKey<Bls12<Parameters>>>) {
+        let shares = Self { pp_zk: state.pp_zk, pp_issuance: state.pp_issuance, secret_keys: state.issuers_secret_keys };
+        (shares, state.issuers_public_keys)
+    }
+
+    /// The share of a single node, and the public keys of every issuer
+    pub fn from_node_state(state: NodeState) -> (Self, Vec<IssuerPublicKey<Bls12<Parameters>>>) {
+        let shares = Self { pp_zk: state.pp_zk, pp_issuance: state.pp_issuance, secret_keys: vec![state.secret_key] };
+        (shares, state.issuers_public_keys)
+    }
+
+    /// One blind partial user key per share, in the order of the shares
+    pub fn extract(&self, (registrar_public_key, blind_id, blind_reg_attestation): ExtractRequest) -> Result<Vec<Blob>, ServiceError> {
+        // Run ID-NIKE.VerifyID and ID-NIKE.BlindPartialExtract
+        println!("- Running VerifyID and BlindPartialExtract");
+        // VerifyID fails when the proof of the blind ID or the blind attestation does not verify
+        let blind_partial_user_keys: Vec<BlindPartialSecretKey<Bls12_377>> = self.secret_keys
+            .iter()
+            .map(|secret_key| {
+                ArkeIdNIKE::blind_partial_extract(
+                    &self.pp_issuance,
+                    &self.pp_zk,
+                    &registrar_public_key,
+                    secret_key,
+                    &blind_id,
+                    &blind_reg_attestation,
+                    REGISTRAR_DOMAIN,
+                )
+            }).collect::<Result<_, _>>()
+            .map_err(ServiceError::invalid_proof)?;
+        println!("✓ Finished VerifyID and BlindPartialExtract");
+
+        // Serialize each key on its own so the client gets one blob per issuer
+        blind_partial_user_keys
+            .iter()
+            .map(serialize_blind_partial_user_key)
+            .collect()
+    }
+}
+
+impl Extractor {
+    /// Check an extraction request and return the work it takes. Malformed fields and retired registrar
+    /// keys are refused here, only well-formed requests take a place in the queue.
+    pub async fn job(&self, registrar_keys: &RegistrarKeys, registrar_key_id: u32, blind_id: Blob, blind_reg_attestation: Blob)
+        -> Result<ExtractJob, ServiceError> {
+        let inputs = deserialize_extract_request(registrar_keys, registrar_key_id, &blind_id, &blind_reg_attestation).await?;
+        match self {
+            Extractor::Shares(shares) => {
+                let shares = Arc::clone(shares);
+                Ok(Box::new(move || shares.extract(inputs)))
+            },
+            Extractor::Signer(signer) => {
+                // The workers are no runtime threads, they wait for the signer on the runtime of the server
+                let runtime = tokio::runtime::Handle::current();
+                let signer = signer.clone();
+                Ok(Box::new(move || runtime.block_on(signer.blind_partial_extract(registrar_key_id, blind_id, blind_reg_attestation))))
+            },
+        }
+    }
+
+    /// Where the shares are, for the admin endpoint
+    pub fn held_by(&self) -> String {
+        match self {
+            Extractor::Shares(_) => "this process".to_string(),
+            Extractor::Signer(signer) => format!("the signer at {}", signer.path().display()),
+        }
+    }
+}
+
+/// Load the shares the way the authority or the node of config would, and the public part of the
+/// setup for the network process
+pub fn load(config: &Config) -> Result<(Shares, PublishedSetup), Box<dyn std::error::Error>> {
+    let (shares, issuers_public_keys) = match config.node {
+        Some(issuer_index) => {
+            if config.dkg_config().is_some() && !config.node_state_path().exists() {
+                return Err(format!("no issuer share at {} yet, run the DKG with --dkg-only before starting the signer",
+                                   config.node_state_path().display()).into());
+            }
+            Shares::from_node_state(node::load_share_at_startup(&config.node_state_path(), issuer_index)?)
+        },
+        None => Shares::from_authority_state(key_issuing_authority::setup(&config.state_path(), config.regenerate)?),
+    };
+
+    let published = PublishedSetup { pp_zk: shares.pp_zk.clone(), issuers_public_keys };
+    Ok((shares, published))
+}
+
+/// The public setup of the signer, which has to be running
+pub async fn fetch_published(signer: &SignerClient) -> Result<PublishedSetup, ServiceError> {
+    let public_setup = signer.public_setup().await?;
+    state::decode_published(&public_setup.0).map_err(|e| ServiceError::new(ErrorCode::Internal, e.to_string()))
+}
+
+/// Run BlindPartialExtract with shares for the network process and send it the published setup,
+/// answering it on the Unix socket at path until the listener fails
+pub async fn serve(path: &Path, shares: Shares, published: PublishedSetup, registrar_keys: RegistrarKeys) -> std::io::Result<()> {
+    let shares = Arc::new(shares);
+    // Encoded once, pp_zk is large
+    let public_setup = state::encode_published(&published)
+        .map(|public_setup| Arc::new(Blob(public_setup)))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    let registrar_keys = Arc::new(registrar_keys);
+    serve_signer(path, move |request| match request {
+        SignerRequest::BlindPartialExtract { registrar_key_id, blind_id, blind_reg_attestation } => {
+            // The handler runs on a blocking thread, it waits for the registration authority on the runtime of the signer
+            let inputs = tokio::runtime::Handle::current()
+                .block_on(deserialize_extract_request(&registrar_keys, registrar_key_id, &blind_id, &blind_reg_attestation))?;
+            Ok(SignerResponse::BlindPartialUserKeys { blind_partial_user_keys: shares.extract(inputs)? })
+        },
+        SignerRequest::PublicKeys => Ok(SignerResponse::PublicSetup { public_setup: (*public_setup).clone() }),
+        other => Err(ServiceError::unsupported_action(other.action())),
+    }).await
+}
@@ -0,0 +1,155 @@
+// ---------------------------------------
+// File: state.rs
+// Date: 18 Oct 2026
+// Description: Persistent trusted setup and DKG output of the key-issuing authority
+//              and of the separate issuer nodes
+// ---------------------------------------
+
+use std::path::Path;
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use ark_std::io::Cursor;
+use arke_common::durable_file::corrupt;
+use arke_common::state_file::{read_state_file, write_state_file};
+use crate::dkg::DkgIdentity;
+
+use ark_ec::bls12::Bls12;
+use ark_ec::bw6::BW6;
+use arke_core::{IssuerPublicKey, BLSPublicParameters, BlindIDCircuitParameters};
+use ark_bls12_377::{Parameters, FrParameters};
+use ark_bw6_761::Parameters as Parameters761;
+use ark_ff::Fp256;
+use secret_sharing::shamir_secret_sharing::SecretShare;
+
+const STATE_MAGIC: &[u8; 8] = b"ARKE-KIA";
+/// Bump whenever the fields of AuthorityState change
+const STATE_VERSION: u32 = 1;
+const NODE_STATE_MAGIC: &[u8; 8] = b"ARKE-KIN";
+/// Bump whenever the fields of NodeState change
+const NODE_STATE_VERSION: u32 = 1;
+const PUBLIC_SETUP_MAGIC: &[u8; 8] = b"ARKE-KIP";
+/// Bump whenever the fields of PublicSetup change
+const PUBLIC_SETUP_VERSION: u32 = 1;
+const DKG_IDENTITY_MAGIC: &[u8; 8] = b"ARKE-KID";
+/// Bump whenever the encoding of DkgIdentity changes
+const DKG_IDENTITY_VERSION: u32 = 1;
+
+/// Everything the setup produces. Users' keys only keep working while this stays the same.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct AuthorityState {
+    pub pp_zk: BlindIDCircuitParameters<BW6<Parameters761>>,
+    pub pp_issuance: BLSPublicParameters<Bls12<Parameters>>,
+    pub issuers_secret_keys: Vec<SecretShare<Fp256<FrParameters>>>,
+    pub issuers_public_keys: Vec<IssuerPublicKey<Bls12<Parameters>>>,
+}
+
+/// The public part of the setup, all the nodes need before they run the DKG
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct PublicSetup {
+    pub pp_zk: BlindIDCircuitParameters<BW6<Parameters761>>,
+    pub pp_issuance: BLSPublicParameters<Bls12<Parameters>>,
+}
+
+/// What a single issuer node needs: the public parameters and its own share only
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct NodeState {
+    pub issuer_index: u32,
+    pub pp_zk: BlindIDCircuitParameters<BW6<Parameters761>>,
+    pub pp_issuance: BLSPublicParameters<Bls12<Parameters>>,
+    pub secret_key: SecretShare<Fp256<FrParameters>>,
+    pub issuers_public_keys: Vec<IssuerPublicKey<Bls12<Parameters>>>,
+}
+
+/// What a signer sends its network process: everything the clients may see
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct PublishedSetup {
+    pub pp_zk: BlindIDCircuitParameters<BW6<Parameters761>>,
+    pub issuers_public_keys: Vec<IssuerPublicKey<Bls12<Parameters>>>,
+}
+
+impl NodeState {
+    /// Take the share of issuer_index out of the full setup
+    pub fn from_authority_state(state: &AuthorityState, issuer_index: usize) -> Self {
+        NodeState {
+            issuer_index: issuer_index as u32,
+            pp_zk: state.pp_zk.clone(),
+            pp_issuance: state.pp_issuance.clone(),
+            secret_key: state.issuers_secret_keys[issuer_index].clone(),
+            issuers_public_keys: state.issuers_public_keys.clone(),
+        }
+    }
+}
+
+/// Load the state saved by an earlier start, or None if there is none yet
+pub fn load(path: &Path) -> Result<Option<AuthorityState>, Box<dyn std::error::Error>> {
+    load_file(path, STATE_MAGIC, STATE_VERSION)
+}
+
+pub fn save(path: &Path, state: &AuthorityState) -> Result<(), Box<dyn std::error::Error>> {
+    save_file(path, STATE_MAGIC, STATE_VERSION, state)
+}
+
+/// Load the share of an issuer node, or None if none was dealt to it yet
+pub fn load_node(path: &Path) -> Result<Option<NodeState>, Box<dyn std::error::Error>> {
+    load_file(path, NODE_STATE_MAGIC, NODE_STATE_VERSION)
+}
+
+pub fn save_node(path: &Path, state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    save_file(path, NODE_STATE_MAGIC, NODE_STATE_VERSION, state)
+}
+
+pub fn load_public_setup(path: &Path) -> Result<Option<PublicSetup>, Box<dyn std::error::Error>> {
+    load_file(path, PUBLIC_SETUP_MAGIC, PUBLIC_SETUP_VERSION)
+}
+
+pub fn save_public_setup(path: &Path, setup: &PublicSetup) -> Result<(), Box<dyn std::error::Error>> {
+    save_file(path, PUBLIC_SETUP_MAGIC, PUBLIC_SETUP_VERSION, setup)
+}
+
+/// The public setup as a signer sends it. Both processes run the same build, so it carries no version.
+pub fn encode_published(setup: &PublishedSetup) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut body = Vec::new();
+    setup.serialize(&mut body).map_err(|e| format!("could not serialize the public setup: {:?}", e))?;
+    Ok(body)
+}
+
+pub fn decode_published(body: &[u8]) -> Result<PublishedSetup, Box<dyn std::error::Error>> {
+    let setup = PublishedSetup::deserialize(&mut Cursor::new(body))
+        .map_err(|e| format!("invalid public setup from the signer: {:?}", e))?;
+    Ok(setup)
+}
+
+/// Load the DKG identity key of a node, or None if it has none yet
+pub fn load_dkg_identity(path: &Path) -> Result<Option<DkgIdentity>, Box<dyn std::error::Error>> {
+    let body = match read_state_file(path, DKG_IDENTITY_MAGIC, DKG_IDENTITY_VERSION)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+    let bytes: [u8; DkgIdentity::LEN] = body.as_slice().try_into()
+        .map_err(|_| corrupt(path, format!("expected {} bytes", DkgIdentity::LEN)))?;
+    Ok(Some(DkgIdentity::from_bytes(&bytes)))
+}
+
+pub fn save_dkg_identity(path: &Path, identity: &DkgIdentity) -> Result<(), Box<dyn std::error::Error>> {
+    write_state_file(path, DKG_IDENTITY_MAGIC, DKG_IDENTITY_VERSION, &identity.to_bytes())?;
+    Ok(())
+}
+
+fn load_file<T: CanonicalDeserialize>(path: &Path, magic: &[u8; 8], version: u32) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let body = match read_state_file(path, magic, version)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+    // CanonicalDeserialize
+    let mut cursor = Cursor::new(&body);
+    let state = T::deserialize(&mut cursor)
+        .map_err(|e| corrupt(path, format!("{:?}", e)))?;
+    Ok(Some(state))
+}
+
+fn save_file<T: CanonicalSerialize>(path: &Path, magic: &[u8; 8], version: u32, state: &T) -> Result<(), Box<dyn std::error::Error>> {
+    // CanonicalSerialize
+    let mut body = Vec::new();
+    state.serialize(&mut body).map_err(|e| format!("could not serialize the state: {:?}", e))?;
+    write_state_file(path, magic, version, &body)?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+// ---------------------------------------
+// File: config.rs
+// Date: 18 Oct 2026
+// Description: Runtime configuration of the registration authority
+// ---------------------------------------
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::Parser;
+use serde::Deserialize;
+use arke_common::config::{load_file, parse_socket_addr, prepare_data_dir, refuse_secrets, tls_files, validate_endpoint, ConfigError, TlsFiles};
+use arke_common::tls::{Pin, Pins};
+use crate::verification::CodeSenderKind;
+
+const DEFAULT_BIND: &str = "127.0.0.1:8082";
+// The admin endpoint only listens on localhost unless configured otherwise
+const DEFAULT_ADMIN_BIND: &str = "127.0.0.1:9082";
+const DEFAULT_DATA_DIR: &str = "data";
+const DEFAULT_DATABASE_SERVER: &str = "127.0.0.1:8080";
+// Long enough to wait for an email
+const DEFAULT_CODE_TTL_SECS: u64 = 600;
+const DEFAULT_CODE_ATTEMPTS: u32 = 3;
+
+/// Command line flags, each of them can also be set through its environment variable
+#[derive(Parser, Debug)]
+#[command(about = "Arke registration authority")]
+struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ARKE_RA_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, env = "ARKE_RA_BIND")]
+    bind: Option<String>,
+    /// Directory holding the state of the registration authority
+    #[arg(long, env = "ARKE_RA_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Generate new registrar and enrollment keypairs even if saved ones exist, invalidating every attestation
+    #[arg(long)]
+    regenerate: bool,
+    /// Address of the admin endpoint for arke-admin
+    #[arg(long, env = "ARKE_RA_ADMIN_BIND")]
+    admin_bind: Option<String>,
+    /// Do not start the admin endpoint
+    #[arg(long)]
+    no_admin: bool,
+    /// File with the admin key, created if it does not exist. admin.key in the data directory by default
+    #[arg(long, env = "ARKE_RA_ADMIN_KEY")]
+    admin_key: Option<PathBuf>,
+    /// PEM certificate chain to serve clients over TLS with, together with --tls-key
+    #[arg(long, env = "ARKE_RA_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of the TLS certificate
+    #[arg(long, env = "ARKE_RA_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+    /// How the one-time codes verifying an ID are delivered
+    #[arg(long, env = "ARKE_RA_CODE_SENDER", value_enum)]
+    code_sender: Option<CodeSenderKind>,
+    /// File the file sender appends the codes to, outbox.txt in the data directory by default
+    #[arg(long, env = "ARKE_RA_CODE_OUTBOX")]
+    code_outbox: Option<PathBuf>,
+    /// Seconds a one-time code can be answered
+    #[arg(long, env = "ARKE_RA_CODE_TTL")]
+    code_ttl: Option<u64>,
+    /// Wrong answers to a one-time code before it is dropped
+    #[arg(long, env = "ARKE_RA_CODE_ATTEMPTS")]
+    code_attempts: Option<u32>,
+    /// host:port of the database server the registration authority enrolls IDs with
+    #[arg(long, env = "ARKE_RA_DATABASE_SERVER")]
+    database_server: Option<String>,
+    /// Pin of the database server to connect to it over TLS, cert-sha256:<hex> or spki-sha256:<hex>
+    #[arg(long, env = "ARKE_RA_DATABASE_SERVER_PIN")]
+    database_server_pin: Option<String>,
+    /// Connect to the database server over plain TCP when it has no pin, for a database server started without TLS
+    #[arg(long, env = "ARKE_RA_INSECURE_PLAINTEXT")]
+    insecure_plaintext: bool,
+    /// Unix socket of the signer holding the registrar keys and the enrollment key, this process then holds none of them
+    #[arg(long, env = "ARKE_RA_SIGNER")]
+    signer: Option<PathBuf>,
+    /// Run as the signer: hold the registrar keys and the enrollment key and answer on the --signer socket (signer.sock in the
+    /// data directory by default) instead of serving clients
+    #[arg(long)]
+    run_signer: bool,
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<String>,
+    data_dir: Option<PathBuf>,
+    admin_bind: Option<String>,
+    admin_key: Option<PathBuf>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    code_sender: Option<CodeSenderKind>,
+    code_outbox: Option<PathBuf>,
+    code_ttl: Option<u64>,
+    code_attempts: Option<u32>,
+    database_server: Option<String>,
+    database_server_pin: Option<String>,
+    insecure_plaintext: Option<bool>,
+    signer: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub data_dir: PathBuf,
+    pub regenerate: bool,
+    /// None if the admin endpoint is off
+    pub admin_bind: Option<SocketAddr>,
+    admin_key: Option<PathBuf>,
+    /// None if clients connect over plain TCP
+    pub tls: Option<TlsFiles>,
+    pub code_sender: CodeSenderKind,
+    code_outbox: Option<PathBuf>,
+    pub code_ttl: Duration,
+    pub code_attempts: u32,
+    pub database_server: String,
+    /// Holds the pin of the database server if it is reached over TLS, or allows plain TCP
+    pub database_server_pins: Pins,
+    /// None if the registrar keys are held by this process
+    pub signer: Option<PathBuf>,
+    pub run_signer: bool,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file: FileConfig = load_file(cli.config.as_deref())?;
+
+        let bind = cli.bind.or(file.bind).unwrap_or_else(|| DEFAULT_BIND.to_string());
+        let data_dir = cli.data_dir.or(file.data_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+        let admin_bind = cli.admin_bind.or(file.admin_bind).unwrap_or_else(|| DEFAULT_ADMIN_BIND.to_string());
+        let code_ttl = cli.code_ttl.or(file.code_ttl).unwrap_or(DEFAULT_CODE_TTL_SECS);
+        let code_attempts = cli.code_attempts.or(file.code_attempts).unwrap_or(DEFAULT_CODE_ATTEMPTS);
+
+        let database_server = validate_endpoint("database_server",
+            &cli.database_server.or(file.database_server).unwrap_or_else(|| DEFAULT_DATABASE_SERVER.to_string()))?;
+        let mut database_server_pins = BTreeMap::new();
+        if let Some(pin) = cli.database_server_pin.or(file.database_server_pin) {
+            let parsed = Pin::parse(&pin)
+                .ok_or_else(|| ConfigError::invalid("database_server_pin", pin.clone(), "expected cert-sha256:<hex> or spki-sha256:<hex>"))?;
+            database_server_pins.insert(database_server.clone(), parsed);
+        }
+        let mut database_server_pins = Pins::new(database_server_pins);
+        if cli.insecure_plaintext || file.insecure_plaintext.unwrap_or(false) {
+            database_server_pins = database_server_pins.allow_plaintext();
+        }
+
+        if code_ttl == 0 {
+            return Err(ConfigError::invalid("code_ttl", "0", "must be at least 1 second"));
+        }
+        if code_attempts == 0 {
+            return Err(ConfigError::invalid("code_attempts", "0", "must be at least 1"));
+        }
+
+        let config = Config {
+            bind: parse_socket_addr("bind", &bind)?,
+            data_dir: prepare_data_dir("data_dir", data_dir)?,
+            regenerate: cli.regenerate,
+            admin_bind: if cli.no_admin { None } else { Some(parse_socket_addr("admin_bind", &admin_bind)?) },
+            admin_key: cli.admin_key.or(file.admin_key),
+            tls: tls_files(cli.tls_cert.or(file.tls_cert), cli.tls_key.or(file.tls_key))?,
+            code_sender: cli.code_sender.or(file.code_sender).unwrap_or(CodeSenderKind::Stdout),
+            code_outbox: cli.code_outbox.or(file.code_outbox),
+            code_ttl: Duration::from_secs(code_ttl),
+            code_attempts,
+            database_server,
+            database_server_pins,
+            signer: cli.signer.or(file.signer),
+            run_signer: cli.run_signer,
+        };
+        // The network process of a signer must not be able to read the keys either
+        if config.signer.is_some() && !config.run_signer {
+            refuse_secrets("data_dir", &config.data_dir, &[config.state_path(), config.enrollment_key_path()])?;
+        }
+        Ok(config)
+    }
+
+    pub fn state_path(&self) -> PathBuf {
+        self.data_dir.join("registration_authority.state")
+    }
+
+    /// Secret key signing the enrollment tokens
+    pub fn enrollment_key_path(&self) -> PathBuf {
+        self.data_dir.join("enrollment_key.state")
+    }
+
+    /// Public key of the enrollment tokens, for the database server
+    pub fn enrollment_public_key_path(&self) -> PathBuf {
+        self.data_dir.join("enrollment_public_key.txt")
+    }
+
+    /// Socket a process started with --run-signer listens on
+    pub fn signer_path(&self) -> PathBuf {
+        self.signer.clone().unwrap_or_else(|| self.data_dir.join("signer.sock"))
+    }
+
+    /// IDs and the contacts they are bound to
+    pub fn contacts_path(&self) -> PathBuf {
+        self.data_dir.join("contacts.json")
+    }
+
+    /// Where the file sender writes the one-time codes
+    pub fn code_outbox_path(&self) -> PathBuf {
+        self.code_outbox.clone().unwrap_or_else(|| self.data_dir.join("outbox.txt"))
+    }
+
+    /// File with the key arke-admin logs in with
+    pub fn admin_key_path(&self) -> PathBuf {
+        self.admin_key.clone().unwrap_or_else(|| self.data_dir.join("admin.key"))
+    }
+}
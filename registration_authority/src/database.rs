@@ -0,0 +1,110 @@
+// ---------------------------------------
+// File: database.rs
+// Date: 18 Oct 2026
+// Description: Reserve and enroll IDs with the database server (registration authority-side)
+// ---------------------------------------
+
+// The database server only lets a connection reserve, enroll or release IDs once it signed a nonce
+// with the enrollment key, so clients can no longer skip the uniqueness check or enroll IDs that
+// were never registered. The signatures come from an EnrollmentSigner, which may be the signer process.
+
+use arke_common::client::{Connection, ProtocolError};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{AccountStatus, Request, Response};
+use arke_common::tls::Pins;
+use crate::signer::EnrollmentSigner;
+
+/// A connection to the database server, logged in as the registration authority
+pub struct DatabaseSession {
+    connection: Connection,
+}
+
+// Whatever went wrong between the two servers is not the fault of the client, and its code is
+// not passed on, an unauthorized login is no reason to ask the user to log in
+fn unavailable(e: ProtocolError) -> ServiceError {
+    ServiceError::new(ErrorCode::Internal, format!("the database server could not be used: {}", e))
+}
+
+impl DatabaseSession {
+    pub async fn open(endpoint: &str, pins: &Pins, enrollment: &EnrollmentSigner) -> Result<Self, ServiceError> {
+        let mut connection = Connection::connect_pinned(endpoint, pins).await.map_err(unavailable)?;
+        let nonce = match connection.call(Request::RegistrarChallenge).await.map_err(unavailable)? {
+            Response::RegistrarChallenge { nonce } => nonce,
+            other => return Err(unavailable(ProtocolError::unexpected("registrar_challenge", other))),
+        };
+        let signature = enrollment.registrar_login(&nonce).await?;
+        match connection.call(Request::RegistrarLogin { signature }).await.map_err(unavailable)? {
+            Response::RegistrarLoggedIn => Ok(Self { connection }),
+            other => Err(unavailable(ProtocolError::unexpected("registrar_login", other))),
+        }
+    }
+
+    /// Whether nobody has the ID or holds a lease on it
+    pub async fn is_available(&mut self, id_string: &str) -> Result<bool, ServiceError> {
+        match self.connection.call(Request::CheckUniqueness { id_string: id_string.to_string() }).await.map_err(unavailable)? {
+            Response::IdAvailable => Ok(true),
+            Response::IdTaken => Ok(false),
+            other => Err(unavailable(ProtocolError::unexpected("check_uniqueness", other))),
+        }
+    }
+
+    /// The status of the account with the ID, None if there is none
+    pub async fn status(&mut self, id_string: &str) -> Result<Option<AccountStatus>, ServiceError> {
+        match self.connection.call(Request::GetUserStatus { id_string: id_string.to_string() }).await.map_err(unavailable)? {
+            Response::UserStatus { status } => Ok(Some(status)),
+            Response::UserNotFound => Ok(None),
+            other => Err(unavailable(ProtocolError::unexpected("get_user_status", other))),
+        }
+    }
+
+    /// A lease on the ID, None if it is taken or reserved
+    pub async fn reserve(&mut self, id_string: &str) -> Result<Option<String>, ServiceError> {
+        match self.connection.call(Request::ReserveId { id_string: id_string.to_string() }).await.map_err(unavailable)? {
+            Response::IdReserved { lease_token, .. } => Ok(Some(lease_token)),
+            Response::IdTaken => Ok(None),
+            other => Err(unavailable(ProtocolError::unexpected("reserve_id", other))),
+        }
+    }
+
+    /// Add the user holding the lease, false if the lease expired and somebody else took the ID
+    pub async fn commit(&mut self, id_string: &str, lease_token: &str, enrollment: &EnrollmentSigner) -> Result<bool, ServiceError> {
+        let (enrollment_token, issued_at) = enrollment.enrollment_token(id_string, Some(lease_token)).await?;
+        let request = Request::CommitId {
+            id_string: id_string.to_string(),
+            lease_token: lease_token.to_string(),
+            enrollment_token,
+            issued_at,
+        };
+        match self.connection.call(request).await.map_err(unavailable)? {
+            Response::UserAdded => Ok(true),
+            Response::IdTaken => Ok(false),
+            other => Err(unavailable(ProtocolError::unexpected("commit_id", other))),
+        }
+    }
+
+    /// Give up a lease, so the ID is free again right away
+    pub async fn release(&mut self, id_string: &str, lease_token: &str) -> Result<(), ServiceError> {
+        let request = Request::ReleaseId { id_string: id_string.to_string(), lease_token: lease_token.to_string() };
+        match self.connection.call(request).await.map_err(unavailable)? {
+            Response::IdReleased => Ok(()),
+            other => Err(unavailable(ProtocolError::unexpected("release_id", other))),
+        }
+    }
+}
+
+/// Where the database server is, a session is opened for every request that needs it
+#[derive(Clone)]
+pub struct DatabaseServer {
+    endpoint: String,
+    pins: Pins,
+}
+
+impl DatabaseServer {
+    pub fn new(endpoint: String, pins: Pins) -> Self {
+        Self { endpoint, pins }
+    }
+
+    pub async fn session(&self, enrollment: &EnrollmentSigner) -> Result<DatabaseSession, ServiceError> {
+        DatabaseSession::open(&self.endpoint, &self.pins, enrollment).await
+    }
+}
@@ -0,0 +1,138 @@
+// ---------------------------------------
+// File: keyring.rs
+// Date: 18 Oct 2026
+// Description: Versioned registrar keys of the registration authority
+// ---------------------------------------
+
+// Attestations are made with the newest key that is not retired, and carry its key_id. A rotation
+// adds a new key without retiring the previous one, so attestations made before keep verifying
+// until an admin retires their key. The public half of every key is served by
+// get_registrar_key_history to the key-issuing authority, which refuses attestations of retired keys,
+// and by the signer to its network process when a signer holds the keys.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use rand::thread_rng;
+use arke_common::error::ServiceError;
+use arke_common::messages::{Blob, RegistrarKeyInfo};
+
+use ark_ec::bls12::Bls12;
+use arke_core::{ThresholdObliviousIdNIKE, RegistrarPublicKey, UserID};
+use ark_bls12_377::{Bls12_377, Parameters, FrParameters};
+use ark_bw6_761::BW6_761;
+use ark_ff::Fp256;
+type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
+const REGISTRAR_DOMAIN: &'static [u8] = b"registration";
+
+/// One output of ID-NIKE.Setup and when it was in use
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct RegistrarKey {
+    pub key_id: u32,
+    pub registrar_secret_key: Fp256<FrParameters>,
+    pub registrar_public_key: RegistrarPublicKey<Bls12<Parameters>>,
+    /// Seconds since the Unix epoch
+    pub valid_from: u64,
+    pub superseded_at: Option<u64>,
+    pub retired_at: Option<u64>,
+}
+
+/// Every registrar key so far, oldest first. The last one is never retired.
+#[derive(CanonicalSerialize)]
+pub struct Keyring {
+    keys: Vec<RegistrarKey>,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Run ID-NIKE.Setup for a new key
+fn setup(key_id: u32) -> RegistrarKey {
+    println!("- Running Setup");
+    let (_pp_registration, registrar_secret_key, registrar_public_key) =
+        ArkeIdNIKE::setup_registration(&mut thread_rng());
+    println!("✓ Finished Setup");
+    RegistrarKey { key_id, registrar_secret_key, registrar_public_key, valid_from: now_secs(), superseded_at: None, retired_at: None }
+}
+
+impl Keyring {
+    /// A keyring with a single new key
+    pub fn generate() -> Self {
+        Self { keys: vec![setup(1)] }
+    }
+
+    /// The keys of a keyring read from disk, which has to end with a key that is not retired
+    pub fn from_keys(keys: Vec<RegistrarKey>) -> Result<Self, String> {
+        match keys.last() {
+            Some(key) if key.retired_at.is_none() => Ok(Self { keys }),
+            Some(key) => Err(format!("the newest registrar key {} is retired", key.key_id)),
+            None => Err("there is no registrar key".to_string()),
+        }
+    }
+
+    /// The key attestations are made with
+    pub fn current(&self) -> &RegistrarKey {
+        self.keys.last().expect("a keyring always has a key")
+    }
+
+    pub fn count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Make a new key the current one. Returns its key_id.
+    pub fn rotate(&mut self) -> u32 {
+        let key = setup(self.current().key_id + 1);
+        let key_id = key.key_id;
+        if let Some(current) = self.keys.last_mut() {
+            current.superseded_at = Some(key.valid_from);
+        }
+        self.keys.push(key);
+        key_id
+    }
+
+    /// Stop key_id from verifying, rotating first if it is the current key. Returns false if it
+    /// already was retired.
+    pub fn retire(&mut self, key_id: u32) -> Result<bool, ServiceError> {
+        if !self.keys.iter().any(|key| key.key_id == key_id) {
+            return Err(ServiceError::malformed_field("key_id", format!("there is no registrar key {}", key_id)));
+        }
+        if self.current().key_id == key_id {
+            self.rotate();
+        }
+        let key = self.keys.iter_mut().find(|key| key.key_id == key_id).expect("the key was just found");
+        if key.retired_at.is_some() {
+            return Ok(false);
+        }
+        key.retired_at = Some(now_secs());
+        Ok(true)
+    }
+
+    /// The attestation of id_string under the current key, and the key_id of that key
+    pub fn register(&self, id_string: &str) -> Result<(Blob, u32), ServiceError> {
+        let registrar_key = self.current();
+        let id = UserID::new(id_string);
+        // Run ID-NIKE.Register
+        println!("- Running Register");
+        let reg_attestation = ArkeIdNIKE::register(&registrar_key.registrar_secret_key, &id, REGISTRAR_DOMAIN)
+            .map_err(ServiceError::internal)?;
+        println!("✓ Finished Register");
+        let mut reg_attestation_bytes = Vec::new();
+        reg_attestation.serialize(&mut reg_attestation_bytes).map_err(ServiceError::internal)?;
+        Ok((Blob(reg_attestation_bytes), registrar_key.key_id))
+    }
+
+    /// The public half of every key
+    pub fn history(&self) -> Result<Vec<RegistrarKeyInfo>, ServiceError> {
+        self.keys.iter().map(|key| {
+            let mut registrar_public_key = Vec::new();
+            key.registrar_public_key.serialize(&mut registrar_public_key).map_err(ServiceError::internal)?;
+            Ok(RegistrarKeyInfo {
+                key_id: key.key_id,
+                registrar_public_key: Blob(registrar_public_key),
+                valid_from: key.valid_from,
+                superseded_at: key.superseded_at,
+                retired_at: key.retired_at,
+            })
+        }).collect()
+    }
+}
@@ -1,12 +1,85 @@
 use tokio::runtime::Runtime;
+use arke_common::admin::AdminKey;
+use arke_common::tls::load_acceptor;
+mod config;
+use config::Config;
+mod database;
+mod keyring;
 mod registration_authority;
+mod signer;
+mod state;
+mod verification;
 use registration_authority::registrationAuthority; 
 
 fn main() {
-    println!("Registration authority running...");
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("X {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // The signer serves no clients and no admin, only the registration authority on its socket. It holds
+    // the registrar keys and the enrollment key, and writes the enrollment public key as the authority would
+    if config.run_signer {
+        let signer_path = config.signer_path();
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let keys = registration_authority::registrar_keys(&config.state_path(), config.regenerate).and_then(|keyring| {
+                let enrollment_key = registration_authority::enrollment_key(&config.enrollment_key_path(),
+                    &config.enrollment_public_key_path(), config.regenerate)?;
+                Ok((keyring, enrollment_key))
+            });
+            let (keyring, enrollment_key) = match keys {
+                Ok(keys) => keys,
+                Err(e) => {
+                    eprintln!("X {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Registrar signer running on {}...", signer_path.display());
+            if let Err(e) = signer::serve(&signer_path, keyring, enrollment_key).await {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        });
+        return;
+    }
+
+    // Created on first start, arke-admin needs a copy of it
+    let admin = match config.admin_bind {
+        Some(admin_bind) => match AdminKey::load_or_create(&config.admin_key_path()) {
+            Ok(admin_key) => Some((admin_bind, admin_key)),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let tls = match &config.tls {
+        Some(files) => match load_acceptor(files) {
+            Ok(acceptor) => Some(acceptor),
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    println!("Registration authority running on {}{}...", config.bind, if tls.is_some() { " over TLS" } else { "" });
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
-        let registration_authority = registrationAuthority::new().await;
-        registration_authority.start().await.unwrap();
+        let registration_authority = match registrationAuthority::new(&config).await {
+            Ok(registration_authority) => registration_authority,
+            Err(e) => {
+                eprintln!("X {}", e);
+                std::process::exit(1);
+            }
+        };
+        registration_authority.start(config.bind, admin, tls).await.unwrap();
     });
 }
\ No newline at end of file
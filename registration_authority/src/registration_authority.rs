@@ -5,115 +5,404 @@
 //              Sign up new user (registration authority-side)
 // ---------------------------------------
 #![allow(non_camel_case_types)]
-#![allow(private_in_public)]
 #![allow(unused_variables)]
 
 use tokio::net::TcpListener;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use serde_json::Value;
-use serde_json::json;
-use std::sync::Arc;
-
-use ark_ec::bls12::Bls12;
-use arke_core::{UserID, ThresholdObliviousIdNIKE, RegistrarPublicKey};
-use ark_serialize::CanonicalSerialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use arke_common::admin::{serve_admin, serve_counted_connection, AdminKey, RequestStats};
+use arke_common::durable_file::write_atomic;
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{validate_id_string, Request, Response, Verification};
+use arke_common::signer::SignerClient;
+use arke_common::tls::{accept, TlsAcceptor};
+
 use rand::thread_rng;
-use ark_bls12_377::{Bls12_377, Parameters};
-use ark_bw6_761::BW6_761;
-use ark_bls12_377::FrParameters;
-use ark_ff::Fp256;
-type ArkeIdNIKE = ThresholdObliviousIdNIKE<Bls12_377, BW6_761>;
-const REGISTRAR_DOMAIN: &'static [u8] = b"registration";
+use crate::config::Config;
+use crate::database::{DatabaseServer, DatabaseSession};
+use crate::keyring::Keyring;
+use crate::signer::{EnrollmentSigner, Registrar};
+use crate::state;
+use crate::verification::{code_sender, Contact, ContactBook, OneTimeCodeVerifier, Verifier};
+use arke_common::messages::AccountStatus;
+use ed25519_dalek::SigningKey;
+
+/// Every key the registration authority signs with, replaced as a whole by admin_reload and by
+/// changes to the registrar keys
+struct Keys {
+    registrar: Registrar,
+    enrollment: EnrollmentSigner,
+}
 
 #[derive(Clone)]
 pub struct registrationAuthority {
-    registrar_secret_key: Arc<Fp256<FrParameters>>,
-    registrar_public_key: Arc<RegistrarPublicKey<Bls12<Parameters>>>,
+    keys: Arc<RwLock<Arc<Keys>>>,
+    verifier: Arc<dyn Verifier>,
+    contacts: Arc<ContactBook>,
+    database: DatabaseServer,
+    stats: Arc<RequestStats>,
+    // Where admin_reload reads the keys from
+    data_dir: PathBuf,
+    state_path: PathBuf,
+    enrollment_key_path: PathBuf,
+}
+
+impl registrationAuthority {
+    /// Load the keypairs saved in the data directory, or generate new ones if there are none or if regenerate is set.
+    /// With a signer none of them is loaded, the signer holds them.
+    pub async fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let (registrar, enrollment) = match &config.signer {
+            Some(signer) => {
+                println!("✓ The registrar keys and the enrollment key are held by the signer at {}", signer.display());
+                let signer = SignerClient::new(signer.clone());
+                (Registrar::Signer(signer.clone()), EnrollmentSigner::Signer(signer))
+            },
+            None => {
+                let enrollment_key = enrollment_key(&config.enrollment_key_path(), &config.enrollment_public_key_path(), config.regenerate)?;
+                (Registrar::Keyring(registrar_keys(&config.state_path(), config.regenerate)?), EnrollmentSigner::Key(enrollment_key))
+            },
+        };
+        let contacts = ContactBook::load(&config.contacts_path())?;
+        println!("✓ Loaded the contacts of {} IDs, codes go out through {:?}", contacts.count(), config.code_sender);
+        let sender = code_sender(config.code_sender, &config.code_outbox_path());
+        Ok(Self {
+            keys: Arc::new(RwLock::new(Arc::new(Keys { registrar, enrollment }))),
+            verifier: Arc::new(OneTimeCodeVerifier::new(sender, config.code_ttl, config.code_attempts)),
+            contacts: Arc::new(contacts),
+            database: DatabaseServer::new(config.database_server.clone(), config.database_server_pins.clone()),
+            stats: Arc::new(RequestStats::new()),
+            data_dir: config.data_dir.clone(),
+            state_path: config.state_path(),
+            enrollment_key_path: config.enrollment_key_path(),
+        })
+    }
+
+    fn keys(&self) -> Arc<Keys> {
+        // Only ever replaced as a whole, a panic cannot leave it half-written
+        Arc::clone(&self.keys.read().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+/// Load the registrar keys saved at state_path, or run a new setup if there are none or if regenerate is set
+pub fn registrar_keys(state_path: &Path, regenerate: bool) -> Result<Keyring, Box<dyn std::error::Error>> {
+    if regenerate {
+        println!("- Regenerating the registrar keys, attestations issued so far will stop verifying");
+    } else {
+        println!("- Loading the registrar keys from {}", state_path.display());
+        if let Some(keyring) = state::load(state_path)? {
+            println!("✓ Loaded {} registrar keys, attestations are made with key {}", keyring.count(), keyring.current().key_id);
+            // Saved again in case it was read from a state file of an older version
+            state::save(state_path, &keyring)?;
+            return Ok(keyring);
+        }
+        println!("- No saved registrar keys found");
+    }
+
+    let keyring = Keyring::generate();
+    state::save(state_path, &keyring)?;
+    println!("✓ Saved the registrar keys to {}", state_path.display());
+
+    Ok(keyring)
+}
+
+/// Load the key signing the enrollment tokens, or generate one. Its public key is written
+/// next to it, for the database server.
+pub fn enrollment_key(key_path: &Path, public_key_path: &Path, regenerate: bool) -> Result<SigningKey, Box<dyn std::error::Error>> {
+    let loaded = if regenerate { None } else { state::load_enrollment_key(key_path)? };
+    let enrollment_key = match loaded {
+        Some(enrollment_key) => {
+            println!("✓ Loaded the enrollment key");
+            enrollment_key
+        },
+        None => {
+            println!("- Generating the enrollment key, enrollment tokens issued so far stop verifying");
+            let enrollment_key = SigningKey::generate(&mut thread_rng());
+            state::save_enrollment_key(key_path, &enrollment_key)?;
+            println!("✓ Saved the enrollment key to {}", key_path.display());
+            enrollment_key
+        },
+    };
+    // Rewritten on every start, in case it was deleted
+    let public_key = base64::encode(enrollment_key.verifying_key().as_bytes());
+    // Replaced atomically, the database server may read it at any moment
+    write_atomic(public_key_path, (public_key + "\n").as_bytes())?;
+    println!("✓ Database servers verify enrollment tokens with {}", public_key_path.display());
+    Ok(enrollment_key)
 }
 
 impl registrationAuthority {
-    pub async fn new() -> Self {
-        let mut rng = thread_rng();
-        // Run ID-NIKE.Setup
-        println!("- Running Setup");
-        let (_pp_registration, registrar_secret_key, registrar_public_key) =
-            ArkeIdNIKE::setup_registration(&mut rng);
-        println!("✓ Finished Setup");
-
-        Self { registrar_secret_key: Arc::new(registrar_secret_key),
-               registrar_public_key: Arc::new(registrar_public_key),
+    /// Apply change to the saved registrar keys, then save and use them
+    fn change_keyring<T>(&self, change: impl FnOnce(&mut Keyring) -> Result<T, ServiceError>) -> Result<T, ServiceError> {
+        // Held throughout, so two changes cannot both start from the same saved keys
+        let mut keys = self.keys.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Registrar::Signer(_) = keys.registrar {
+            return Err(ServiceError::new(ErrorCode::UnsupportedAction, format!(
+                "the registrar keys are held by {}, stop it and change them with an authority started without --signer",
+                keys.registrar.held_by())));
         }
+        let mut keyring = state::load(&self.state_path).map_err(ServiceError::storage)?
+            .ok_or_else(|| ServiceError::storage(format!("there is no saved key in {}", self.state_path.display())))?;
+        let changed = change(&mut keyring)?;
+        state::save(&self.state_path, &keyring).map_err(ServiceError::storage)?;
+        let enrollment = keys.enrollment.clone();
+        *keys = Arc::new(Keys { registrar: Registrar::Keyring(keyring), enrollment });
+        Ok(changed)
     }
 
+    /// Serve clients on bind, over TLS if there is an acceptor, and arke-admin on the admin address if there is one
+    pub async fn start(self, bind: SocketAddr, admin: Option<(SocketAddr, AdminKey)>, tls: Option<TlsAcceptor>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((admin_bind, admin_key)) = admin {
+            let registration_authority = self.clone();
+            let stats = Arc::clone(&self.stats);
+            let admin_tls = tls.clone();
+            println!("Admin endpoint running on {}{}...", admin_bind, if tls.is_some() { " over TLS" } else { "" });
+            tokio::spawn(async move {
+                let served = serve_admin(admin_bind, Arc::new(admin_key), stats, admin_tls, move |request| {
+                    let registration_authority = registration_authority.clone();
+                    async move { registration_authority.process_admin_request(request).await }
+                }).await;
+                if let Err(e) = served {
+                    eprintln!("X Admin endpoint stopped: {}", e);
+                }
+            });
+        }
 
-    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
-        let listener = TcpListener::bind("127.0.0.1:8082").await?;
+        let listener = TcpListener::bind(bind).await?;
 
         loop {
-            let (mut socket, _) = listener.accept().await?;
-            let registrar_secret_key = Arc::clone(&self.registrar_secret_key);
-            let registrar_public_key = Arc::clone(&self.registrar_public_key);
+            let (socket, _) = listener.accept().await?;
+            let registration_authority = self.clone();
+            let tls = tls.clone();
 
             tokio::spawn(async move {
-                let mut buf = vec![0; 1024];
-
-                loop {
-                    match socket.read(&mut buf).await {
-                        // Connection closed
-                        Ok(n) if n == 0 => return,
-                        // Connection open
-                        Ok(n) => {
-                            let request: Value = serde_json::from_slice(&buf[..n]).unwrap();
-                            // Handle request
-                            let response = process_request(request, &registrar_secret_key, &registrar_public_key).await;
-                            let response_bytes = serde_json::to_vec(&response).unwrap();
-                            if let Err(e) = socket.write_all(&response_bytes).await {
-                                eprintln!("failed to write to socket; err = {:?}", e);
-                                return;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("failed to read from socket; err = {:?}", e);
-                            return;
-                        }
+                let socket = match accept(tls.as_ref(), socket).await {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        eprintln!("X TLS handshake failed; err = {}", e);
+                        return;
+                    },
+                };
+                let stats = Arc::clone(&registration_authority.stats);
+                serve_counted_connection(socket, stats, move |request| {
+                    // Taken per request, so a reload applies to connections that are already open
+                    let keys = registration_authority.keys();
+                    let verifier = Arc::clone(&registration_authority.verifier);
+                    let contacts = Arc::clone(&registration_authority.contacts);
+                    let database = registration_authority.database.clone();
+                    async move {
+                        process_request(request, &keys.registrar, &keys.enrollment, verifier.as_ref(), &contacts, &database).await
                     }
-                }
+                }).await
             });
         }
     }
+
+    async fn process_admin_request(&self, request: Request) -> Result<Response, ServiceError> {
+        match request {
+            Request::AdminHealth => {
+                let keys = self.keys();
+                let enrollment_public_key = keys.enrollment.public_key().await.unwrap_or_else(|e| e.message);
+                // A signer that does not answer is reported rather than failing the whole report
+                let (registrar_keys, current_registrar_key_id) = match keys.registrar.history().await {
+                    Ok(history) => (history.len().to_string(), history.last().map_or_else(String::new, |key| key.key_id.to_string())),
+                    Err(e) => (e.message.clone(), e.message),
+                };
+                let details = BTreeMap::from([
+                    ("data_dir".to_string(), self.data_dir.display().to_string()),
+                    ("enrollment_public_key".to_string(), enrollment_public_key),
+                    ("enrollment_key_held_by".to_string(), keys.enrollment.held_by()),
+                    ("registrar_keys_held_by".to_string(), keys.registrar.held_by()),
+                    ("registrar_keys".to_string(), registrar_keys),
+                    ("current_registrar_key_id".to_string(), current_registrar_key_id),
+                    ("contacts".to_string(), self.contacts.count().to_string()),
+                    ("pending_challenges".to_string(), self.verifier.pending().to_string()),
+                ]);
+                Ok(Response::AdminHealth { health: self.stats.health("registration_authority", details) })
+            },
+
+            Request::AdminReload => {
+                // Unlike at startup, missing keys are an error here rather than a reason to generate new ones
+                let missing = |path: &Path| ServiceError::storage(format!("there is no saved key in {}", path.display()));
+                let keys = self.keys();
+                let (registrar, reloaded) = match &keys.registrar {
+                    Registrar::Keyring(_) => {
+                        let keyring = state::load(&self.state_path).map_err(ServiceError::storage)?
+                            .ok_or_else(|| missing(&self.state_path))?;
+                        let reloaded = format!("{} registrar keys, ", keyring.count());
+                        (Registrar::Keyring(keyring), reloaded)
+                    },
+                    // The signer keeps the keys it started with
+                    Registrar::Signer(signer) => (Registrar::Signer(signer.clone()), String::new()),
+                };
+                let enrollment = match &keys.enrollment {
+                    EnrollmentSigner::Key(_) => {
+                        let enrollment_key = state::load_enrollment_key(&self.enrollment_key_path).map_err(ServiceError::storage)?
+                            .ok_or_else(|| missing(&self.enrollment_key_path))?;
+                        EnrollmentSigner::Key(enrollment_key)
+                    },
+                    signer @ EnrollmentSigner::Signer(_) => signer.clone(),
+                };
+                let contacts = self.contacts.reload().map_err(ServiceError::storage)?;
+                *self.keys.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(Keys { registrar, enrollment });
+                println!("✓ Keys reloaded by an admin");
+                Ok(Response::AdminReloaded {
+                    detail: format!("reloaded {}the enrollment key and the contacts of {} IDs from {}",
+                                    reloaded, contacts, self.data_dir.display()),
+                })
+            },
+
+            Request::AdminRotateRegistrarKey => {
+                let key_id = self.change_keyring(|keyring| Ok(keyring.rotate()))?;
+                println!("✓ Rotated to registrar key {} for an admin", key_id);
+                Ok(Response::AdminRegistrarKeyRotated { key_id })
+            },
+
+            Request::AdminRetireRegistrarKey { key_id } => {
+                let (changed, current_key_id) = self.change_keyring(|keyring| {
+                    let changed = keyring.retire(key_id)?;
+                    Ok((changed, keyring.current().key_id))
+                })?;
+                if changed {
+                    println!("✓ Retired registrar key {} for an admin, attestations are made with key {}", key_id, current_key_id);
+                }
+                Ok(Response::AdminRegistrarKeyRetired { current_key_id, changed })
+            },
+
+            other => Err(ServiceError::new(ErrorCode::UnsupportedAction,
+                format!("{} is not an admin action of the registration authority", other.action()))),
+        }
+    }
 }
 
-async fn process_request(request: Value,
-                        registrar_secret_key: &Arc<Fp256<FrParameters>>,
-                        registrar_public_key: &Arc<RegistrarPublicKey<Bls12<Parameters>>>,) -> Value {
-    match request["action"].as_str() {
-        Some("to_Register") => {
-            let id_string = request["id_string"].as_str().unwrap().to_string();
-            let id = UserID::new(&id_string);
-            // Run ID-NIKE.Register
-            println!("- Running Register");
-            let reg_attestation = ArkeIdNIKE::register(&registrar_secret_key, &id, REGISTRAR_DOMAIN).unwrap();
-            println!("✓ Finished Register");
-            let mut reg_attestation_bytes = Vec::new();
-            reg_attestation.serialize(&mut reg_attestation_bytes).unwrap();
-            let reg_attestation_str = base64::encode(&reg_attestation_bytes);  
-            json!({ "status": "success", "message": "✓ Registered", 
-                    "reg_attestation": reg_attestation_str,
-                 })
+async fn process_request(request: Request,
+                        registrar: &Registrar,
+                        enrollment: &EnrollmentSigner,
+                        verifier: &dyn Verifier,
+                        contacts: &ContactBook,
+                        database: &DatabaseServer) -> Result<Response, ServiceError> {
+    match request {
+        Request::StartVerification { id_string, contact } => {
+            validate_id_string(&id_string)?;
+            let mut database = database.session(enrollment).await?;
+            // A bound ID only ever gets codes at its own contact, whatever the request names
+            let contact = match (bound_contact(&mut database, contacts, &id_string).await?, contact) {
+                (Some(bound), _) => bound,
+                (None, Some(contact)) => {
+                    let contact = Contact::parse(&contact)?;
+                    // No code goes out for an ID somebody else already has
+                    if !database.is_available(&id_string).await? {
+                        println!("X ID taken");
+                        return Ok(Response::IdTaken);
+                    }
+                    contact
+                },
+                (None, None) => return Err(ServiceError::malformed_field("contact", "the ID is not bound to a contact yet, give one")),
+            };
+            let challenge = verifier.start(&id_string, &contact)?;
+            println!("- Sent a code for an ID to {}", challenge.sent_to.masked());
+
+            Ok(Response::VerificationStarted {
+                challenge_id: challenge.challenge_id,
+                sent_to: challenge.sent_to.masked(),
+                expires_in_secs: challenge.expires_in.as_secs(),
+                attempts: challenge.attempts,
+            })
+        },
+
+        Request::Register { id_string, verification } => {
+            validate_id_string(&id_string)?;
+            let contact = verify(verifier, &id_string, verification.as_ref())?;
+            let mut database = database.session(enrollment).await?;
+            let enrolled = match bound_contact(&mut database, contacts, &id_string).await? {
+                Some(bound) if bound != contact => {
+                    return Err(ServiceError::new(ErrorCode::Unverified, "the ID is bound to another contact"));
+                },
+                // Registering again, e.g. after losing the attestation, only enrolls if that did not happen yet
+                Some(_) => database.status(&id_string).await? == Some(AccountStatus::Active)
+                    || enroll(&mut database, contacts, &id_string, &contact, enrollment).await?,
+                None => enroll(&mut database, contacts, &id_string, &contact, enrollment).await?,
+            };
+            if !enrolled {
+                println!("X ID taken or reserved");
+                return Ok(Response::IdTaken);
+            }
+            let (reg_attestation, key_id) = registrar.register(&id_string).await?;
+
+            Ok(Response::RegAttestation { reg_attestation, key_id })
         },
 
-        Some("get_registrar_public_key") => {
-            let mut registrar_public_key_bytes = Vec::new();
-            registrar_public_key.serialize(&mut registrar_public_key_bytes).unwrap();
-            let registrar_public_key_str = base64::encode(&registrar_public_key_bytes);      
+        Request::GetRegistrarPublicKey => {
+            let registrar_key = registrar.current().await?;
 
-            json!({ "status": "success", "message": "✓ Got registrar_public_key", 
-                    "registrar_public_key": registrar_public_key_str,
-                 })
+            Ok(Response::RegistrarPublicKey { registrar_public_key: registrar_key.registrar_public_key, key_id: registrar_key.key_id })
         },
 
-        _ => {
-            json!({ "status": "error", "message": "invalid action" })
+        Request::GetRegistrarKeyHistory => Ok(Response::RegistrarKeyHistory { keys: registrar.history().await? }),
+
+        Request::AuthorizeDeletion { id_string, verification } => {
+            validate_id_string(&id_string)?;
+            // Authorized on the same terms as Register, whoever may register an ID may also delete its account.
+            // IDs registered before they were bound to a contact cannot prove it, their deletion is up to the operators
+            let contact = verify(verifier, &id_string, verification.as_ref())?;
+            if contacts.get(&id_string) != Some(contact) {
+                return Err(ServiceError::new(ErrorCode::Unverified, "the ID is not bound to this contact"));
+            }
+            println!("- Authorizing the deletion of an account");
+            let (deletion_token, issued_at) = enrollment.deletion_token(&id_string).await?;
+
+            Ok(Response::DeletionToken { deletion_token, issued_at })
         },
+
+        other => Err(ServiceError::unsupported_action(other.action())),
+    }
+}
+
+// Reserve the ID, bind it to contact and add the user. Returns false if the ID is taken or reserved.
+// The binding is kept if adding the user fails, so the same contact can try again but nobody else
+// can take the ID meanwhile
+async fn enroll(database: &mut DatabaseSession, contacts: &ContactBook, id_string: &str, contact: &Contact,
+                enrollment: &EnrollmentSigner) -> Result<bool, ServiceError> {
+    println!("- Enrolling the ID with the database server");
+    let lease_token = match database.reserve(id_string).await? {
+        Some(lease_token) => lease_token,
+        None => return Ok(false),
+    };
+    // Only the holder of the lease gets this far, nobody else can bind the ID meanwhile
+    let bound = contacts.bind(id_string, contact);
+    if !matches!(bound, Ok(true)) {
+        if let Err(e) = database.release(id_string, &lease_token).await {
+            eprintln!("X Could not release the lease, it expires on its own: {}", e.message);
+        }
+        bound?;
+        return Err(ServiceError::new(ErrorCode::Unverified, "the ID is bound to another contact"));
+    }
+    let added = database.commit(id_string, &lease_token, enrollment).await?;
+    if added {
+        println!("✓ User added");
     }
-}
\ No newline at end of file
+    Ok(added)
+}
+
+// The contact the ID is bound to. The account of a deleted ID gives the ID up once the database
+// server's reuse cooldown is over, and its binding goes with it, so that a new contact can take the ID.
+async fn bound_contact(database: &mut DatabaseSession, contacts: &ContactBook, id_string: &str) -> Result<Option<Contact>, ServiceError> {
+    let bound = match contacts.get(id_string) {
+        Some(bound) => bound,
+        None => return Ok(None),
+    };
+    if database.status(id_string).await? == Some(AccountStatus::Deleted) && database.is_available(id_string).await? {
+        println!("- The account of an ID was deleted and its reuse cooldown is over, unbinding its contact");
+        contacts.unbind(id_string, &bound)?;
+        return Ok(None);
+    }
+    Ok(Some(bound))
+}
+
+// The contact the answer to the challenge proves control of
+fn verify(verifier: &dyn Verifier, id_string: &str, verification: Option<&Verification>) -> Result<Contact, ServiceError> {
+    let verification = verification
+        .ok_or_else(|| ServiceError::new(ErrorCode::Unverified, "verify the ID with start_verification first"))?;
+    verifier.check(id_string, verification)
+}
@@ -0,0 +1,164 @@
+// ---------------------------------------
+// File: signer.rs
+// Date: 18 Oct 2026
+// Description: Registrar keys and the enrollment key held by this process or by a separate signer
+// ---------------------------------------
+
+// With --signer the registration authority never loads the registrar keys or the enrollment key.
+// A second process started with --run-signer holds them on a data directory of its own, and makes
+// the attestations, the logins to the database server and the enrollment and deletion tokens for it
+// over a Unix socket (see arke_common/src/signer.rs). The public keys come over the same socket. The
+// signer cannot tell whether an ID was verified, it only keeps the keys out of the process parsing requests.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::{deletion_message, enrollment_message, registrar_login_message, validate_id_string, Blob, RegistrarKeyInfo};
+use arke_common::signer::{serve_signer, SignerClient, SignerRequest, SignerResponse};
+use ed25519_dalek::{Signer, SigningKey};
+use crate::keyring::Keyring;
+
+/// Who makes the attestations
+pub enum Registrar {
+    /// This process, with the keys it loaded
+    Keyring(Keyring),
+    /// The signer listening on a socket, which is also asked for the public keys
+    Signer(SignerClient),
+}
+
+impl Registrar {
+    /// The attestation of id_string under the current key, and the key_id of that key
+    pub async fn register(&self, id_string: &str) -> Result<(Blob, u32), ServiceError> {
+        match self {
+            Registrar::Keyring(keyring) => keyring.register(id_string),
+            Registrar::Signer(signer) => signer.register(id_string).await,
+        }
+    }
+
+    /// The public half of every key, oldest first
+    pub async fn history(&self) -> Result<Vec<RegistrarKeyInfo>, ServiceError> {
+        match self {
+            Registrar::Keyring(keyring) => keyring.history(),
+            // Asked on every use, the signer may have been restarted with other keys
+            Registrar::Signer(signer) => Ok(signer.registrar_public_keys().await?.0),
+        }
+    }
+
+    /// The public half of the key attestations are made with
+    pub async fn current(&self) -> Result<RegistrarKeyInfo, ServiceError> {
+        self.history().await?.pop().ok_or_else(|| ServiceError::new(ErrorCode::Internal, "there is no registrar key"))
+    }
+
+    /// Where the keys are, for the admin endpoint
+    pub fn held_by(&self) -> String {
+        match self {
+            Registrar::Keyring(_) => "this process".to_string(),
+            Registrar::Signer(signer) => format!("the signer at {}", signer.path().display()),
+        }
+    }
+}
+
+/// Who signs with the enrollment key: the logins to the database server and the enrollment and deletion tokens
+#[derive(Clone)]
+pub enum EnrollmentSigner {
+    /// This process, with the key it loaded
+    Key(SigningKey),
+    /// The signer listening on a socket, which is also asked for the public key
+    Signer(SignerClient),
+}
+
+impl EnrollmentSigner {
+    /// The signature of the login to the database server with the nonce it sent
+    pub async fn registrar_login(&self, nonce: &Blob) -> Result<Blob, ServiceError> {
+        match self {
+            EnrollmentSigner::Key(key) => Ok(sign(key, &registrar_login_message(&nonce.0))),
+            EnrollmentSigner::Signer(signer) => signer.registrar_login(nonce).await,
+        }
+    }
+
+    /// The token that lets id_string enroll with the database server under the lease on it, and the time it was issued at
+    pub async fn enrollment_token(&self, id_string: &str, lease_token: Option<&str>) -> Result<(Blob, u64), ServiceError> {
+        match self {
+            EnrollmentSigner::Key(key) => enrollment_token(key, id_string, lease_token),
+            EnrollmentSigner::Signer(signer) => signer.enrollment_token(id_string, lease_token).await,
+        }
+    }
+
+    /// The token that lets the owner of id_string delete its account, and the time it was issued at
+    pub async fn deletion_token(&self, id_string: &str) -> Result<(Blob, u64), ServiceError> {
+        match self {
+            EnrollmentSigner::Key(key) => deletion_token(key, id_string),
+            EnrollmentSigner::Signer(signer) => signer.deletion_token(id_string).await,
+        }
+    }
+
+    /// The public key database servers verify with, in base64
+    pub async fn public_key(&self) -> Result<String, ServiceError> {
+        match self {
+            EnrollmentSigner::Key(key) => Ok(base64::encode(key.verifying_key().as_bytes())),
+            EnrollmentSigner::Signer(signer) => {
+                let (_, public_key) = signer.registrar_public_keys().await?;
+                Ok(base64::encode(public_key.0))
+            },
+        }
+    }
+
+    /// Where the key is, for the admin endpoint
+    pub fn held_by(&self) -> String {
+        match self {
+            EnrollmentSigner::Key(_) => "this process".to_string(),
+            EnrollmentSigner::Signer(signer) => format!("the signer at {}", signer.path().display()),
+        }
+    }
+}
+
+fn sign(key: &SigningKey, message: &[u8]) -> Blob {
+    Blob(key.sign(message).to_bytes().to_vec())
+}
+
+fn now_secs() -> Result<u64, ServiceError> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH).map_err(ServiceError::internal)?.as_secs())
+}
+
+fn enrollment_token(key: &SigningKey, id_string: &str, lease_token: Option<&str>) -> Result<(Blob, u64), ServiceError> {
+    let issued_at = now_secs()?;
+    Ok((sign(key, &enrollment_message(id_string, lease_token, issued_at)), issued_at))
+}
+
+fn deletion_token(key: &SigningKey, id_string: &str) -> Result<(Blob, u64), ServiceError> {
+    let issued_at = now_secs()?;
+    Ok((sign(key, &deletion_message(id_string, issued_at)), issued_at))
+}
+
+/// Make the attestations of the registration authority with keyring and sign with enrollment_key for it,
+/// answering it on the Unix socket at path until the listener fails
+pub async fn serve(path: &Path, keyring: Keyring, enrollment_key: SigningKey) -> std::io::Result<()> {
+    let keyring = Arc::new(keyring);
+    let enrollment_key = Arc::new(enrollment_key);
+    serve_signer(path, move |request| match request {
+        SignerRequest::Register { id_string } => {
+            validate_id_string(&id_string)?;
+            let (reg_attestation, key_id) = keyring.register(&id_string)?;
+            Ok(SignerResponse::RegAttestation { reg_attestation, key_id })
+        },
+        SignerRequest::RegistrarLogin { nonce } => {
+            Ok(SignerResponse::Signature { signature: sign(&enrollment_key, &registrar_login_message(&nonce.0)) })
+        },
+        SignerRequest::EnrollmentToken { id_string, lease_token } => {
+            validate_id_string(&id_string)?;
+            let (enrollment_token, issued_at) = enrollment_token(&enrollment_key, &id_string, lease_token.as_deref())?;
+            Ok(SignerResponse::EnrollmentToken { enrollment_token, issued_at })
+        },
+        SignerRequest::DeletionToken { id_string } => {
+            validate_id_string(&id_string)?;
+            let (deletion_token, issued_at) = deletion_token(&enrollment_key, &id_string)?;
+            Ok(SignerResponse::DeletionToken { deletion_token, issued_at })
+        },
+        SignerRequest::PublicKeys => {
+            let enrollment_public_key = Blob(enrollment_key.verifying_key().to_bytes().to_vec());
+            Ok(SignerResponse::RegistrarPublicKeys { registrar_keys: keyring.history()?, enrollment_public_key })
+        },
+        other => Err(ServiceError::unsupported_action(other.action())),
+    }).await
+}
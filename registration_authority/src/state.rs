@@ -0,0 +1,92 @@
+// ---------------------------------------
+// File: state.rs
+// Date: 18 Oct 2026
+// Description: Persistent registrar keys of the registration authority
+// ---------------------------------------
+
+use std::path::Path;
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use ark_std::io::Cursor;
+use arke_common::durable_file::corrupt;
+use arke_common::state_file::{read_state_file, write_state_file, StateFileError};
+use crate::keyring::{now_secs, Keyring, RegistrarKey};
+
+use ark_ec::bls12::Bls12;
+use arke_core::RegistrarPublicKey;
+use ark_bls12_377::{Parameters, FrParameters};
+use ark_ff::Fp256;
+use ed25519_dalek::{SigningKey, SECRET_KEY_LENGTH};
+
+const STATE_MAGIC: &[u8; 8] = b"ARKE-RA\0";
+/// Bump whenever the fields of Keyring change
+const STATE_VERSION: u32 = 2;
+/// Held a single registrar keypair, which is read as key 1 of the keyring
+const SINGLE_KEY_STATE_VERSION: u32 = 1;
+const ENROLLMENT_KEY_MAGIC: &[u8; 8] = b"ARKE-RAE";
+/// Bump whenever the encoding of the enrollment key changes
+const ENROLLMENT_KEY_VERSION: u32 = 1;
+
+/// The state of version 1, the output of a single ID-NIKE.Setup
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct SingleKeyState {
+    registrar_secret_key: Fp256<FrParameters>,
+    registrar_public_key: RegistrarPublicKey<Bls12<Parameters>>,
+}
+
+/// Load the keyring saved by an earlier start, or None if there is none yet
+pub fn load(path: &Path) -> Result<Option<Keyring>, Box<dyn std::error::Error>> {
+    let body = match read_state_file(path, STATE_MAGIC, STATE_VERSION) {
+        Ok(Some(body)) => body,
+        Ok(None) => return Ok(None),
+        Err(StateFileError::UnsupportedVersion { found: SINGLE_KEY_STATE_VERSION, .. }) => return load_single_key(path).map(Some),
+        Err(e) => return Err(e.into()),
+    };
+    // CanonicalDeserialize
+    let mut cursor = Cursor::new(&body);
+    let keys = Vec::<RegistrarKey>::deserialize(&mut cursor)
+        .map_err(|e| corrupt(path, format!("{:?}", e)))?;
+    Ok(Some(Keyring::from_keys(keys).map_err(|reason| corrupt(path, reason))?))
+}
+
+// The keypair of a state file written before keys had versions becomes key 1, valid from now on
+fn load_single_key(path: &Path) -> Result<Keyring, Box<dyn std::error::Error>> {
+    let body = read_state_file(path, STATE_MAGIC, SINGLE_KEY_STATE_VERSION)?
+        .ok_or_else(|| corrupt(path, "removed while it was read"))?;
+    // CanonicalDeserialize
+    let mut cursor = Cursor::new(&body);
+    let state = SingleKeyState::deserialize(&mut cursor)
+        .map_err(|e| corrupt(path, format!("{:?}", e)))?;
+    let key = RegistrarKey {
+        key_id: 1,
+        registrar_secret_key: state.registrar_secret_key,
+        registrar_public_key: state.registrar_public_key,
+        valid_from: now_secs(),
+        superseded_at: None,
+        retired_at: None,
+    };
+    Ok(Keyring::from_keys(vec![key])?)
+}
+
+pub fn save(path: &Path, state: &Keyring) -> Result<(), Box<dyn std::error::Error>> {
+    // CanonicalSerialize
+    let mut body = Vec::new();
+    state.serialize(&mut body).map_err(|e| format!("could not serialize the state: {:?}", e))?;
+    write_state_file(path, STATE_MAGIC, STATE_VERSION, &body)?;
+    Ok(())
+}
+
+/// Load the key signing the enrollment tokens, or None if there is none yet
+pub fn load_enrollment_key(path: &Path) -> Result<Option<SigningKey>, Box<dyn std::error::Error>> {
+    let body = match read_state_file(path, ENROLLMENT_KEY_MAGIC, ENROLLMENT_KEY_VERSION)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+    let secret_key: [u8; SECRET_KEY_LENGTH] = body.as_slice().try_into()
+        .map_err(|_| corrupt(path, format!("expected {} bytes", SECRET_KEY_LENGTH)))?;
+    Ok(Some(SigningKey::from_bytes(&secret_key)))
+}
+
+pub fn save_enrollment_key(path: &Path, key: &SigningKey) -> Result<(), Box<dyn std::error::Error>> {
+    write_state_file(path, ENROLLMENT_KEY_MAGIC, ENROLLMENT_KEY_VERSION, &key.to_bytes())?;
+    Ok(())
+}
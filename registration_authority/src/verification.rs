@@ -0,0 +1,575 @@
+// ---------------------------------------
+// File: verification.rs
+// Date: 18 Oct 2026
+// Description: Check that whoever asks for an attestation controls the contact the ID is bound to
+// ---------------------------------------
+
+// Every ID is bound to a contact, an email address or a phone number, the first time it is
+// registered. From then on to_Register and authorize_deletion only succeed for whoever answers a
+// challenge sent to that contact, so nobody can register or delete the ID of somebody else.
+// The one-time code verifier below is the only Verifier for now; how its codes reach the contact is
+// up to a CodeSender, and the file and stdout senders stand in for email and SMS.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use clap::ValueEnum;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use arke_common::durable_file::{read_json_list, write_json_list, DurableFileError};
+use arke_common::error::{ErrorCode, ServiceError};
+use arke_common::messages::Verification;
+
+// Number of digits of a one-time code
+const CODE_DIGITS: u32 = 6;
+// Challenges waiting for an answer, further ones are refused as busy until some expire
+const MAX_PENDING_CHALLENGES: usize = 10_000;
+// Challenges one ID or one contact may have waiting at once, so nobody can flood a contact with codes
+const MAX_PENDING_PER_ID: usize = 3;
+const MAX_PENDING_PER_CONTACT: usize = 3;
+// Wrong codes for one ID or one contact within FAILURE_WINDOW, across challenges, before it cools down
+const MAX_FAILED_ATTEMPTS: u32 = 10;
+const FAILURE_WINDOW: Duration = Duration::from_secs(60 * 60);
+// While an ID or a contact cools down, no code is sent to or accepted for it
+const COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+/// Where an ID receives its challenges
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "address", rename_all = "snake_case")]
+pub enum Contact {
+    Email(String),
+    /// In international format, + and digits only
+    Phone(String),
+}
+
+impl Contact {
+    /// An email address (anything with a local part and a domain around an @) or a phone number
+    /// (+ followed by 8 to 15 digits, spaces and dashes are dropped)
+    pub fn parse(contact: &str) -> Result<Self, ServiceError> {
+        let contact = contact.trim();
+        if let Some((local, domain)) = contact.split_once('@') {
+            if local.is_empty() || !domain.contains('.') || domain.starts_with('.') || domain.ends_with('.')
+                || contact.chars().any(char::is_whitespace) || domain.contains('@') {
+                return Err(ServiceError::malformed_field("contact", "not an email address"));
+            }
+            return Ok(Contact::Email(contact.to_ascii_lowercase()));
+        }
+        let digits: String = contact.chars().filter(|c| *c != ' ' && *c != '-').collect();
+        match digits.strip_prefix('+') {
+            Some(number) if (8..=15).contains(&number.len()) && number.bytes().all(|byte| byte.is_ascii_digit()) => {
+                Ok(Contact::Phone(digits))
+            },
+            _ => Err(ServiceError::malformed_field("contact", "expected an email address or a phone number starting with +")),
+        }
+    }
+
+    /// The contact with most of it hidden, safe to show to whoever asked for a code
+    pub fn masked(&self) -> String {
+        match self {
+            Contact::Email(address) => {
+                let (local, domain) = address.split_once('@').unwrap_or((address, ""));
+                let shown: String = local.chars().take(1).collect();
+                format!("{}***@{}", shown, domain)
+            },
+            Contact::Phone(number) => {
+                let last: String = number.chars().skip(number.len().saturating_sub(2)).collect();
+                format!("+***{}", last)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Contact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Contact::Email(address) => f.write_str(address),
+            Contact::Phone(number) => f.write_str(number),
+        }
+    }
+}
+
+/// A challenge that was sent, as the client learns about it
+pub struct Challenge {
+    pub challenge_id: String,
+    pub sent_to: Contact,
+    pub expires_in: Duration,
+    pub attempts: u32,
+}
+
+/// Challenge/response proof that the one asking for an ID controls a contact
+pub trait Verifier: Send + Sync {
+    /// Send a new challenge for id_string to contact
+    fn start(&self, id_string: &str, contact: &Contact) -> Result<Challenge, ServiceError>;
+    /// Check the answer to a challenge started for id_string, and return the contact it was sent to.
+    /// A challenge answered correctly cannot be used again.
+    fn check(&self, id_string: &str, answer: &Verification) -> Result<Contact, ServiceError>;
+    /// Challenges waiting for an answer, for the admin endpoint
+    fn pending(&self) -> usize;
+}
+
+/// Delivers one-time codes to contacts
+pub trait CodeSender: Send + Sync {
+    fn send(&self, contact: &Contact, id_string: &str, code: &str) -> Result<(), String>;
+}
+
+/// How the one-time codes are delivered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeSenderKind {
+    /// Printed by the registration authority, for local testing
+    Stdout,
+    /// Appended to the outbox file, one line per code, for tests and for a separate mailer to pick up
+    File,
+}
+
+/// The sender of kind, file senders writing to outbox
+pub fn code_sender(kind: CodeSenderKind, outbox: &Path) -> Box<dyn CodeSender> {
+    match kind {
+        CodeSenderKind::Stdout => Box::new(StdoutSender),
+        CodeSenderKind::File => Box::new(FileSender { outbox: outbox.to_path_buf() }),
+    }
+}
+
+struct StdoutSender;
+
+impl CodeSender for StdoutSender {
+    fn send(&self, contact: &Contact, id_string: &str, code: &str) -> Result<(), String> {
+        println!("✉ To {}: the code for {} is {}", contact, id_string, code);
+        Ok(())
+    }
+}
+
+struct FileSender {
+    outbox: PathBuf,
+}
+
+impl CodeSender for FileSender {
+    // One line per code: seconds since the Unix epoch, contact, ID and code, separated by spaces
+    fn send(&self, contact: &Contact, id_string: &str, code: &str) -> Result<(), String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_secs();
+        let mut outbox = std::fs::OpenOptions::new().create(true).append(true).open(&self.outbox)
+            .map_err(|e| format!("could not open {}: {}", self.outbox.display(), e))?;
+        writeln!(outbox, "{} {} {} {}", now, contact, id_string, code)
+            .map_err(|e| format!("could not write to {}: {}", self.outbox.display(), e))
+    }
+}
+
+struct PendingCode {
+    id_string: String,
+    contact: Contact,
+    code: String,
+    expires_at: Instant,
+    attempts_left: u32,
+}
+
+/// What the limits are kept for
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Subject {
+    Id(String),
+    Contact(Contact),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Subject::Id(_) => f.write_str("ID"),
+            Subject::Contact(_) => f.write_str("contact"),
+        }
+    }
+}
+
+/// Wrong codes given for a subject since window_start
+struct Failures {
+    count: u32,
+    window_start: Instant,
+    cooldown_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Challenges {
+    pending: HashMap<String, PendingCode>,
+    failures: HashMap<Subject, Failures>,
+}
+
+impl Challenges {
+    /// How much longer subject cools down, None if it does not
+    fn cooldown(&self, subject: &Subject, now: Instant) -> Option<Duration> {
+        let until = self.failures.get(subject)?.cooldown_until?;
+        until.checked_duration_since(now)
+    }
+
+    /// Count a wrong code against subject. Once there are too many, subject cools down and its
+    /// pending challenges are dropped.
+    fn fail(&mut self, subject: Subject, now: Instant) {
+        let failures = self.failures.entry(subject.clone())
+            .or_insert(Failures { count: 0, window_start: now, cooldown_until: None });
+        failures.count += 1;
+        if failures.count < MAX_FAILED_ATTEMPTS {
+            return;
+        }
+        failures.cooldown_until = Some(now + COOLDOWN);
+        self.pending.retain(|_, challenge| match &subject {
+            Subject::Id(id_string) => challenge.id_string != *id_string,
+            Subject::Contact(contact) => challenge.contact != *contact,
+        });
+    }
+}
+
+fn cooling_down(subject: &Subject, left: Duration) -> ServiceError {
+    ServiceError::new(ErrorCode::Unverified,
+        format!("too many wrong codes for this {}, try again in {} s", subject, left.as_secs().max(1)))
+}
+
+/// Sends a random numeric code and accepts it back a limited number of times before it expires.
+/// Each ID and each contact may only have a few challenges waiting, and too many wrong codes for
+/// either, across challenges, make it cool down for a while. Kept in memory only, a restart drops
+/// the challenges that were not answered and the wrong codes counted so far.
+pub struct OneTimeCodeVerifier {
+    sender: Box<dyn CodeSender>,
+    ttl: Duration,
+    attempts: u32,
+    challenges: Mutex<Challenges>,
+}
+
+impl OneTimeCodeVerifier {
+    pub fn new(sender: Box<dyn CodeSender>, ttl: Duration, attempts: u32) -> Self {
+        Self { sender, ttl, attempts, challenges: Mutex::new(Challenges::default()) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Challenges> {
+        // A panic cannot leave a half-written entry in the maps
+        let mut challenges = self.challenges.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        challenges.pending.retain(|_, challenge| challenge.expires_at > now);
+        challenges.failures.retain(|_, failures| {
+            failures.cooldown_until.map_or(now < failures.window_start + FAILURE_WINDOW, |until| now < until)
+        });
+        challenges
+    }
+}
+
+impl Verifier for OneTimeCodeVerifier {
+    fn start(&self, id_string: &str, contact: &Contact) -> Result<Challenge, ServiceError> {
+        let code = format!("{:0width$}", rand::thread_rng().gen_range(0..10u32.pow(CODE_DIGITS)), width = CODE_DIGITS as usize);
+        let challenge_id = format!("{:032x}", rand::random::<u128>());
+        {
+            let mut challenges = self.lock();
+            let now = Instant::now();
+            for subject in [Subject::Id(id_string.to_string()), Subject::Contact(contact.clone())] {
+                if let Some(left) = challenges.cooldown(&subject, now) {
+                    return Err(cooling_down(&subject, left));
+                }
+            }
+            if challenges.pending.len() >= MAX_PENDING_CHALLENGES {
+                return Err(ServiceError::new(ErrorCode::Busy, "too many challenges waiting for an answer, try again later"));
+            }
+            if challenges.pending.values().filter(|challenge| challenge.id_string == id_string).count() >= MAX_PENDING_PER_ID {
+                return Err(ServiceError::new(ErrorCode::Busy, "too many codes for this ID are waiting for an answer, use one of them or wait until they expire"));
+            }
+            if challenges.pending.values().filter(|challenge| challenge.contact == *contact).count() >= MAX_PENDING_PER_CONTACT {
+                return Err(ServiceError::new(ErrorCode::Busy, "too many codes for this contact are waiting for an answer, use one of them or wait until they expire"));
+            }
+            // Stored under the lock the limits were checked under, so starts at the same moment cannot all pass them.
+            // Nobody knows the challenge ID before it is returned, the code cannot be answered before it was sent.
+            challenges.pending.insert(challenge_id.clone(), PendingCode {
+                id_string: id_string.to_string(),
+                contact: contact.clone(),
+                code: code.clone(),
+                expires_at: now + self.ttl,
+                attempts_left: self.attempts,
+            });
+        }
+        // Sent without the lock, a slow sender does not hold up the other challenges
+        if let Err(reason) = self.sender.send(contact, id_string, &code) {
+            self.lock().pending.remove(&challenge_id);
+            return Err(ServiceError::new(ErrorCode::Internal, format!("could not send the code: {}", reason)));
+        }
+        Ok(Challenge { challenge_id, sent_to: contact.clone(), expires_in: self.ttl, attempts: self.attempts })
+    }
+
+    fn check(&self, id_string: &str, answer: &Verification) -> Result<Contact, ServiceError> {
+        let mut challenges = self.lock();
+        let now = Instant::now();
+        let id = Subject::Id(id_string.to_string());
+        if let Some(left) = challenges.cooldown(&id, now) {
+            return Err(cooling_down(&id, left));
+        }
+        let challenge = match challenges.pending.get_mut(&answer.challenge_id) {
+            Some(challenge) if challenge.id_string == id_string => challenge,
+            _ => return Err(ServiceError::new(ErrorCode::Unverified, "unknown or expired challenge, ask for a new code")),
+        };
+        if challenge.code == answer.code.trim() {
+            let challenge = challenges.pending.remove(&answer.challenge_id).expect("the challenge was just found");
+            challenges.failures.remove(&id);
+            challenges.failures.remove(&Subject::Contact(challenge.contact.clone()));
+            return Ok(challenge.contact);
+        }
+        challenge.attempts_left -= 1;
+        let attempts_left = challenge.attempts_left;
+        let contact = Subject::Contact(challenge.contact.clone());
+        if attempts_left == 0 {
+            challenges.pending.remove(&answer.challenge_id);
+        }
+        challenges.fail(id.clone(), now);
+        challenges.fail(contact.clone(), now);
+        for subject in [id, contact] {
+            if let Some(left) = challenges.cooldown(&subject, now) {
+                return Err(cooling_down(&subject, left));
+            }
+        }
+        if attempts_left == 0 {
+            return Err(ServiceError::new(ErrorCode::Unverified, "wrong code, and no attempts are left, ask for a new code"));
+        }
+        Err(ServiceError::new(ErrorCode::Unverified, format!("wrong code, attempts left: {}", attempts_left)))
+    }
+
+    fn pending(&self) -> usize {
+        self.lock().pending.len()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BoundContact {
+    id_string: String,
+    contact: Contact,
+}
+
+/// The contact every registered ID is bound to, saved in contacts.json in the data directory
+pub struct ContactBook {
+    path: PathBuf,
+    contacts: Mutex<BTreeMap<String, Contact>>,
+}
+
+impl ContactBook {
+    pub fn load(path: &Path) -> Result<Self, DurableFileError> {
+        Ok(Self { path: path.to_path_buf(), contacts: Mutex::new(Self::read(path)?) })
+    }
+
+    fn read(path: &Path) -> Result<BTreeMap<String, Contact>, DurableFileError> {
+        let bound: Vec<BoundContact> = read_json_list(path)?;
+        Ok(bound.into_iter().map(|bound| (bound.id_string, bound.contact)).collect())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Contact>> {
+        self.contacts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, id_string: &str) -> Option<Contact> {
+        self.lock().get(id_string).cloned()
+    }
+
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Bind the ID to contact unless it is bound already. Returns whether the ID is bound to contact now.
+    pub fn bind(&self, id_string: &str, contact: &Contact) -> Result<bool, ServiceError> {
+        let mut contacts = self.lock();
+        if let Some(bound) = contacts.get(id_string) {
+            return Ok(bound == contact);
+        }
+        contacts.insert(id_string.to_string(), contact.clone());
+        if let Err(e) = self.save(&contacts) {
+            contacts.remove(id_string);
+            return Err(ServiceError::storage(e));
+        }
+        Ok(true)
+    }
+
+    /// Drop the binding of the ID if it is still bound to contact, so that another contact can bind it
+    pub fn unbind(&self, id_string: &str, contact: &Contact) -> Result<(), ServiceError> {
+        let mut contacts = self.lock();
+        if contacts.get(id_string) != Some(contact) {
+            return Ok(());
+        }
+        contacts.remove(id_string);
+        if let Err(e) = self.save(&contacts) {
+            contacts.insert(id_string.to_string(), contact.clone());
+            return Err(ServiceError::storage(e));
+        }
+        Ok(())
+    }
+
+    fn save(&self, contacts: &BTreeMap<String, Contact>) -> Result<(), DurableFileError> {
+        let bound: Vec<BoundContact> = contacts.iter()
+            .map(|(id_string, contact)| BoundContact { id_string: id_string.clone(), contact: contact.clone() })
+            .collect();
+        write_json_list(&self.path, &bound)
+    }
+
+    /// Read the file again, e.g. after a backup was restored
+    pub fn reload(&self) -> Result<usize, DurableFileError> {
+        let contacts = Self::read(&self.path)?;
+        let count = contacts.len();
+        *self.lock() = contacts;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    /// Keeps every code it is asked to send, so the tests can answer them
+    #[derive(Clone, Default)]
+    struct RecordingSender {
+        sent: Arc<Mutex<Vec<(Contact, String, String)>>>,
+        fail: bool,
+    }
+
+    impl RecordingSender {
+        /// The code sent last to contact for id_string
+        fn code(&self, id_string: &str, contact: &Contact) -> String {
+            let sent = self.sent.lock().unwrap();
+            let (_, _, code) = sent.iter().rev().find(|(to, id, _)| to == contact && id == id_string).expect("no code was sent");
+            code.clone()
+        }
+    }
+
+    impl CodeSender for RecordingSender {
+        fn send(&self, contact: &Contact, id_string: &str, code: &str) -> Result<(), String> {
+            if self.fail {
+                return Err("the mailer is down".to_string());
+            }
+            self.sent.lock().unwrap().push((contact.clone(), id_string.to_string(), code.to_string()));
+            Ok(())
+        }
+    }
+
+    fn verifier(sender: &RecordingSender, attempts: u32) -> OneTimeCodeVerifier {
+        OneTimeCodeVerifier::new(Box::new(sender.clone()), Duration::from_secs(600), attempts)
+    }
+
+    fn answer(challenge: &Challenge, code: &str) -> Verification {
+        Verification { challenge_id: challenge.challenge_id.clone(), code: code.to_string() }
+    }
+
+    fn refused(started: Result<Challenge, ServiceError>) -> ServiceError {
+        match started {
+            Ok(challenge) => panic!("a code was sent to {}", challenge.sent_to),
+            Err(e) => e,
+        }
+    }
+
+    fn contact(n: usize) -> Contact {
+        Contact::parse(&format!("user{}@example.com", n)).unwrap()
+    }
+
+    #[test]
+    fn correct_code_is_accepted_once() {
+        let sender = RecordingSender::default();
+        let verifier = verifier(&sender, 3);
+        let challenge = verifier.start("alice123", &contact(0)).unwrap();
+        let code = sender.code("alice123", &contact(0));
+
+        // The code only answers the challenge of the ID it was sent for
+        assert_eq!(verifier.check("bob45678", &answer(&challenge, &code)).unwrap_err().code, ErrorCode::Unverified);
+        assert_eq!(verifier.check("alice123", &answer(&challenge, &code)).unwrap(), contact(0));
+        assert_eq!(verifier.check("alice123", &answer(&challenge, &code)).unwrap_err().code, ErrorCode::Unverified);
+        assert_eq!(verifier.pending(), 0);
+    }
+
+    #[test]
+    fn pending_codes_are_limited_per_id_and_per_contact() {
+        let sender = RecordingSender::default();
+        let verifier = verifier(&sender, 3);
+        for n in 0..MAX_PENDING_PER_ID {
+            verifier.start("alice123", &contact(n)).unwrap();
+        }
+        assert_eq!(refused(verifier.start("alice123", &contact(99))).code, ErrorCode::Busy);
+
+        let first = verifier.start("bob00000", &contact(50)).unwrap();
+        for n in 1..MAX_PENDING_PER_CONTACT {
+            verifier.start(&format!("bob{:05}", n), &contact(50)).unwrap();
+        }
+        assert_eq!(refused(verifier.start("carol123", &contact(50))).code, ErrorCode::Busy);
+        // Answering a code frees its place
+        let code = sender.code("bob00000", &contact(50));
+        verifier.check("bob00000", &answer(&first, &code)).unwrap();
+        verifier.start("carol123", &contact(50)).unwrap();
+    }
+
+    #[test]
+    fn starts_at_the_same_moment_do_not_pass_the_limit_together() {
+        const PARALLEL: usize = 16;
+        let sender = RecordingSender::default();
+        let verifier = Arc::new(verifier(&sender, 3));
+        let barrier = Arc::new(Barrier::new(PARALLEL));
+        let started: Vec<_> = (0..PARALLEL).map(|n| {
+            let (verifier, barrier) = (Arc::clone(&verifier), Arc::clone(&barrier));
+            std::thread::spawn(move || {
+                barrier.wait();
+                verifier.start("alice123", &contact(n)).is_ok()
+            })
+        }).collect();
+        let started = started.into_iter().map(|thread| thread.join().unwrap()).filter(|ok| *ok).count();
+        assert_eq!(started, MAX_PENDING_PER_ID);
+        assert_eq!(sender.sent.lock().unwrap().len(), MAX_PENDING_PER_ID);
+    }
+
+    #[test]
+    fn code_that_was_not_sent_takes_no_place() {
+        let sender = RecordingSender { fail: true, ..RecordingSender::default() };
+        let verifier = verifier(&sender, 3);
+        for _ in 0..=MAX_PENDING_PER_ID {
+            assert_eq!(refused(verifier.start("alice123", &contact(0))).code, ErrorCode::Internal);
+        }
+        assert_eq!(verifier.pending(), 0);
+    }
+
+    #[test]
+    fn wrong_codes_cool_down_the_id_and_the_contact() {
+        let sender = RecordingSender::default();
+        let verifier = verifier(&sender, MAX_FAILED_ATTEMPTS);
+        let challenge = verifier.start("alice123", &contact(0)).unwrap();
+        let code = sender.code("alice123", &contact(0));
+        for _ in 1..MAX_FAILED_ATTEMPTS {
+            let wrong = verifier.check("alice123", &answer(&challenge, "wrong")).unwrap_err();
+            assert!(wrong.message.starts_with("wrong code"), "{}", wrong.message);
+        }
+        let cooling = verifier.check("alice123", &answer(&challenge, "wrong")).unwrap_err();
+        assert!(cooling.message.contains("try again in"), "{}", cooling.message);
+
+        // The challenge is gone, even the right code does not help now
+        assert_eq!(verifier.check("alice123", &answer(&challenge, &code)).unwrap_err().code, ErrorCode::Unverified);
+        assert_eq!(verifier.pending(), 0);
+        // No new code goes to the ID, nor to the contact for another ID
+        assert!(refused(verifier.start("alice123", &contact(1))).message.contains("for this ID"));
+        assert!(refused(verifier.start("bob45678", &contact(0))).message.contains("for this contact"));
+        verifier.start("bob45678", &contact(1)).unwrap();
+        assert_eq!(sender.sent.lock().unwrap().len(), 2);
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("arke_verification_test_{:016x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn unbound_id_of_a_deleted_account_goes_to_a_new_contact() {
+        let dir = temp_dir();
+        let path = dir.join("contacts.json");
+        let old = Contact::parse("old@example.com").unwrap();
+        let new = Contact::parse("+41 79 123 45 67").unwrap();
+        let contacts = ContactBook::load(&path).unwrap();
+
+        assert!(contacts.bind("deleted1", &old).unwrap());
+        assert!(!contacts.bind("deleted1", &new).unwrap());
+        // Only the binding that was found stale is dropped, not one made since
+        contacts.unbind("deleted1", &new).unwrap();
+        assert_eq!(contacts.get("deleted1"), Some(old.clone()));
+
+        // Once the reuse cooldown is over the registration authority drops the binding and the new contact registers
+        contacts.unbind("deleted1", &old).unwrap();
+        assert_eq!(contacts.get("deleted1"), None);
+        assert!(contacts.bind("deleted1", &new).unwrap());
+        assert!(!contacts.bind("deleted1", &old).unwrap());
+        assert_eq!(ContactBook::load(&path).unwrap().get("deleted1"), Some(new));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}